@@ -0,0 +1,153 @@
+use std::path::Path;
+use criterion::{criterion_main, criterion_group, Criterion};
+use trade::Side;
+use trade::tick::TickUnit;
+use trade::order_book::{Book, OrderBook, LimitUpdate};
+use trade::order_book::array::ArrayOrderBook;
+use trade::api::Notification;
+use trade::api::recording::{Records, Format};
+
+/// Environment variable giving the path of a recording, as written by
+/// `trade::api::recording::Recorder`, whose limit updates are replayed by the `recorded`
+/// benchmarks. The format is guessed from the extension: `.jsonl` for JSON lines, binary
+/// otherwise.
+const RECORDING_VAR: &str = "TRADE_BENCH_RECORDING";
+
+/// Generate a stream of updates concentrated around a slowly moving mid, resembling
+/// what an exchange feed sends.
+fn updates(count: usize) -> Vec<LimitUpdate> {
+    let mut state: u64 = 42;
+    let mut mid: TickUnit = 100_000;
+    let mut updates = Vec::with_capacity(count);
+
+    for _ in 0..count {
+        state = state.wrapping_mul(6_364_136_223_846_793_005).wrapping_add(1);
+        let random = state >> 33;
+
+        if random % 64 == 0 {
+            mid = if random % 128 == 0 { mid + 1 } else { mid - 1 };
+        }
+
+        let side = if random % 2 == 0 { Side::Bid } else { Side::Ask };
+        let offset = (random >> 4) % 200;
+        let price = match side {
+            Side::Bid => mid - 1 - offset,
+            Side::Ask => mid + offset,
+        };
+        let size = if (random >> 12) % 4 == 0 { 0 } else { (random >> 14) % 1000 };
+        updates.push(LimitUpdate::new(price, size, side));
+    }
+    updates
+}
+
+fn limit_updates(notifs: impl IntoIterator<Item = Notification>) -> Vec<LimitUpdate> {
+    notifs.into_iter()
+        .filter_map(|notif| match notif {
+            Notification::LimitUpdates(updates) => Some(updates),
+            _ => None,
+        })
+        .flatten()
+        .map(|update| *update)
+        .collect()
+}
+
+/// Return the limit updates of a recorded stream: the recording given by `RECORDING_VAR`
+/// if any, or else the depth updates captured in the binance fixtures, repeated until
+/// there are `count` updates.
+fn recorded_updates(count: usize) -> Vec<LimitUpdate> {
+    if let Ok(path) = std::env::var(RECORDING_VAR) {
+        let format = match Path::new(&path).extension() {
+            Some(extension) if extension == "jsonl" => Format::JsonLines,
+            _ => Format::Binary,
+        };
+        let file = std::fs::File::open(&path).expect("cannot open recording");
+        let records = Records::new(file, format)
+            .map(|record| record.expect("cannot read recording").1);
+        return limit_updates(records);
+    }
+
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/binance/stream/depth_updates.json");
+    let fixture: serde_json::Value = serde_json::from_reader(
+        std::fs::File::open(path).expect("cannot open fixture")
+    ).expect("cannot parse fixture");
+    let notifs: Vec<Notification> = serde_json::from_value(fixture["notifications"].clone())
+        .expect("cannot parse fixture notifications");
+
+    limit_updates(notifs).into_iter().cycle().take(count).collect()
+}
+
+fn replay<B: Book>(mut book: B, updates: &[LimitUpdate]) -> TickUnit {
+    let mut spread = 0;
+    for update in updates {
+        book.update(*update);
+        spread += book.best_ask().saturating_sub(book.best_bid());
+    }
+    spread
+}
+
+fn criterion_benchmark(c: &mut Criterion) {
+    let updates = updates(100_000);
+
+    let stream = updates.clone();
+    c.bench_function(
+        "order_book_replay",
+        move |b| b.iter(|| replay(OrderBook::new(), &stream))
+    );
+
+    let stream = updates.clone();
+    c.bench_function(
+        "array_order_book_replay",
+        move |b| b.iter(|| replay(ArrayOrderBook::new(1024), &stream))
+    );
+
+    let recorded = recorded_updates(100_000);
+    let stream = recorded.clone();
+    c.bench_function(
+        "order_book_replay_recorded",
+        move |b| b.iter(|| replay(OrderBook::new(), &stream))
+    );
+
+    c.bench_function(
+        "array_order_book_replay_recorded",
+        move |b| b.iter(|| replay(ArrayOrderBook::new(1024), &recorded))
+    );
+
+    let mut order_book = OrderBook::new();
+    let mut array = ArrayOrderBook::new(1024);
+    for update in &updates {
+        order_book.update(*update);
+        array.update(*update);
+    }
+
+    c.bench_function(
+        "order_book_best_prices",
+        move |b| b.iter(|| order_book.best_bid() + order_book.best_ask())
+    );
+
+    c.bench_function(
+        "array_order_book_best_prices",
+        move |b| b.iter(|| array.best_bid() + array.best_ask())
+    );
+
+    // Removing the best bid of a sparse book searches the next one far below.
+    let mut sparse = ArrayOrderBook::new(65_536);
+    for update in &[
+        LimitUpdate::new(70_000, 1, Side::Bid),
+        LimitUpdate::new(100_000, 1, Side::Bid),
+        LimitUpdate::new(100_001, 1, Side::Ask),
+    ] {
+        sparse.update(*update);
+    }
+    c.bench_function(
+        "array_order_book_remove_best",
+        move |b| b.iter(|| {
+            sparse.update(LimitUpdate::new(100_000, 0, Side::Bid));
+            let best_bid = sparse.best_bid();
+            sparse.update(LimitUpdate::new(100_000, 1, Side::Bid));
+            best_bid
+        })
+    );
+}
+
+criterion_group!(benches, criterion_benchmark);
+criterion_main!(benches);
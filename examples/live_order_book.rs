@@ -54,10 +54,7 @@ fn send_orders<C: ApiClient>(client: &C, symbol: &str, margin: TickUnit)
 }
 
 fn main() -> Result<(), failure::Error> {
-    let params = trade::api::Params {
-        streaming_endpoint: "wss://ws-feed-public.sandbox.pro.coinbase.com".to_owned(),
-        rest_endpoint: "https://api-public.sandbox.pro.coinbase.com".to_owned(),
-    };
+    let params = trade::api::gdax::params::sandbox();
 
     let key_pair = trade::api::gdax::KeyPair::new(
         "my_api_key".to_owned(),
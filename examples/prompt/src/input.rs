@@ -1,5 +1,5 @@
 use trade::Side;
-use trade::api::{ApiClient, Order, Cancel, TimeInForce, OrderType};
+use trade::api::{Order, Cancel, TimeInForce, OrderType};
 use futures::sync::mpsc::UnboundedSender;
 use std::cell::{RefCell, Cell};
 use cursive::Cursive;
@@ -16,7 +16,7 @@ pub fn push(push: UnboundedSender<PushEvent>) {
     });
 }
 
-pub fn submit_input<C: ApiClient>(siv: &mut Cursive, content: &str) {
+pub fn submit_input(siv: &mut Cursive, content: &str) {
     let args: Vec<_> = content.split(' ').collect();
 
     if args.is_empty() {
@@ -30,7 +30,7 @@ pub fn submit_input<C: ApiClient>(siv: &mut Cursive, content: &str) {
         return;
     }
 
-    if let Err(err) = process_input::<C>(cmd, &args[1..]) {
+    if let Err(err) = process_input(cmd, &args[1..]) {
         PUSH.with(|cell| {
             let msg = format!("{}", err);
             cell.borrow()
@@ -42,7 +42,7 @@ pub fn submit_input<C: ApiClient>(siv: &mut Cursive, content: &str) {
     }
 }
 
-fn process_input<C: ApiClient>(cmd: &str, args: &[&str]) -> Result<(), failure::Error> {
+fn process_input(cmd: &str, args: &[&str]) -> Result<(), failure::Error> {
     match cmd {
         side @ "buy" | side @ "sell" => {
             if args.len() < 3 {
@@ -62,20 +62,17 @@ fn process_input<C: ApiClient>(cmd: &str, args: &[&str]) -> Result<(), failure::
                 other => bail!("expected time in force, got `{}`", other),
             };
 
-            let mut order = Order::new(args[1], args[0], side)
+            let order = Order::new(args[1], args[0], side)
                 .with_order_type(OrderType::LimitMaker)
                 .with_time_in_force(time_in_force)
                 .with_time_window(TIME_WINDOW.with(|cell| cell.get()));
-            
-            if args.len() == 4 {
-                order = order.with_order_id::<C>(&args[3]);
-            }
+            let hint = args.get(3).map(|hint| hint.to_string());
 
             PUSH.with(move |cell| {
                 cell.borrow()
                     .as_ref()
                     .unwrap()
-                    .unbounded_send(PushEvent::Order(order))
+                    .unbounded_send(PushEvent::Order(order, hint))
                     .unwrap();
             });
         }
@@ -6,7 +6,8 @@ mod prompt;
 mod input;
 
 use std::fs::File;
-use trade::api::{self, ApiClient, binance, gdax, hitbtc};
+use trade::api;
+use trade::api::dynamic::{DynApiClient, Registry};
 use clap::clap_app;
 
 use cursive::Cursive;
@@ -27,14 +28,14 @@ fn build_siv() -> Cursive {
     siv
 }
 
-fn draw_input_line<C: ApiClient>(siv: &mut Cursive) {
+fn draw_input_line(siv: &mut Cursive) {
     siv.add_layer(
         EditView::new()
             .filler(" ")
             .on_submit(move |siv, content| {
-                input::submit_input::<C>(siv, content);
+                input::submit_input(siv, content);
                 siv.pop_layer();
-                draw_input_line::<C>(siv);
+                draw_input_line(siv);
             }).full_width()
     );
     siv.reposition_layer(
@@ -43,7 +44,7 @@ fn draw_input_line<C: ApiClient>(siv: &mut Cursive) {
     );
 }
 
-fn run<C: ApiClient + Send + 'static>(client: C, symbol: &str) {
+fn run(client: Box<dyn DynApiClient>, symbol: &str) {
     let mut siv = build_siv();
 
     let (prompt, push) = prompt::Prompt::new(client, symbol);
@@ -51,7 +52,7 @@ fn run<C: ApiClient + Send + 'static>(client: C, symbol: &str) {
 
     siv.add_layer(prompt.full_screen());
 
-    draw_input_line::<C>(&mut siv);
+    draw_input_line(&mut siv);
     siv.run();
 }
 
@@ -62,7 +63,7 @@ fn main() {
         (version: "0.1.0")
         (author: "scalexm <martin.alex32@hotmail.fr>")
         (about: "Small CLI app for testing `trade-rs` API")
-        (@arg exchange: +required "Exchange name (`gdax` or `binance`)")
+        (@arg exchange: +required "Exchange name (`binance`, `gdax` or `hitbtc`)")
         (@arg symbol: +required "Symbol name")
         (@arg params: -p --params +takes_value "Params file (default = `params.json`)")
         (@arg keys: -k --keys +takes_value "Keys file (default = `keys.json`)")
@@ -77,43 +78,13 @@ fn main() {
     let params: api::Params = serde_json::from_reader(params)
         .expect("expected valid JSON for `api::Params`");
 
+    let keys: serde_json::Value = serde_json::from_reader(keys)
+        .expect("expected valid JSON for the key pair");
+
     let symbol = matches.value_of("symbol").unwrap();
-    match matches.value_of("exchange").unwrap() {
-        "binance" => {
-            let keys = serde_json::from_reader(keys)
-                .expect("expected valid JSON for `binance::KeyPair`");
-
-            let client = binance::Client::new(
-                params,
-                Some(keys)
-            ).expect("unable to create client");
-            run(client, symbol);
-        },
-
-        "gdax" => {
-            let keys = serde_json::from_reader(keys)
-                .expect("expected valid JSON for `gdax::KeyPair`");
-
-            let client = gdax::Client::new(
-                params,
-                Some(keys)
-            ).expect("unable to create client");
-            run(client, symbol);
-        }
-
-        "hitbtc" => {
-            let keys = serde_json::from_reader(keys)
-                .expect("expected valid JSON for `hitbtc::KeyPair`");
-
-            let client = hitbtc::Client::new(
-                params,
-                Some(keys)
-            ).expect("unable to create client");
-            run(client, symbol);
-        }
-
-        other => {
-            eprintln!("unsupported exchange: `{}`", other);
-        }
+    let exchange = matches.value_of("exchange").unwrap();
+    match Registry::default().build(exchange, params, Some(keys)) {
+        Ok(client) => run(client, symbol),
+        Err(err) => eprintln!("unable to create client: {}", err),
     }
 }
@@ -1,5 +1,6 @@
 use trade::order_book::{self, OrderBook};
-use trade::api::{OrderConfirmation, ApiClient};
+use trade::api::OrderConfirmation;
+use trade::api::dynamic::DynApiClient;
 use std::collections::HashMap;
 use futures::sync::mpsc::{unbounded, UnboundedSender};
 use std::thread;
@@ -20,7 +21,7 @@ pub struct Prompt {
 }
 
 impl Prompt {
-    pub fn new<C: ApiClient + Send + 'static>(client: C, symbol: &str)
+    pub fn new<C: DynApiClient + 'static>(client: C, symbol: &str)
         -> (Self, UnboundedSender<PushEvent>)
     {
         let (pull_snd, pull_rcv) = mpsc::channel();
@@ -2,6 +2,7 @@ use trade::order_book::OrderBook;
 use trade::api::{self, *};
 use tokio::runtime::current_thread;
 use trade::api::symbol::{Symbol, IntoWithSymbol};
+use trade::api::dynamic::DynApiClient;
 use futures::prelude::*;
 use futures::sync::mpsc::UnboundedReceiver;
 use std::sync::mpsc;
@@ -17,7 +18,7 @@ pub enum PullEvent {
 }
 
 pub enum PushEvent {
-    Order(Order),
+    Order(Order, Option<String>),
     Cancel(Cancel),
     Message(String),
 }
@@ -29,10 +30,13 @@ pub struct PushThread<C> {
     pub symbol: Symbol,
 }
 
-impl<C: ApiClient + Send + 'static> PushThread<C> {
+impl<C: DynApiClient + 'static> PushThread<C> {
     fn process_event(&self, event: PushEvent) -> Result<(), ()> {
         match event {
-            PushEvent::Order(order) => {
+            PushEvent::Order(mut order, hint) => {
+                if let Some(hint) = hint {
+                    order = order.with_dyn_order_id(&self.client, &hint);
+                }
                 let cloned = self.pull.clone();
                 let order_fut = self.client.order(order.with_symbol(self.symbol)).then(move |res| {
                     cloned.send(PullEvent::OrderAck(res.err())).unwrap();
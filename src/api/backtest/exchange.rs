@@ -0,0 +1,244 @@
+//! A module defining the exchange side of the backtester: our own orders are matched
+//! against historical market data, with a model of their position in the queue of
+//! their limit.
+
+use std::collections::HashSet;
+use crate::Side;
+use crate::tick::TickUnit;
+use crate::order_book::{OrderBook, LimitUpdate};
+use crate::api::{OrderType, TimeInForce, Trade};
+use crate::api::errors::{OrderErrorKind, CancelErrorKind};
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+/// An event happening to one of our orders.
+pub(super) enum Event {
+    Confirmation {
+        order_id: String,
+        side: Side,
+        price: TickUnit,
+        size: TickUnit,
+    },
+
+    Fill {
+        order_id: String,
+        side: Side,
+        price: TickUnit,
+        size: TickUnit,
+        remaining: TickUnit,
+        maker: bool,
+    },
+
+    Expiration {
+        order_id: String,
+    },
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+struct RestingOrder {
+    order_id: String,
+    side: Side,
+    price: TickUnit,
+    remaining: TickUnit,
+
+    /// Market size still ahead of this order at its limit.
+    queue_ahead: TickUnit,
+}
+
+#[derive(Clone, Debug, Default)]
+/// Historical order book along with our resting orders.
+///
+/// Each resting order starts at the back of the queue of its limit. The queue ahead
+/// shrinks with trades at that limit, and is capped by the size of the limit when the
+/// latter decreases (i.e. cancellations are assumed to happen behind us whenever
+/// possible). An order is filled by the part of the traded volume exceeding the queue
+/// ahead, and is entirely filled if the market trades or quotes through its price.
+/// Each resting order is modelled independently of the others.
+///
+/// Our orders taking liquidity consume the historical book, until the next update of
+/// the consumed limits.
+pub(super) struct Exchange {
+    book: OrderBook,
+
+    /// Resting orders, in time priority.
+    orders: Vec<RestingOrder>,
+
+    order_ids: HashSet<String>,
+}
+
+fn crosses(book: &OrderBook, side: Side, price: TickUnit) -> bool {
+    match side {
+        Side::Bid => price >= book.best_ask(),
+        Side::Ask => price <= book.best_bid(),
+    }
+}
+
+impl Exchange {
+    pub(super) fn new() -> Self {
+        Default::default()
+    }
+
+    pub(super) fn order_book(&self) -> &OrderBook {
+        &self.book
+    }
+
+    pub(super) fn insert(
+        &mut self,
+        order_id: String,
+        side: Side,
+        price: TickUnit,
+        size: TickUnit,
+        order_type: OrderType,
+        time_in_force: TimeInForce
+    ) -> Result<Vec<Event>, OrderErrorKind>
+    {
+        if self.order_ids.contains(&order_id) {
+            return Err(OrderErrorKind::DuplicateOrder);
+        }
+
+        if order_type == OrderType::LimitMaker && crosses(&self.book, side, price) {
+            return Err(OrderErrorKind::WouldTakeLiquidity);
+        }
+
+        self.order_ids.insert(order_id.clone());
+        let mut events = vec![Event::Confirmation {
+            order_id: order_id.clone(),
+            side,
+            price,
+            size,
+        }];
+
+        let (other_side, limits): (_, Vec<_>) = match side {
+            Side::Bid => (
+                Side::Ask,
+                self.book.ask().take_while(|(&p, _)| p <= price).map(|(&p, &s)| (p, s)).collect()
+            ),
+            Side::Ask => (
+                Side::Bid,
+                self.book.bid().take_while(|(&p, _)| p >= price).map(|(&p, &s)| (p, s)).collect()
+            ),
+        };
+
+        let available: TickUnit = limits.iter().map(|(_, size)| size).sum();
+        if time_in_force == TimeInForce::FillOrKilll && available < size {
+            events.push(Event::Expiration { order_id });
+            return Ok(events);
+        }
+
+        let mut remaining = size;
+        for (limit_price, limit_size) in limits {
+            if remaining == 0 {
+                break;
+            }
+
+            let consumed = std::cmp::min(limit_size, remaining);
+            remaining -= consumed;
+            self.book.update(LimitUpdate::new(limit_price, limit_size - consumed, other_side));
+            events.push(Event::Fill {
+                order_id: order_id.clone(),
+                side,
+                price: limit_price,
+                size: consumed,
+                remaining,
+                maker: false,
+            });
+        }
+
+        if remaining > 0 {
+            if time_in_force == TimeInForce::GoodTilCanceled {
+                self.orders.push(RestingOrder {
+                    order_id,
+                    side,
+                    price,
+                    remaining,
+                    queue_ahead: self.book.size_at_limit(side, price),
+                });
+            } else {
+                events.push(Event::Expiration { order_id });
+            }
+        }
+
+        Ok(events)
+    }
+
+    pub(super) fn cancel(&mut self, order_id: &str) -> Result<Vec<Event>, CancelErrorKind> {
+        let index = self.orders.iter()
+            .position(|order| order.order_id == order_id)
+            .ok_or(CancelErrorKind::UnknownOrder)?;
+        let order = self.orders.remove(index);
+
+        Ok(vec![Event::Expiration {
+            order_id: order.order_id,
+        }])
+    }
+
+    /// Apply a market update to the historical book.
+    pub(super) fn on_limit_update(&mut self, update: LimitUpdate) -> Vec<Event> {
+        self.book.update(update);
+
+        for order in &mut self.orders {
+            if order.side == update.side && order.price == update.price {
+                order.queue_ahead = std::cmp::min(order.queue_ahead, update.size);
+            }
+        }
+
+        let book = &self.book;
+        fill(&mut self.orders, |order| {
+            if crosses(book, order.side, order.price) {
+                order.remaining
+            } else {
+                0
+            }
+        })
+    }
+
+    /// Apply a market trade.
+    pub(super) fn on_trade(&mut self, trade: Trade) -> Vec<Event> {
+        fill(&mut self.orders, |order| {
+            if order.side != trade.maker_side {
+                return 0;
+            }
+
+            let traded_through = match order.side {
+                Side::Bid => trade.price < order.price,
+                Side::Ask => trade.price > order.price,
+            };
+
+            if traded_through {
+                order.remaining
+            } else if trade.price == order.price {
+                let filled = trade.size.saturating_sub(order.queue_ahead);
+                order.queue_ahead = order.queue_ahead.saturating_sub(trade.size);
+                std::cmp::min(filled, order.remaining)
+            } else {
+                0
+            }
+        })
+    }
+}
+
+/// Fill each resting order by the size returned by `filled`, as a maker.
+fn fill<F>(orders: &mut Vec<RestingOrder>, mut filled: F) -> Vec<Event>
+    where F: FnMut(&mut RestingOrder) -> TickUnit
+{
+    let mut events = Vec::new();
+
+    for order in orders.iter_mut() {
+        let size = filled(order);
+        if size == 0 {
+            continue;
+        }
+
+        order.remaining -= size;
+        events.push(Event::Fill {
+            order_id: order.order_id.clone(),
+            side: order.side,
+            price: order.price,
+            size,
+            remaining: order.remaining,
+            maker: true,
+        });
+    }
+
+    orders.retain(|order| order.remaining > 0);
+    events
+}
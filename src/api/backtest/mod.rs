@@ -0,0 +1,626 @@
+//! A module defining an event-driven backtester, replaying historical market data
+//! (e.g. read from a recording) to a `Strategy`.
+//!
+//! The strategy trades through a `Client` implementing `ApiClient`, so that the same
+//! strategy can be run unchanged live. Orders are matched against the historical order
+//! book with a queue position model, and are subject to latency and fees.
+//!
+//! Amounts of cash, commissions and PnL are expressed in units of
+//! `price tick * size tick`.
+
+mod exchange;
+mod test;
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use futures::prelude::*;
+use futures::sync::oneshot;
+use tokio_current_thread::CurrentThread;
+use crate::Side;
+use crate::tick::TickUnit;
+use crate::order_book::OrderBook;
+use crate::api::{
+    self,
+    ApiClient,
+    GenerateOrderId,
+    Notification,
+    NotificationFlags,
+    Order,
+    OrderAck,
+    Cancel,
+    CancelAck,
+    Balances,
+    OrderType,
+    TimeInForce,
+    OrderConfirmation,
+    OrderUpdate,
+    OrderExpiration,
+};
+use crate::api::errors::{ApiError, RestErrorKind, ErrorKind, OrderErrorKind, CancelErrorKind};
+use crate::api::symbol::{Symbol, WithSymbol};
+use crate::api::timestamp::{Timestamp, Timestamped, IntoTimestamped};
+use crate::api::strategy::Strategy;
+use crate::api::channel::{self, ChannelSettings, OverflowPolicy, Receiver, Sender};
+use crate::api::sim::Fees;
+
+use self::exchange::{Exchange, Event};
+
+#[derive(Copy, Clone, PartialEq, Debug, Default)]
+/// Settings of a backtest.
+pub struct Settings {
+    /// One-way latency between the strategy and the exchange, in ms. Requests reach the
+    /// exchange `latency` ms after being sent, and responses as well as notifications
+    /// about our orders are received `latency` ms after the exchange emitted them.
+    /// Market data is received at its recorded timestamp.
+    pub latency: u64,
+
+    /// Fees charged on each fill.
+    pub fees: Fees,
+
+    /// Settings of the notification channel of each stream opened by the strategy.
+    /// `OverflowPolicy::Block` is not supported since the strategy consumes its streams
+    /// on the thread replaying the data.
+    pub channel: ChannelSettings,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+/// A fill of one of our orders.
+pub struct Fill {
+    /// Time at which the fill happened on the exchange.
+    pub timestamp: Timestamp,
+
+    /// Filled order.
+    pub order_id: String,
+
+    /// Side of the filled order.
+    pub side: Side,
+
+    /// Fill price, in ticks.
+    pub price: TickUnit,
+
+    /// Filled size, in ticks.
+    pub size: TickUnit,
+
+    /// Commission charged for this fill.
+    pub commission: TickUnit,
+
+    /// Whether the order was providing liquidity.
+    pub maker: bool,
+}
+
+#[derive(Clone, PartialEq, Debug, Default)]
+/// Performance statistics of a backtest.
+pub struct Statistics {
+    /// Number of orders accepted by the exchange.
+    pub orders: usize,
+
+    /// Number of orders rejected by the exchange.
+    pub rejected_orders: usize,
+
+    /// Number of successful cancels.
+    pub cancels: usize,
+
+    /// Total traded size, in ticks.
+    pub volume: TickUnit,
+
+    /// Maximum absolute inventory reached, in size ticks.
+    pub max_inventory: i128,
+
+    /// Maximum peak-to-trough decline of the PnL, marked to the mid price after each
+    /// event.
+    pub max_drawdown: f64,
+}
+
+#[derive(Clone, PartialEq, Debug, Default)]
+/// Outcome of a backtest.
+pub struct Report {
+    /// All the fills, in chronological order.
+    pub fills: Vec<Fill>,
+
+    /// Final inventory, in size ticks.
+    pub inventory: i128,
+
+    /// Final cash, commissions excluded.
+    pub cash: i128,
+
+    /// Total commissions paid.
+    pub commissions: TickUnit,
+
+    /// Final PnL, marked to the last mid price, commissions included.
+    pub pnl: f64,
+
+    /// Performance statistics.
+    pub statistics: Statistics,
+}
+
+impl Report {
+    fn record(&mut self, fill: Fill) {
+        let notional = i128::from(fill.price) * i128::from(fill.size);
+        match fill.side {
+            Side::Bid => {
+                self.inventory += i128::from(fill.size);
+                self.cash -= notional;
+            }
+            Side::Ask => {
+                self.inventory -= i128::from(fill.size);
+                self.cash += notional;
+            }
+        }
+        self.commissions += fill.commission;
+        self.statistics.volume += fill.size;
+        self.statistics.max_inventory = std::cmp::max(
+            self.statistics.max_inventory,
+            self.inventory.abs()
+        );
+        self.fills.push(fill);
+    }
+
+    /// Mark the PnL to the given `mid` price, returning the marked PnL.
+    fn mark(&mut self, mid: Option<f64>, peak: &mut f64) -> f64 {
+        if let Some(mid) = mid {
+            self.pnl = self.cash as f64 + self.inventory as f64 * mid - self.commissions as f64;
+        }
+
+        if self.pnl > *peak {
+            *peak = self.pnl;
+        }
+        if *peak - self.pnl > self.statistics.max_drawdown {
+            self.statistics.max_drawdown = *peak - self.pnl;
+        }
+        self.pnl
+    }
+}
+
+type OrderResult = Result<Timestamped<OrderAck>, RestErrorKind<OrderErrorKind>>;
+type CancelResult = Result<Timestamped<CancelAck>, RestErrorKind<CancelErrorKind>>;
+type OrderBookResult = Result<Timestamped<OrderBook>, RestErrorKind<!>>;
+
+struct OrderRequest {
+    order_id: String,
+    side: Side,
+    price: TickUnit,
+    size: TickUnit,
+    order_type: OrderType,
+    time_in_force: TimeInForce,
+}
+
+enum Action {
+    /// An order reaches the exchange.
+    Order(OrderRequest, oneshot::Sender<OrderResult>),
+
+    /// A cancel reaches the exchange.
+    Cancel(String, oneshot::Sender<CancelResult>),
+
+    /// A request for the order book reaches the exchange.
+    OrderBook(oneshot::Sender<OrderBookResult>),
+
+    /// A response reaches the strategy.
+    Respond(Box<dyn FnOnce() + Send>),
+
+    /// A notification reaches the strategy.
+    Deliver(Notification),
+}
+
+struct State {
+    now: Timestamp,
+    latency: u64,
+    channel: ChannelSettings,
+
+    /// (time, sequence number) => action, so that actions scheduled at the same time
+    /// are executed in FIFO order
+    scheduled: BTreeMap<(Timestamp, u64), Action>,
+    next_seq: u64,
+
+    subscribers: Vec<(NotificationFlags, Sender)>,
+}
+
+impl State {
+    fn schedule(&mut self, time: Timestamp, action: Action) {
+        self.scheduled.insert((time, self.next_seq), action);
+        self.next_seq += 1;
+    }
+}
+
+#[derive(Clone)]
+/// A client trading in a backtest.
+///
+/// # Note
+/// Balances are not simulated: `balances` always returns an empty map.
+pub struct Client {
+    symbol: Symbol,
+    state: Arc<Mutex<State>>,
+}
+
+impl Client {
+    /// Send `action` to the exchange, and return a future resolving to the response.
+    fn request<T, K, F>(&self, action: F)
+        -> Box<dyn Future<Item = Timestamped<T>, Error = ApiError<K>> + Send + 'static>
+            where T: Send + 'static,
+                  K: ErrorKind + Send + 'static,
+                  F: FnOnce(oneshot::Sender<Result<Timestamped<T>, RestErrorKind<K>>>) -> Action
+    {
+        let (snd, rcv) = oneshot::channel();
+        {
+            let mut state = self.state.lock().unwrap();
+            let time = state.now + state.latency;
+            state.schedule(time, action(snd));
+        }
+
+        let fut = rcv
+            .map_err(|_| ApiError::RestError(RestErrorKind::UnknownStatus.into()))
+            .and_then(|result| result.map_err(|kind| ApiError::RestError(kind.into())));
+        Box::new(fut)
+    }
+}
+
+impl ApiClient for Client {
+    type Stream = Receiver;
+
+    fn find_symbol(&self, symbol: &str) -> Option<Symbol> {
+        if symbol.to_lowercase() == self.symbol.name().to_lowercase() {
+            Some(self.symbol)
+        } else {
+            None
+        }
+    }
+
+    fn stream_with_flags(&self, _symbol: Symbol, flags: NotificationFlags) -> Self::Stream {
+        let mut state = self.state.lock().unwrap();
+        let (snd, rcv) = channel::channel(state.channel);
+        state.subscribers.push((flags, snd));
+        rcv
+    }
+
+    fn order(&self, order: WithSymbol<&Order>)
+        -> Box<dyn Future<Item = Timestamped<OrderAck>, Error = api::errors::OrderError> + Send + 'static>
+    {
+        let symbol = order.symbol();
+        let request = OrderRequest {
+            order_id: order.order_id()
+                .map(|order_id| order_id.to_owned())
+                .unwrap_or_else(|| Self::new_order_id("")),
+            side: order.side,
+            price: order.price.ticked(symbol.price_tick()),
+            size: order.size.ticked(symbol.size_tick()),
+            order_type: order.type_,
+            time_in_force: order.time_in_force,
+        };
+        self.request(move |snd| Action::Order(request, snd))
+    }
+
+    fn cancel(&self, cancel: WithSymbol<&Cancel>)
+        -> Box<dyn Future<Item = Timestamped<CancelAck>, Error = api::errors::CancelError> + Send + 'static>
+    {
+        let order_id = cancel.order_id().to_owned();
+        self.request(move |snd| Action::Cancel(order_id, snd))
+    }
+
+    fn ping(&self)
+        -> Box<dyn Future<Item = Timestamped<()>, Error = api::errors::Error> + Send + 'static>
+    {
+        let (snd, rcv) = oneshot::channel();
+        {
+            let mut state = self.state.lock().unwrap();
+            let time = state.now + 2 * state.latency;
+            state.schedule(time, Action::Respond(Box::new(move || {
+                let _ = snd.send(());
+            })));
+        }
+
+        let fut = rcv
+            .map(|()| ().timestamped())
+            .map_err(|_| ApiError::RestError(RestErrorKind::UnknownStatus.into()));
+        Box::new(fut)
+    }
+
+    fn order_book(&self, _symbol: Symbol)
+        -> Box<dyn Future<Item = Timestamped<OrderBook>, Error = api::errors::Error> + Send + 'static>
+    {
+        self.request(Action::OrderBook)
+    }
+
+    fn balances(&self)
+        -> Box<dyn Future<Item = Balances, Error = api::errors::Error> + Send + 'static>
+    {
+        Box::new(Ok(Balances::new()).into_future())
+    }
+}
+
+impl GenerateOrderId for Client {
+    fn new_order_id(hint: &str) -> String {
+        if hint.is_empty() {
+            use uuid::Uuid;
+            Uuid::new_v4().to_string()
+        } else {
+            hint.to_owned()
+        }
+    }
+}
+
+/// An event-driven backtester for one symbol.
+pub struct Backtester {
+    symbol: Symbol,
+    settings: Settings,
+}
+
+struct Run<'a, S> {
+    settings: Settings,
+    client: Client,
+    strategy: &'a mut S,
+    exchange: Exchange,
+    report: Report,
+    peak: f64,
+}
+
+impl Backtester {
+    /// Return a new `Backtester` trading `symbol` with the given `settings`.
+    ///
+    /// # Panics
+    /// Panic if the channel is bounded with `OverflowPolicy::Block`.
+    pub fn new(symbol: Symbol, settings: Settings) -> Self {
+        let channel = settings.channel;
+        assert!(
+            channel.capacity.is_none() || channel.policy != OverflowPolicy::Block,
+            "blocking channels are not supported"
+        );
+        Backtester {
+            symbol,
+            settings,
+        }
+    }
+
+    /// Feed `data` to `strategy`, and return the outcome. `data` yields market data
+    /// notifications along with the time at which they were received, in chronological
+    /// order, e.g. as read from a recording. Notifications about orders are ignored, since
+    /// they belong to the recorded session. Only order book updates and trades move the
+    /// simulated exchange, other market data (e.g. best bid and offer) is delivered as is.
+    ///
+    /// The run happens inside a `current_thread` executor: the tasks spawned by the strategy
+    /// run as soon as they are woken up, e.g. by an order acknowledgment, before the next
+    /// event is replayed. The executor is drained before returning.
+    pub fn run<I, S>(&self, data: I, strategy: &mut S) -> Report
+        where I: IntoIterator<Item = (Timestamp, Notification)>, S: Strategy<Client>
+    {
+        let client = Client {
+            symbol: self.symbol,
+            state: Arc::new(Mutex::new(State {
+                now: 0,
+                latency: self.settings.latency,
+                channel: self.settings.channel,
+                scheduled: BTreeMap::new(),
+                next_seq: 0,
+                subscribers: Vec::new(),
+            })),
+        };
+
+        let mut run = Run {
+            settings: self.settings,
+            client,
+            strategy,
+            exchange: Exchange::new(),
+            report: Report::default(),
+            peak: 0.,
+        };
+        let symbol = self.symbol;
+
+        let mut executor = CurrentThread::new();
+        let mut data = data.into_iter().peekable();
+        if let Some((timestamp, _)) = data.peek() {
+            run.client.state.lock().unwrap().now = *timestamp;
+        }
+        settle(&mut executor, || run.strategy.start(&run.client, symbol));
+
+        for (timestamp, notif) in data {
+            run.process_until(timestamp, &mut executor);
+            settle(&mut executor, || run.on_market_data(timestamp, notif));
+        }
+        run.process_until(Timestamp::max_value(), &mut executor);
+
+        // Close the subscribers' streams.
+        run.client.state.lock().unwrap().subscribers.clear();
+        executor.run().unwrap();
+        run.report
+    }
+}
+
+/// Call `f` inside `executor`, then run the tasks woken up by `f` until none is ready.
+fn settle<F: FnOnce()>(executor: &mut CurrentThread, f: F) {
+    executor.block_on(futures::future::lazy(|| {
+        f();
+        Ok::<_, ()>(())
+    })).unwrap();
+    while executor.turn(Some(Duration::from_millis(0))).unwrap().has_polled() {}
+}
+
+impl<'a, S: Strategy<Client>> Run<'a, S> {
+    /// Execute all scheduled actions up to time `time` (included). The tasks woken up by an
+    /// action, e.g. a strategy waiting for an order acknowledgment, run before the next one.
+    fn process_until(&mut self, time: Timestamp, executor: &mut CurrentThread) {
+        loop {
+            let (now, action) = {
+                let mut state = self.client.state.lock().unwrap();
+                let key = match state.scheduled.keys().next() {
+                    Some(&key) if key.0 <= time => key,
+                    _ => break,
+                };
+                state.now = key.0;
+                (key.0, state.scheduled.remove(&key).unwrap())
+            };
+            settle(executor, || self.execute(now, action));
+        }
+
+        let mut state = self.client.state.lock().unwrap();
+        if time > state.now && time != Timestamp::max_value() {
+            state.now = time;
+        }
+    }
+
+    fn execute(&mut self, now: Timestamp, action: Action) {
+        match action {
+            Action::Order(request, snd) => {
+                let result = self.exchange.insert(
+                    request.order_id.clone(),
+                    request.side,
+                    request.price,
+                    request.size,
+                    request.order_type,
+                    request.time_in_force
+                );
+
+                let result = match result {
+                    Ok(events) => {
+                        self.report.statistics.orders += 1;
+                        self.on_events(now, events);
+                        Ok(OrderAck {
+                            order_id: request.order_id,
+                        }.with_timestamp(now))
+                    }
+                    Err(kind) => {
+                        self.report.statistics.rejected_orders += 1;
+                        Err(RestErrorKind::Specific(kind))
+                    }
+                };
+                self.respond(now, snd, result);
+            }
+
+            Action::Cancel(order_id, snd) => {
+                let result = match self.exchange.cancel(&order_id) {
+                    Ok(events) => {
+                        self.report.statistics.cancels += 1;
+                        self.on_events(now, events);
+                        Ok(CancelAck.with_timestamp(now))
+                    }
+                    Err(kind) => Err(RestErrorKind::Specific(kind)),
+                };
+                self.respond(now, snd, result);
+            }
+
+            Action::OrderBook(snd) => {
+                let order_book = self.exchange.order_book().clone();
+                self.respond(now, snd, Ok(order_book.with_timestamp(now)));
+            }
+
+            Action::Respond(respond) => respond(),
+
+            Action::Deliver(notif) => self.deliver(notif),
+        }
+    }
+
+    fn respond<T: Send + 'static>(&self, now: Timestamp, snd: oneshot::Sender<T>, result: T) {
+        let mut state = self.client.state.lock().unwrap();
+        let time = now + state.latency;
+        state.schedule(time, Action::Respond(Box::new(move || {
+            let _ = snd.send(result);
+        })));
+    }
+
+    /// Record the events which happened to our orders at time `now`, and schedule the
+    /// corresponding notifications.
+    fn on_events(&mut self, now: Timestamp, events: Vec<Event>) {
+        let mut notifs = Vec::new();
+
+        for event in events {
+            let notif = match event {
+                Event::Confirmation { order_id, side, price, size } => {
+                    Notification::OrderConfirmation(OrderConfirmation {
+                        order_id,
+                        price,
+                        size,
+                        side,
+                    }.with_timestamp(now))
+                }
+
+                Event::Fill { order_id, side, price, size, remaining, maker } => {
+                    let fees = self.settings.fees;
+                    let fee = if maker { fees.maker } else { fees.taker };
+                    let commission = Fees::commission(fee, price, size);
+                    self.report.record(Fill {
+                        timestamp: now,
+                        order_id: order_id.clone(),
+                        side,
+                        price,
+                        size,
+                        commission,
+                        maker,
+                    });
+
+                    Notification::OrderUpdate(OrderUpdate {
+                        order_id,
+                        consumed_size: size,
+                        remaining_size: remaining,
+                        consumed_price: price,
+                        commission,
+                    }.with_timestamp(now))
+                }
+
+                Event::Expiration { order_id } => {
+                    Notification::OrderExpiration(OrderExpiration {
+                        order_id,
+                    }.with_timestamp(now))
+                }
+            };
+            notifs.push(notif);
+        }
+
+        let mut state = self.client.state.lock().unwrap();
+        let time = now + state.latency;
+        for notif in notifs {
+            state.schedule(time, Action::Deliver(notif));
+        }
+    }
+
+    fn on_market_data(&mut self, timestamp: Timestamp, notif: Notification) {
+        let events = match &notif {
+            Notification::LimitUpdates(updates) => {
+                let mut events = Vec::new();
+                for update in updates {
+                    events.extend(self.exchange.on_limit_update(**update));
+                }
+                events
+            }
+            Notification::Trade(trade) => self.exchange.on_trade(**trade),
+            Notification::OrderConfirmation(..) |
+            Notification::OrderUpdate(..) |
+            Notification::OrderExpiration(..) => return,
+            _ => Vec::new(),
+        };
+        self.on_events(timestamp, events);
+
+        let mid = self.exchange.order_book().mid();
+        self.report.mark(mid, &mut self.peak);
+
+        self.deliver(notif);
+    }
+
+    fn deliver(&mut self, notif: Notification) {
+        let flag = match notif {
+            // The full book is also sent to partial book and best bid offer subscribers.
+            Notification::LimitUpdates(..) => {
+                NotificationFlags::ORDER_BOOK |
+                    NotificationFlags::PARTIAL_ORDER_BOOK |
+                    NotificationFlags::BEST_BID_OFFER
+            }
+            Notification::BestBidOffer(..) => NotificationFlags::BEST_BID_OFFER,
+            Notification::Trade(..) => NotificationFlags::TRADES,
+            Notification::L3Updates(..) => NotificationFlags::ORDER_BOOK_L3,
+            Notification::Integrity(..) => {
+                NotificationFlags::ORDER_BOOK | NotificationFlags::PARTIAL_ORDER_BOOK
+            }
+            Notification::Overflow(..) => NotificationFlags::all(),
+            _ => NotificationFlags::ORDERS,
+        };
+
+        {
+            let mut state = self.client.state.lock().unwrap();
+            state.subscribers.retain(|(_, snd)| !snd.is_closed());
+            for (flags, snd) in &state.subscribers {
+                if flags.intersects(flag) {
+                    let _ = snd.send(notif.clone());
+                }
+            }
+        }
+
+        self.strategy.on_notification(&self.client, &notif);
+    }
+}
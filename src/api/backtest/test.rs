@@ -0,0 +1,199 @@
+#![cfg(test)]
+
+use crate::Side;
+use crate::tick::Tick;
+use crate::api::{ApiClient, Order, OrderUpdate, BestBidOffer, Notification};
+use crate::api::symbol::{Symbol, IntoWithSymbol};
+use crate::api::timestamp::IntoTimestamped;
+use crate::api::strategy::Strategy;
+use crate::api::sim::Fees;
+use crate::api::notifications::{limits, trade};
+use crate::api::backtest::{Backtester, Settings, Client};
+
+/// Join the bid once, at the first book update.
+#[derive(Default)]
+struct JoinBid {
+    symbol: Option<Symbol>,
+    received: Vec<Notification>,
+}
+
+impl<C: ApiClient> Strategy<C> for JoinBid {
+    fn start(&mut self, _client: &C, symbol: Symbol) {
+        self.symbol = Some(symbol);
+    }
+
+    fn on_notification(&mut self, client: &C, notification: &Notification) {
+        if self.received.is_empty() {
+            let order = Order::new(100, 2, Side::Bid).with_order_id::<C>("join");
+            let symbol = self.symbol.unwrap();
+            tokio::executor::current_thread::spawn(
+                futures::Future::then(client.order(order.with_symbol(symbol)), |_| Ok(()))
+            );
+        }
+        self.received.push(notification.clone());
+    }
+}
+
+#[test]
+fn queue_position() {
+    let symbol = Symbol::new("BTCUSD", Tick::new(1), Tick::new(1)).unwrap();
+    let settings = Settings {
+        latency: 10,
+        fees: Fees { maker: 0.01, taker: 0.02 },
+        ..Settings::default()
+    };
+
+    let data = vec![
+        (1000, limits(1000, &[(100, 5, Side::Bid), (102, 5, Side::Ask)])),
+
+        // Before our order reaches the exchange.
+        (1005, trade(1005, 100, 1, Side::Bid)),
+        (1010, limits(1010, &[(100, 4, Side::Bid)])),
+
+        // Order is inserted with 4 ahead, a cancel leaves 3 ahead.
+        (1020, limits(1020, &[(100, 3, Side::Bid)])),
+        (1030, trade(1030, 100, 4, Side::Bid)),
+        (1040, limits(1040, &[(102, 0, Side::Ask), (101, 5, Side::Ask)])),
+    ];
+
+    let mut strategy = JoinBid::default();
+    let report = Backtester::new(symbol, settings).run(data, &mut strategy);
+
+    assert_eq!(report.fills.len(), 1);
+    let fill = &report.fills[0];
+    assert_eq!((fill.timestamp, fill.price, fill.size, fill.maker), (1030, 100, 1, true));
+    assert_eq!(fill.commission, 1);
+    assert_eq!(report.inventory, 1);
+    assert_eq!(report.cash, -100);
+    assert_eq!(report.pnl, -100. + 100.5 - 1.);
+    assert_eq!(report.statistics.orders, 1);
+
+    // Confirmation and fill are received with latency.
+    match &strategy.received[..] {
+        [_, _, _, Notification::OrderConfirmation(confirmation), _, _,
+         Notification::OrderUpdate(update), _] =>
+        {
+            assert_eq!(confirmation.timestamp(), 1010);
+            assert_eq!(update.timestamp(), 1030);
+            assert_eq!(update.remaining_size, 1);
+        }
+        other => panic!("unexpected notifications: {:?}", other),
+    }
+}
+
+#[test]
+fn take_liquidity() {
+    struct Take(bool);
+
+    impl Strategy<Client> for Take {
+        fn on_notification(&mut self, client: &Client, _: &Notification) {
+            if self.0 {
+                return;
+            }
+            self.0 = true;
+
+            let symbol = client.find_symbol("btcusd").unwrap();
+            let order = Order::new(103, 8, Side::Bid);
+            tokio::executor::current_thread::spawn(
+                futures::Future::then(client.order(order.with_symbol(symbol)), |_| Ok(()))
+            );
+        }
+    }
+
+    let symbol = Symbol::new("BTCUSD", Tick::new(1), Tick::new(1)).unwrap();
+    let data = vec![
+        (0, limits(0, &[(102, 5, Side::Ask), (103, 2, Side::Ask), (104, 5, Side::Ask)])),
+    ];
+    let report = Backtester::new(symbol, Settings::default()).run(data, &mut Take(false));
+
+    let fills: Vec<_> = report.fills.iter().map(|fill| (fill.price, fill.size)).collect();
+    assert_eq!(fills, vec![(102, 5), (103, 2)]);
+    assert_eq!(report.inventory, 7);
+    assert_eq!(report.statistics.volume, 7);
+}
+
+#[test]
+fn react_to_reject() {
+    use futures::future::{self, Either, Future};
+    use crate::api::OrderType;
+
+    /// Send a post-only order crossing the book, and a less aggressive one once it is
+    /// rejected.
+    struct Retry(bool);
+
+    impl Strategy<Client> for Retry {
+        fn on_notification(&mut self, client: &Client, _: &Notification) {
+            if self.0 {
+                return;
+            }
+            self.0 = true;
+
+            let symbol = client.find_symbol("btcusd").unwrap();
+            let order = Order::new(102, 2, Side::Bid).with_order_type(OrderType::LimitMaker);
+            let client = client.clone();
+            let fut = client.order(order.with_symbol(symbol)).then(move |result| {
+                match result {
+                    Ok(_) => Either::A(future::ok(())),
+                    Err(_) => {
+                        let order = Order::new(101, 2, Side::Bid);
+                        Either::B(client.order(order.with_symbol(symbol)).then(|_| Ok(())))
+                    }
+                }
+            });
+            tokio::executor::current_thread::spawn(fut);
+        }
+    }
+
+    let symbol = Symbol::new("BTCUSD", Tick::new(1), Tick::new(1)).unwrap();
+    let settings = Settings {
+        latency: 10,
+        ..Settings::default()
+    };
+    let data = vec![
+        (1000, limits(1000, &[(100, 5, Side::Bid), (102, 5, Side::Ask)])),
+
+        // The rejection is received at 1020, the second order reaches the exchange at 1030.
+        (1040, trade(1040, 101, 3, Side::Bid)),
+    ];
+    let report = Backtester::new(symbol, settings).run(data, &mut Retry(false));
+
+    assert_eq!(report.statistics.rejected_orders, 1);
+    assert_eq!(report.statistics.orders, 1);
+    let fills: Vec<_> = report.fills.iter()
+        .map(|fill| (fill.timestamp, fill.price, fill.size))
+        .collect();
+    assert_eq!(fills, vec![(1040, 101, 2)]);
+}
+
+#[test]
+fn other_market_data() {
+    let symbol = Symbol::new("BTCUSD", Tick::new(1), Tick::new(1)).unwrap();
+    let bbo = Notification::BestBidOffer(BestBidOffer {
+        bid_price: 100,
+        bid_size: 5,
+        ask_price: 102,
+        ask_size: 5,
+    }.with_timestamp(1010));
+    let recorded_update = Notification::OrderUpdate(OrderUpdate {
+        order_id: "recorded".to_owned(),
+        consumed_size: 1,
+        remaining_size: 0,
+        consumed_price: 100,
+        commission: 0,
+    }.with_timestamp(1020));
+    let data = vec![
+        (1000, limits(1000, &[(100, 5, Side::Bid), (102, 5, Side::Ask)])),
+        (1010, bbo.clone()),
+        (1020, recorded_update),
+    ];
+
+    let mut strategy = JoinBid::default();
+    Backtester::new(symbol, Settings::default()).run(data, &mut strategy);
+
+    // The best bid and offer is delivered, the orders of the recorded session are not.
+    assert!(strategy.received.contains(&bbo));
+    assert!(strategy.received.iter().all(|notif| match notif {
+        Notification::OrderUpdate(update) => update.order_id != "recorded",
+        _ => true,
+    }));
+}
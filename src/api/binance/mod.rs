@@ -1,11 +1,13 @@
 //! Implementation of `ApiClient` for the binance API.
 
 pub mod errors;
+pub mod params;
 mod wss;
 mod rest;
+mod test;
 
-use openssl::pkey::{PKey, Private};
 use std::collections::HashMap;
+use std::sync::Arc;
 use futures::prelude::*;
 use log::debug;
 use serde_derive::{Serialize, Deserialize};
@@ -18,18 +20,20 @@ use crate::api::{
     OrderAck,
     Cancel,
     CancelAck,
-    Notification,
     NotificationFlags,
     Balances,
 };
+use crate::order_book::OrderBook;
 use crate::api::symbol::{Symbol, WithSymbol};
 use crate::api::timestamp::Timestamped;
+use crate::api::keys::{SecretKey, RequestSigner, KeyProvider};
+use crate::api::transport::{self, RestTransport, StreamingTransport, HttpsTransport, WsTransport};
 
-#[derive(Clone, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 /// A binance key pair: api key + secret key.
 pub struct KeyPair {
     api_key: String,
-    secret_key: String,
+    secret_key: SecretKey,
 }
 
 impl KeyPair {
@@ -37,14 +41,31 @@ impl KeyPair {
     pub fn new(api_key: String, secret_key: String) -> Self {
         KeyPair {
             api_key,
-            secret_key,
+            secret_key: secret_key.into(),
         }
     }
+
+    /// Return a new key pair whose requests are signed by `signer` instead of a secret key,
+    /// e.g. a `keys::SocketSigner`.
+    pub fn with_signer(api_key: String, signer: Arc<dyn RequestSigner>) -> Self {
+        KeyPair {
+            api_key,
+            secret_key: SecretKey::Signer(signer),
+        }
+    }
+
+    /// Load a key pair from the secrets `api_key` and `secret_key` of `provider`.
+    pub fn from_provider(provider: &dyn KeyProvider) -> Result<Self, failure::Error> {
+        Ok(KeyPair {
+            api_key: provider.secret("api_key")?.expose().to_owned(),
+            secret_key: provider.secret("secret_key")?.into(),
+        })
+    }
 }
 
 struct Keys {
     api_key: String,
-    secret_key: PKey<Private>,
+    signer: Arc<dyn RequestSigner>,
     listen_key: String,
 }
 
@@ -59,11 +80,12 @@ struct Keys {
 /// recommends sending a ping every 30 minutes. If the listen key becomes invalid, this client
 /// will stop forwarding the user data stream. The only way to fix it will be to drop the client
 /// and create a new one.
-pub struct Client {
+pub struct Client<R = HttpsTransport, S = WsTransport> {
     params: Params,
     keys: Option<Keys>,
     symbols: HashMap<String, Symbol>,
-    http_client: hyper::Client<hyper_tls::HttpsConnector<hyper::client::HttpConnector>>,
+    rest: R,
+    streaming: S,
 }
 
 impl Client {
@@ -75,25 +97,34 @@ impl Client {
     /// The request may block the thread if requesting a listen key for the user data stream.
     /// The method will also block when fetching the available symbols from binance.
     pub fn new(params: Params, key_pair: Option<KeyPair>) -> Result<Self, failure::Error> {
-        use tokio::runtime::current_thread;
+        let rest = transport::https(&params.transport)?;
+        let streaming = WsTransport::new(&params.transport);
+        Client::with_transport(params, key_pair, rest, streaming)
+    }
+}
 
-        let http_client = hyper::Client::builder().build::<_, hyper::Body>(
-            hyper_tls::HttpsConnector::new(2)?
-        );
+impl<R: RestTransport, S: StreamingTransport> Client<R, S> {
+    /// Create a new binance API client with given `params`, performing REST requests
+    /// through `rest` and streaming notifications through `streaming`. See `Client::new`.
+    pub fn with_transport(params: Params, key_pair: Option<KeyPair>, rest: R, streaming: S)
+        -> Result<Self, failure::Error>
+    {
+        use tokio::runtime::current_thread;
 
         let mut client = match key_pair {
             Some(pair) => {
-                let secret_key = PKey::hmac(pair.secret_key.as_bytes())?;
+                let signer = pair.secret_key.signer(|key| Ok(key.as_bytes().to_vec()))?;
 
                 let mut client = Client {
                     params,
                     keys: Some(Keys {
                         api_key: pair.api_key,
-                        secret_key,
+                        signer,
                         listen_key: String::new(),
                     }),
                     symbols: HashMap::new(),
-                    http_client,
+                    rest,
+                    streaming,
                 };
 
                 debug!("requesting listen key");
@@ -108,7 +139,8 @@ impl Client {
                 params,
                 keys: None,
                 symbols: HashMap::new(),
-                http_client,
+                rest,
+                streaming,
             }
         };
 
@@ -120,8 +152,8 @@ impl Client {
     }
 }
 
-impl ApiClient for Client {
-    type Stream = futures::sync::mpsc::UnboundedReceiver<Notification>;
+impl<R: RestTransport, S: StreamingTransport> ApiClient for Client<R, S> {
+    type Stream = crate::api::channel::Receiver;
 
     fn find_symbol(&self, symbol: &str) -> Option<Symbol> {
         self.symbols.get(&symbol.to_lowercase()).cloned()
@@ -149,6 +181,12 @@ impl ApiClient for Client {
         self.ping_impl()
     }
 
+    fn order_book(&self, symbol: Symbol)
+        -> Box<dyn Future<Item = Timestamped<OrderBook>, Error = api::errors::Error> + Send + 'static>
+    {
+        Box::new(self.order_book_impl(symbol))
+    }
+
     fn balances(&self)
         -> Box<dyn Future<Item = Balances, Error = api::errors::Error> + Send + 'static>
     {
@@ -156,7 +194,7 @@ impl ApiClient for Client {
     }
 }
 
-impl GenerateOrderId for Client {
+impl<R, S> GenerateOrderId for Client<R, S> {
     fn new_order_id(hint: &str) -> String {
         hint.to_owned()
     }
@@ -0,0 +1,14 @@
+//! Known endpoints of binance.
+
+use crate::api::Params;
+
+/// Params for the production environment.
+pub fn production() -> Params {
+    Params::new("wss://stream.binance.com:9443", "https://api.binance.com")
+}
+
+/// Params for the spot testnet, see https://testnet.binance.vision. The testnet requires
+/// its own key pair.
+pub fn testnet() -> Params {
+    Params::new("wss://testnet.binance.vision", "https://testnet.binance.vision")
+}
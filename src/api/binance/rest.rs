@@ -1,11 +1,14 @@
 use hyper::Method;
 use futures::prelude::*;
+use futures::future::{self, Either};
 use std::collections::HashMap;
 use failure::Fail;
 use serde_derive::Deserialize;
+use serde::de::IgnoredAny;
 use log::error;
 use crate::Side;
-use crate::tick::Tick;
+use crate::tick::{Tick, TickUnit};
+use crate::order_book::{OrderBook, LimitUpdate};
 use crate::api::{
     self,
     OrderType,
@@ -21,6 +24,7 @@ use crate::api::symbol::{Symbol, WithSymbol};
 use crate::api::binance::Client;
 use crate::api::binance::errors::RestError;
 use crate::api::timestamp::{timestamp_ms, Timestamped, IntoTimestamped};
+use crate::api::transport::{RestTransport, StreamingTransport};
 
 #[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Deserialize)]
 #[allow(non_snake_case)]
@@ -42,6 +46,14 @@ struct BinanceAccountInformation<'a> {
     balances: Vec<BinanceBalance<'a>>,
 }
 
+#[derive(Debug, Deserialize)]
+struct BinanceDepth<'a> {
+    #[serde(borrow)]
+    bids: Vec<(&'a str, &'a str, IgnoredAny)>,
+    #[serde(borrow)]
+    asks: Vec<(&'a str, &'a str, IgnoredAny)>,
+}
+
 #[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Deserialize)]
 #[allow(non_snake_case)]
 struct BinanceListenKey<'a> {
@@ -105,7 +117,7 @@ impl AsStr for TimeInForce {
     }
 }
 
-impl Client {
+impl<R: RestTransport, S: StreamingTransport> Client<R, S> {
     fn request<K: api::errors::ErrorKind>(
         &self,
         path: &str,
@@ -122,7 +134,13 @@ impl Client {
             None => query.into_string(),
             Some(keys) => {
                 request.header("X-MBX-APIKEY", keys.api_key.as_bytes());
-                query.into_string_with_signature(&keys.secret_key)
+                match query.into_string_with_signature(&*keys.signer) {
+                    Ok(query) => query,
+                    Err(err) => {
+                        let err = api::errors::RequestError::new(err.compat());
+                        return Either::A(future::err(api::errors::ApiError::RequestError(err)));
+                    }
+                }
             }
         };
 
@@ -133,19 +151,12 @@ impl Client {
         );
 
         request.method(method)
-            .header("User-Agent", &b"hyper"[..])
             .header("Content-Type", &b"application/x-www-form-urlencoded"[..])
             .uri(&address);
 
         // Unwrap because it is a bug if this fails (header failed to parse or something)
         let request = request.body(query.into()).unwrap();
-        self.http_client.request(request).and_then(|res| {
-            let status = res.status();
-            res.into_body().concat2().and_then(move |body| {
-                Ok((status, body))
-            })
-        })
-        .map_err(api::errors::RequestError::new)
+        Either::B(self.rest.request(request)
         .map_err(api::errors::ApiError::RequestError)
         .and_then(|(status, body)| {
             if status != hyper::StatusCode::OK {
@@ -157,7 +168,7 @@ impl Client {
                 )?;
             }
             Ok(body)
-        })
+        }))
     }
 
     crate fn order_impl(&self, order: WithSymbol<&Order>)
@@ -264,6 +275,56 @@ impl Client {
         })
     }
 
+    crate fn order_book_impl(&self, symbol: Symbol)
+        -> impl Future<Item = Timestamped<OrderBook>, Error = api::errors::Error> + Send + 'static
+    {
+        // Not going through `request`, which would sign this public endpoint.
+        let address = format!(
+            "{}/api/v1/depth?symbol={}&limit=1000",
+            self.params.rest_endpoint,
+            symbol.name()
+        );
+
+        let request = hyper::Request::get(address)
+            .body(hyper::Body::empty())
+            .expect("invalid address");
+
+        self.rest.request(request)
+        .map_err(api::errors::ApiError::RequestError)
+        .and_then(move |(status, body)| {
+            if status != hyper::StatusCode::OK {
+                let binance_error = serde_json::from_slice(&body);
+                let error = RestError::from_binance_error(status, binance_error.ok());
+                let kind = error.kind();
+                Err(
+                    api::errors::ApiError::RestError(error.context(kind).into())
+                )?;
+            }
+
+            let depth: BinanceDepth<'_> = serde_json::from_slice(&body)
+                .map_err(api::errors::RequestError::new)
+                .map_err(api::errors::ApiError::RequestError)?;
+
+            let ticked = |value, tick: Tick| -> Result<TickUnit, api::errors::Error> {
+                tick.ticked(value)
+                    .map_err(api::errors::RequestError::new)
+                    .map_err(api::errors::ApiError::RequestError)
+            };
+
+            let mut order_book = OrderBook::new();
+            let bid = depth.bids.iter().map(|limit| (limit, Side::Bid));
+            let ask = depth.asks.iter().map(|limit| (limit, Side::Ask));
+            for (&(price, size, _), side) in bid.chain(ask) {
+                order_book.update(LimitUpdate::new(
+                    ticked(price, symbol.price_tick())?,
+                    ticked(size, symbol.size_tick())?,
+                    side
+                ));
+            }
+            Ok(order_book.timestamped())
+        })
+    }
+
     crate fn get_symbols(&self)
         -> impl Future<Item = HashMap<String, Symbol>, Error = api::errors::Error> + Send + 'static
     {
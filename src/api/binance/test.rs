@@ -0,0 +1,132 @@
+#![cfg(test)]
+
+use hyper::{Method, StatusCode};
+use crate::Side;
+use crate::api::{ApiClient, Params, Order, Notification};
+use crate::api::errors::{ApiError, RestErrorKind, OrderErrorKind};
+use crate::api::symbol::IntoWithSymbol;
+use crate::api::transport::memory::{MemoryRest, MemoryStreaming, RecordedRequest};
+use crate::api::fixtures::{replay_stream_fixtures, replay_rest_error_fixtures};
+use crate::api::binance::{Client, KeyPair};
+use crate::api::binance::wss::HandlerImpl;
+use crate::api::binance::errors::RestError;
+
+const EXCHANGE_INFO: &str = r#"{"symbols":[{"symbol":"BTCUSDT","filters":[
+    {"filterType":"PRICE_FILTER","tickSize":"0.01000000"},
+    {"filterType":"LOT_SIZE","stepSize":"0.00000100"}
+]}]}"#;
+
+fn respond(request: &RecordedRequest) -> (StatusCode, String) {
+    match (&request.method, request.path.as_ref()) {
+        (&Method::GET, "/api/v1/exchangeInfo") => (StatusCode::OK, EXCHANGE_INFO.to_owned()),
+        (&Method::POST, "/api/v1/userDataStream") => {
+            (StatusCode::OK, r#"{"listenKey":"my_listen_key"}"#.to_owned())
+        }
+        (&Method::POST, "/api/v3/order") if request.body.contains("SELL") => (
+            StatusCode::BAD_REQUEST,
+            r#"{"code":-2010,"msg":"Account has insufficient balance for requested action."}"#
+                .to_owned()
+        ),
+        (&Method::POST, "/api/v3/order") => (
+            StatusCode::OK,
+            r#"{"clientOrderId":"my_order","transactTime":1536000000000}"#.to_owned()
+        ),
+        (&Method::GET, "/api/v1/depth") => (
+            StatusCode::OK,
+            r#"{"lastUpdateId":10,"bids":[["100.00","1.000000",[]]],"asks":[]}"#.to_owned()
+        ),
+        _ => (StatusCode::NOT_FOUND, String::new()),
+    }
+}
+
+fn params() -> Params {
+    Params::new("ws://mock", "http://mock")
+}
+
+fn client() -> (Client<MemoryRest, MemoryStreaming>, MemoryRest, MemoryStreaming) {
+    let params = params();
+    let key_pair = KeyPair::new("my_api_key".to_owned(), "my_secret_key".to_owned());
+    let rest = MemoryRest::new(respond);
+    let streaming = MemoryStreaming::new();
+    let client = Client::with_transport(
+        params,
+        Some(key_pair),
+        rest.clone(),
+        streaming.clone()
+    ).unwrap();
+    (client, rest, streaming)
+}
+
+#[test]
+fn order() {
+    use tokio::runtime::current_thread;
+
+    let (client, rest, _) = client();
+    let symbol = client.find_symbol("BTCUSDT").unwrap();
+
+    let order = Order::new("100.00", "1.5", Side::Bid).with_order_id::<Client>("my_order");
+    let ack = current_thread::block_on_all(client.order(order.with_symbol(symbol))).unwrap();
+    assert_eq!(ack.order_id, "my_order");
+    assert_eq!(ack.timestamp(), 1536000000000);
+
+    let request = rest.requests().pop().unwrap();
+    assert_eq!(request.headers["X-MBX-APIKEY"], "my_api_key");
+    assert!(request.body.starts_with(
+        "symbol=BTCUSDT&side=BUY&type=LIMIT&timeInForce=GTC&quantity=1.5&price=100.00"
+    ));
+    assert!(request.body.contains("&signature="));
+
+    let order = Order::new("100.00", "1.5", Side::Ask);
+    match current_thread::block_on_all(client.order(order.with_symbol(symbol))) {
+        Err(ApiError::RestError(err)) => assert_eq!(
+            err.kind(),
+            RestErrorKind::Specific(OrderErrorKind::InsufficientBalance)
+        ),
+        other => panic!("expected a REST error, got {:?}", other.map(|ack| ack.into_inner())),
+    }
+}
+
+#[test]
+fn stream() {
+    use futures::prelude::*;
+
+    let (client, _, streaming) = client();
+    let symbol = client.find_symbol("BTCUSDT").unwrap();
+
+    let server = streaming.accept();
+    server.send(r#"{"e":"depthUpdate","E":1,"U":10,"u":11,"b":[["99.00","2.000000",[]]],"a":[]}"#);
+    server.send(r#"{"e":"trade","E":3,"t":1,"p":"100.00","q":"0.500000","T":2,"m":true}"#);
+    drop(server);
+
+    let notifs: Vec<_> = client.stream(symbol).wait().map(|notif| notif.unwrap()).collect();
+    assert_eq!(notifs.len(), 1);
+    match &notifs[0] {
+        Notification::Trade(trade) => {
+            assert_eq!(trade.price, 10000);
+            assert_eq!(trade.size, 500000);
+            assert_eq!(trade.maker_side, Side::Bid);
+        }
+        other => panic!("unexpected notification {:?}", other),
+    }
+}
+
+#[test]
+fn stream_fixtures() {
+    replay_stream_fixtures(
+        "binance",
+        |symbol, flags| HandlerImpl::new(symbol, flags, params(), MemoryRest::new(respond)),
+        |handler, json, out| {
+            if let Some(notif) = handler.parse_message(json)? {
+                out.send(notif)?;
+            }
+            Ok(())
+        }
+    );
+}
+
+#[test]
+fn rest_error_fixtures() {
+    replay_rest_error_fixtures("binance", |status, body| {
+        RestError::from_binance_error(status, serde_json::from_slice(body).ok())
+    });
+}
@@ -2,13 +2,14 @@ use std::{mem, thread};
 use std::sync::mpsc;
 use std::borrow::Cow;
 use futures::prelude::*;
-use futures::sync::mpsc::{unbounded, UnboundedReceiver};
 use log::{error, debug};
 use failure::bail;
 use serde_derive::Deserialize;
 use crate::{tick, Side};
-use crate::order_book::LimitUpdate;
+use crate::order_book::{OrderBook, LimitUpdate};
 use crate::api::{
+    PARTIAL_DEPTH,
+    BestBidOffer,
     Notification,
     NotificationFlags,
     Params,
@@ -19,40 +20,49 @@ use crate::api::{
 };
 use crate::api::symbol::Symbol;
 use crate::api::wss;
-use crate::api::timestamp::{Timestamped, IntoTimestamped};
+use crate::api::channel;
+use crate::api::timestamp::{Timestamped, IntoTimestamped, Sequence};
+use crate::api::transport::{KeepAlive, RestTransport, StreamSink, StreamingTransport};
 use crate::api::binance::Client;
 use crate::api::binance::errors::RestError;
 
 
-impl Client {
+impl<R: RestTransport, S: StreamingTransport> Client<R, S> {
     crate fn new_stream(&self, symbol: Symbol, flags: NotificationFlags)
-        -> UnboundedReceiver<Notification>
+        -> channel::Receiver
     {
         let params = self.params.clone();
         let listen_key = self.keys.as_ref().map(|keys| keys.listen_key.clone());
-        let (snd, rcv) = unbounded();
+        let rest = self.rest.clone();
+        let streaming = self.streaming.clone();
+        let (snd, rcv) = channel::channel(self.params.channel);
         thread::spawn(move || {
-            let mut address = format!(
-               "{0}/ws/{1}@trade/{1}@depth",
-                params.streaming_endpoint,
-                symbol.name().to_lowercase(),
-            );
+            // Only subscribe to the streams needed by `flags`.
+            let name = symbol.name().to_lowercase();
+            let mut streams = Vec::new();
+            if flags.contains(NotificationFlags::TRADES) {
+                streams.push(format!("{}@trade", name));
+            }
+            if flags.contains(NotificationFlags::PARTIAL_ORDER_BOOK) {
+                streams.push(format!("{}@depth{}", name, PARTIAL_DEPTH));
+            } else if flags.contains(NotificationFlags::ORDER_BOOK) {
+                streams.push(format!("{}@depth", name));
+            }
+            if flags.contains(NotificationFlags::BEST_BID_OFFER) {
+                streams.push(format!("{}@bookTicker", name));
+            }
             if let Some(listen_key) = listen_key {
-                address += &format!("/{}", listen_key);
+                if flags.contains(NotificationFlags::ORDERS) {
+                    streams.push(listen_key);
+                }
             }
+            let address = format!("{}/ws/{}", params.streaming_endpoint, streams.join("/"));
 
             debug!("initiating WebSocket connection at {}", address);
 
-            if let Err(err) = ws::connect(address, |out| {
-                wss::Handler::new(out, snd.clone(), wss::KeepAlive::True, HandlerImpl{
-                    flags,
-                    symbol,
-                    params: params.clone(),
-                    book_snapshot_state: BookSnapshotState::None,
-                    previous_u: None,
-                })
-            })
-            {
+            let handler = wss::Handler::new(snd, HandlerImpl::new(symbol, flags, params, rest));
+
+            if let Err(err) = streaming.connect(&address, KeepAlive::True, Box::new(handler)) {
                 error!("WebSocket connection terminated with error: `{}`", err);
             }
         });
@@ -89,20 +99,27 @@ enum BookSnapshotState {
     Ok,
 }
 
-struct HandlerImpl {
+pub(super) struct HandlerImpl<R> {
     symbol: Symbol,
     flags: NotificationFlags,
     params: Params,
+    rest: R,
     book_snapshot_state: BookSnapshotState,
 
     /// Keep track of the `u` indicator sent by binance, this is used for checking
     /// the of the ordering of the limit updates.
     previous_u: Option<u64>,
+
+    /// Last book received through the partial depth stream, which sends the whole top of
+    /// the book each time.
+    partial_book: OrderBook,
 }
 
 #[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Deserialize)]
 #[allow(non_snake_case)]
 struct BinanceTrade<'a> {
+    E: u64,
+    t: u64,
     p: &'a str,
     q: &'a str,
     T: u64,
@@ -115,6 +132,7 @@ struct BinanceLimitUpdate<'a> {
     price: Cow<'a, str>,
     #[serde(borrow)]
     size: Cow<'a, str>,
+    #[serde(default)]
     _ignore: Vec<i32>,
 }
 
@@ -163,6 +181,7 @@ impl<'a> BinanceBookSnapshot<'a> {
 #[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Deserialize)]
 #[allow(non_snake_case)]
 struct BinanceExecutionReport<'a> {
+    E: u64,
     c: &'a str,
     C: &'a str,
     S: &'a str,
@@ -176,12 +195,39 @@ struct BinanceExecutionReport<'a> {
     T: u64,
 }
 
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Deserialize)]
+#[allow(non_snake_case)]
+struct BinanceBookTicker<'a> {
+    u: u64,
+    b: &'a str,
+    B: &'a str,
+    a: &'a str,
+    A: &'a str,
+}
+
 #[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Deserialize)]
 struct EventType<'a> {
+    /// Partial depth and book ticker messages have no event type.
+    #[serde(default)]
     e: &'a str,
+
+    /// Update id, only used for telling book ticker messages apart.
+    u: Option<u64>,
 }
 
-impl HandlerImpl {
+impl<R: RestTransport> HandlerImpl<R> {
+    pub(super) fn new(symbol: Symbol, flags: NotificationFlags, params: Params, rest: R) -> Self {
+        HandlerImpl {
+            symbol,
+            flags,
+            params,
+            rest,
+            book_snapshot_state: BookSnapshotState::None,
+            previous_u: None,
+            partial_book: OrderBook::new(),
+        }
+    }
+
     fn convert_binance_update(&self, l: &BinanceLimitUpdate, side: Side)
         -> Result<LimitUpdate, tick::ConversionError>
     {
@@ -194,10 +240,16 @@ impl HandlerImpl {
         )
     }
 
-    fn parse_message(&mut self, json: &str) -> Result<Option<Notification>, failure::Error> {
+    pub(super) fn parse_message(&mut self, json: &str)
+        -> Result<Option<Notification>, failure::Error>
+    {
         let event_type: EventType<'_> = serde_json::from_str(json)?;
+        let event = match event_type {
+            EventType { e: "", u: Some(_) } => "bookTicker",
+            EventType { e, .. } => e,
+        };
 
-        let notif = match event_type.e {
+        let notif = match event {
             "trade" if self.flags.contains(NotificationFlags::TRADES) => {
                 let trade: BinanceTrade<'_> = serde_json::from_str(json)?;
                 Some(
@@ -205,7 +257,9 @@ impl HandlerImpl {
                         size: self.symbol.size_tick().ticked(trade.q)?,
                         price: self.symbol.price_tick().ticked(trade.p)?,
                         maker_side: if trade.m { Side::Bid } else { Side::Ask },
-                    }.with_timestamp(trade.T))
+                    }.with_timestamp(trade.T)
+                        .with_sent(trade.E)
+                        .with_sequence(Sequence::single(trade.t)))
                 )
             },
 
@@ -220,14 +274,26 @@ impl HandlerImpl {
                 }
                 self.previous_u = Some(depth_update.u);
 
+                let sequence = Sequence {
+                    first: depth_update.U,
+                    last: depth_update.u,
+                };
                 let bid = depth_update.b
                     .iter()
                     .map(|l| self.convert_binance_update(l, Side::Bid))
-                    .map(|l| Ok(l?.with_timestamp(depth_update.E)));
+                    .map(|l| {
+                        Ok(l?.with_timestamp(depth_update.E)
+                            .with_sent(depth_update.E)
+                            .with_sequence(sequence))
+                    });
                 let ask = depth_update.a
                     .iter()
                     .map(|l| self.convert_binance_update(l, Side::Ask))
-                    .map(|l| Ok(l?.with_timestamp(depth_update.E)));
+                    .map(|l| {
+                        Ok(l?.with_timestamp(depth_update.E)
+                            .with_sent(depth_update.E)
+                            .with_sequence(sequence))
+                    });
 
                 let updates =  bid.chain(ask).collect::<Result<Vec<_>, tick::ConversionError>>()?;
                 if !updates.is_empty() {
@@ -237,6 +303,44 @@ impl HandlerImpl {
                 }
             },
 
+            "" if self.flags.contains(NotificationFlags::PARTIAL_ORDER_BOOK) => {
+                let snapshot: BinanceBookSnapshot<'_> = serde_json::from_str(json)?;
+
+                let mut order_book = OrderBook::new();
+                let bid = snapshot.bids.iter().map(|l| self.convert_binance_update(l, Side::Bid));
+                let ask = snapshot.asks.iter().map(|l| self.convert_binance_update(l, Side::Ask));
+                for update in bid.chain(ask) {
+                    order_book.update(update?);
+                }
+
+                // Partial depth messages are not timestamped by binance.
+                let sequence = Sequence::single(snapshot.lastUpdateId);
+                let updates: Vec<_> = self.partial_book.diff(&order_book)
+                    .map(|update| update.timestamped().with_sequence(sequence))
+                    .collect();
+                self.partial_book = order_book;
+
+                if !updates.is_empty() {
+                    Some(Notification::LimitUpdates(updates))
+                } else {
+                    None
+                }
+            }
+
+            "bookTicker" if self.flags.contains(NotificationFlags::BEST_BID_OFFER) => {
+                let ticker: BinanceBookTicker<'_> = serde_json::from_str(json)?;
+
+                // Book ticker messages are not timestamped by binance.
+                Some(
+                    Notification::BestBidOffer(BestBidOffer {
+                        bid_price: self.symbol.price_tick().ticked(ticker.b)?,
+                        bid_size: self.symbol.size_tick().ticked(ticker.B)?,
+                        ask_price: self.symbol.price_tick().ticked(ticker.a)?,
+                        ask_size: self.symbol.size_tick().ticked(ticker.A)?,
+                    }.timestamped().with_sequence(Sequence::single(ticker.u)))
+                )
+            }
+
             "executionReport" if self.flags.contains(NotificationFlags::ORDERS) => {
                 let report: BinanceExecutionReport<'_> = serde_json::from_str(json)?;
 
@@ -251,7 +355,7 @@ impl HandlerImpl {
                                 "SELL" => Side::Ask,
                                 other => bail!("wrong side `{}`", other),
                             },
-                        }.with_timestamp(report.T))
+                        }.with_timestamp(report.T).with_sent(report.E))
                     ),
                     
                     "TRADE" => Some(
@@ -264,19 +368,19 @@ impl HandlerImpl {
 
                             consumed_price: self.symbol.price_tick().ticked(report.L)?,
                             commission: self.symbol.commission_tick().ticked(report.n)?,
-                        }.with_timestamp(report.T))
+                        }.with_timestamp(report.T).with_sent(report.E))
                     ),
 
                     "EXPIRED" => Some(
                         Notification::OrderExpiration(OrderExpiration {
                             order_id: report.c.to_owned(), // subtle: lower case `c`
-                        }.with_timestamp(report.T))
+                        }.with_timestamp(report.T).with_sent(report.E))
                     ),
 
                     "CANCELED" => Some(
                         Notification::OrderExpiration(OrderExpiration {
                             order_id: report.C.to_owned(), // subtle: upper case `C`
-                        }.with_timestamp(report.T))
+                        }.with_timestamp(report.T).with_sent(report.E))
                     ),
 
                     // "REJECTED" should already be handled by the REST API.
@@ -296,16 +400,17 @@ impl HandlerImpl {
     ) -> Result<Notification, failure::Error>
     {
         let snapshot = snapshot?;
+        let sequence = Sequence::single(snapshot.lastUpdateId);
 
         let bid = snapshot.bids
             .iter()
             .map(|l| self.convert_binance_update(l, Side::Bid))
-            .map(|l| Ok(l?.timestamped()));
+            .map(|l| Ok(l?.timestamped().with_sequence(sequence)));
 
         let ask = snapshot.asks
             .iter()
             .map(|l| self.convert_binance_update(l, Side::Ask))
-            .map(|l| Ok(l?.timestamped()));
+            .map(|l| Ok(l?.timestamped().with_sequence(sequence)));
 
         let buffered = buffered_events
             .into_iter()
@@ -374,26 +479,17 @@ impl HandlerImpl {
             "{}/api/v1/depth?symbol={}&limit=1000",
             self.params.rest_endpoint,
             self.symbol.name()
-        ).parse().expect("invalid address");
+        );
 
         debug!("initiating LOB request at `{}`", address);
 
+        let request = hyper::Request::get(address)
+            .body(hyper::Body::empty())
+            .expect("invalid address");
+        let rest = self.rest.clone();
+
         thread::spawn(move || {
-            let https = match hyper_tls::HttpsConnector::new(2) {
-                Ok(https) => https,
-                Err(err) => {
-                    let _ = snd.send(Err(err).map_err(From::from));
-                    return;
-                }
-            };
-
-            let client = hyper::Client::builder().build::<_, hyper::Body>(https);
-            let fut = client.get(address).and_then(|res| {
-                let status = res.status();
-                res.into_body().concat2().and_then(move |body| {
-                    Ok((status, body))
-                })
-            }).map_err(From::from).and_then(move |(status, body)| {
+            let fut = rest.request(request).map_err(From::from).and_then(move |(status, body)| {
                 if status != hyper::StatusCode::OK {
                     let binance_error = serde_json::from_slice(&body);
                     Err(
@@ -417,15 +513,18 @@ impl HandlerImpl {
     }
 }
 
-impl wss::HandlerImpl for HandlerImpl {
-    fn on_open(&mut self, out: &ws::Sender) -> ws::Result<()> {
-        out.ping(vec![])
+impl<R: RestTransport> wss::HandlerImpl for HandlerImpl<R> {
+    fn on_open(&mut self, out: &dyn StreamSink) -> Result<(), failure::Error> {
+        out.ping()
     }
 
     fn on_message(&mut self, text: &str, out: &wss::NotifSender) -> Result<(), failure::Error> {
         match self.parse_message(text)? {
             // Depth update notif: behavior depends on the status of the order book snapshot.
-            Some(Notification::LimitUpdates(updates)) => {
+            // Partial depth notifs need no snapshot and are forwarded as is.
+            Some(Notification::LimitUpdates(updates))
+                if !self.flags.contains(NotificationFlags::PARTIAL_ORDER_BOOK) =>
+            {
                 match mem::replace(&mut self.book_snapshot_state, BookSnapshotState::Ok) {
                     // Very first limit update event received: time to ask for the book snapshot.
                     BookSnapshotState::None => self.request_book_snapshot(updates),
@@ -438,20 +537,20 @@ impl wss::HandlerImpl for HandlerImpl {
                         });
 
                         if let Some(notif) = self.maybe_recv_book(state) {
-                            out.unbounded_send(notif).unwrap();
+                            out.send(notif)?;
                         }
                     }
 
                     // We already received the book snapshot and notified the final consumer,
                     // we can now notify further notifications to them.
-                    BookSnapshotState::Ok => out.unbounded_send(
+                    BookSnapshotState::Ok => out.send(
                         Notification::LimitUpdates(updates)
-                    ).unwrap(),
+                    )?,
                 }
             },
 
             // Other notif: just forward to the consumer.
-            Some(notif) => out.unbounded_send(notif).unwrap(),
+            Some(notif) => out.send(notif)?,
 
             None => (),
         }
@@ -0,0 +1,258 @@
+//! A module defining the channel through which exchange clients forward notifications to
+//! the consumer of a stream.
+//!
+//! The channel can be bounded, in which case an `OverflowPolicy` decides what happens
+//! when the consumer does not keep up. Whenever notifications are dropped or conflated,
+//! the consumer receives a `Notification::Overflow` before the remaining notifications.
+
+mod test;
+
+use std::collections::{HashSet, VecDeque};
+use std::sync::{Arc, Mutex, Condvar};
+use futures::prelude::*;
+use futures::task::{self, Task};
+use failure_derive::Fail;
+use serde_derive::{Serialize, Deserialize};
+use crate::api::Notification;
+use crate::api::timestamp::{Timestamp, IntoTimestamped, timestamp_ms};
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+/// What to do when a notification is sent while the channel is full.
+pub enum OverflowPolicy {
+    /// Wait until the consumer makes some room. This stalls the connection, which may
+    /// get closed by the exchange if the consumer is too slow.
+    Block,
+
+    /// Drop the oldest pending notification.
+    DropOldest,
+
+    /// Merge each run of consecutive pending `LimitUpdates` notifications into a single
+    /// one, keeping only the last update of each limit, so that the order book stays
+    /// consistent and other notifications keep their place relative to the book updates.
+    /// If no room can be made that way, drop the oldest pending notification.
+    Conflate,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+/// Settings of the notification channel of a stream.
+pub struct ChannelSettings {
+    /// Maximum number of pending notifications, `None` for an unbounded channel.
+    pub capacity: Option<usize>,
+
+    /// Policy applied when the channel is full.
+    pub policy: OverflowPolicy,
+}
+
+impl Default for ChannelSettings {
+    fn default() -> Self {
+        ChannelSettings {
+            capacity: None,
+            policy: OverflowPolicy::Block,
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Default, Serialize, Deserialize)]
+/// Notifications lost or altered because the consumer did not keep up. If some
+/// notifications were dropped, a local order book maintained from the stream is out of
+/// sync and must be rebuilt.
+pub struct Overflow {
+    /// Number of notifications dropped.
+    pub dropped: u64,
+
+    /// Number of `LimitUpdates` notifications merged into a previous one.
+    pub conflated: u64,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Fail)]
+#[fail(display = "the receiver has been dropped")]
+/// An error indicating that the receiving half of a channel has been dropped.
+pub struct Disconnected;
+
+struct State {
+    queue: VecDeque<Notification>,
+
+    /// Overflow not yet delivered to the consumer, along with the time it started.
+    overflow: Option<(Timestamp, Overflow)>,
+
+    /// Consumer waiting for a notification.
+    task: Option<Task>,
+
+    sender_alive: bool,
+    receiver_alive: bool,
+}
+
+impl State {
+    fn overflow(&mut self) -> &mut Overflow {
+        &mut self.overflow.get_or_insert_with(|| (timestamp_ms(), Overflow::default())).1
+    }
+
+    /// Merge each run of consecutive `LimitUpdates` notifications into a single one, return
+    /// the number of notifications removed that way.
+    fn conflate(&mut self) -> usize {
+        let before = self.queue.len();
+        let mut kept = VecDeque::with_capacity(before);
+
+        // Index of the last notification of `kept` if it results from a merge.
+        let mut merged = Vec::new();
+        for mut notif in self.queue.drain(..) {
+            if let Notification::LimitUpdates(batch) = &mut notif {
+                if let Some(Notification::LimitUpdates(run)) = kept.back_mut() {
+                    run.append(batch);
+                    if merged.last() != Some(&(kept.len() - 1)) {
+                        merged.push(kept.len() - 1);
+                    }
+                    continue;
+                }
+            }
+            kept.push_back(notif);
+        }
+
+        // Only keep the last update of each limit.
+        for index in merged {
+            if let Notification::LimitUpdates(updates) = &mut kept[index] {
+                let mut seen = HashSet::new();
+                let mut conflated: Vec<_> = updates.drain(..)
+                    .rev()
+                    .filter(|update| seen.insert((update.side, update.price)))
+                    .collect();
+                conflated.reverse();
+                *updates = conflated;
+            }
+        }
+
+        self.queue = kept;
+        before - self.queue.len()
+    }
+}
+
+struct Shared {
+    state: Mutex<State>,
+    not_full: Condvar,
+}
+
+/// Sending half of a notification channel.
+pub struct Sender {
+    shared: Arc<Shared>,
+    settings: ChannelSettings,
+}
+
+/// Receiving half of a notification channel, a stream of notifications ending once the
+/// sender is dropped.
+pub struct Receiver {
+    shared: Arc<Shared>,
+}
+
+/// Return a new notification channel with the given `settings`.
+///
+/// # Panics
+/// Panic if the capacity is zero.
+pub fn channel(settings: ChannelSettings) -> (Sender, Receiver) {
+    assert!(settings.capacity != Some(0), "zero capacity");
+
+    let shared = Arc::new(Shared {
+        state: Mutex::new(State {
+            queue: VecDeque::new(),
+            overflow: None,
+            task: None,
+            sender_alive: true,
+            receiver_alive: true,
+        }),
+        not_full: Condvar::new(),
+    });
+
+    let sender = Sender {
+        shared: shared.clone(),
+        settings,
+    };
+    (sender, Receiver { shared })
+}
+
+impl Sender {
+    /// Send `notif` to the receiver, applying the overflow policy if the channel is full.
+    /// Fail if the receiver has been dropped.
+    pub fn send(&self, notif: Notification) -> Result<(), Disconnected> {
+        let mut state = self.shared.state.lock().unwrap();
+
+        let settings = self.settings;
+        if let (Some(capacity), OverflowPolicy::Block) = (settings.capacity, settings.policy) {
+            while state.receiver_alive && state.queue.len() >= capacity {
+                state = self.shared.not_full.wait(state).unwrap();
+            }
+        }
+
+        if !state.receiver_alive {
+            return Err(Disconnected);
+        }
+        state.queue.push_back(notif);
+
+        if let Some(capacity) = self.settings.capacity {
+            if self.settings.policy == OverflowPolicy::Conflate && state.queue.len() > capacity {
+                let conflated = state.conflate();
+                if conflated > 0 {
+                    state.overflow().conflated += conflated as u64;
+                }
+            }
+
+            while state.queue.len() > capacity {
+                state.queue.pop_front();
+                state.overflow().dropped += 1;
+            }
+        }
+
+        if let Some(task) = state.task.take() {
+            task.notify();
+        }
+        Ok(())
+    }
+
+    /// Return whether the receiver has been dropped.
+    pub fn is_closed(&self) -> bool {
+        !self.shared.state.lock().unwrap().receiver_alive
+    }
+}
+
+impl Drop for Sender {
+    fn drop(&mut self) {
+        let mut state = self.shared.state.lock().unwrap();
+        state.sender_alive = false;
+        if let Some(task) = state.task.take() {
+            task.notify();
+        }
+    }
+}
+
+impl Stream for Receiver {
+    type Item = Notification;
+    type Error = ();
+
+    fn poll(&mut self) -> Poll<Option<Notification>, ()> {
+        let mut state = self.shared.state.lock().unwrap();
+
+        if let Some((timestamp, overflow)) = state.overflow.take() {
+            let overflow = overflow.with_local_timestamp(timestamp);
+            return Ok(Async::Ready(Some(Notification::Overflow(overflow))));
+        }
+
+        if let Some(notif) = state.queue.pop_front() {
+            self.shared.not_full.notify_one();
+            return Ok(Async::Ready(Some(notif)));
+        }
+
+        if !state.sender_alive {
+            return Ok(Async::Ready(None));
+        }
+
+        state.task = Some(task::current());
+        Ok(Async::NotReady)
+    }
+}
+
+impl Drop for Receiver {
+    fn drop(&mut self) {
+        let mut state = self.shared.state.lock().unwrap();
+        state.receiver_alive = false;
+        state.queue.clear();
+        self.shared.not_full.notify_all();
+    }
+}
@@ -0,0 +1,85 @@
+#![cfg(test)]
+
+use std::thread;
+use std::time::Duration;
+use futures::prelude::*;
+use crate::Side;
+use crate::api::Notification;
+use crate::api::notifications::{limits, trade};
+use crate::api::channel::{channel, ChannelSettings, OverflowPolicy, Overflow, Disconnected};
+
+fn settings(capacity: usize, policy: OverflowPolicy) -> ChannelSettings {
+    ChannelSettings {
+        capacity: Some(capacity),
+        policy,
+    }
+}
+
+fn overflow(notif: &Notification) -> Overflow {
+    match notif {
+        Notification::Overflow(overflow) => **overflow,
+        other => panic!("unexpected notification {:?}", other),
+    }
+}
+
+#[test]
+fn drop_oldest() {
+    let (snd, rcv) = channel(settings(2, OverflowPolicy::DropOldest));
+    for price in 0..5 {
+        snd.send(trade(0, price, 1, Side::Bid)).unwrap();
+    }
+    drop(snd);
+
+    let notifs: Vec<_> = rcv.wait().map(Result::unwrap).collect();
+    assert_eq!(overflow(&notifs[0]), Overflow { dropped: 3, conflated: 0 });
+    assert_eq!(notifs[1..], [trade(0, 3, 1, Side::Bid), trade(0, 4, 1, Side::Bid)]);
+}
+
+#[test]
+fn conflate() {
+    let (snd, rcv) = channel(settings(3, OverflowPolicy::Conflate));
+    snd.send(limits(0, &[(100, 1, Side::Bid), (101, 1, Side::Ask)])).unwrap();
+    snd.send(trade(0, 100, 1, Side::Bid)).unwrap();
+    snd.send(limits(0, &[(100, 2, Side::Bid)])).unwrap();
+    snd.send(limits(0, &[(101, 0, Side::Ask), (100, 3, Side::Bid)])).unwrap();
+    drop(snd);
+
+    // Only the updates following the trade are merged, the trade keeps its place.
+    let notifs: Vec<_> = rcv.wait().map(Result::unwrap).collect();
+    assert_eq!(overflow(&notifs[0]), Overflow { dropped: 0, conflated: 1 });
+    assert_eq!(
+        notifs[1..],
+        [
+            limits(0, &[(100, 1, Side::Bid), (101, 1, Side::Ask)]),
+            trade(0, 100, 1, Side::Bid),
+            limits(0, &[(101, 0, Side::Ask), (100, 3, Side::Bid)]),
+        ]
+    );
+}
+
+#[test]
+fn block() {
+    let (snd, rcv) = channel(settings(1, OverflowPolicy::Block));
+    let producer = thread::spawn(move || {
+        for price in 0..10 {
+            snd.send(trade(0, price, 1, Side::Bid)).unwrap();
+        }
+    });
+
+    thread::sleep(Duration::from_millis(50));
+    let notifs: Vec<_> = rcv.wait().map(Result::unwrap).collect();
+    producer.join().unwrap();
+    assert_eq!(notifs, (0..10).map(|price| trade(0, price, 1, Side::Bid)).collect::<Vec<_>>());
+}
+
+#[test]
+fn receiver_dropped() {
+    let (snd, rcv) = channel(settings(1, OverflowPolicy::Block));
+    snd.send(trade(0, 0, 1, Side::Bid)).unwrap();
+
+    // The blocked sender is released once the receiver goes away.
+    let producer = thread::spawn(move || snd.send(trade(0, 1, 1, Side::Bid)));
+    thread::sleep(Duration::from_millis(50));
+    drop(rcv);
+    assert_eq!(producer.join().unwrap(), Err(Disconnected));
+}
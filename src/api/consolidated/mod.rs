@@ -0,0 +1,219 @@
+//! A module defining a consolidated order book, merging the order books of the same pair
+//! on several venues.
+//!
+//! Venues may quote with different ticks, so their limits are normalised to a common grid:
+//! bid prices are rounded down and ask prices are rounded up to the grid, sizes are rounded
+//! down, so that the consolidated book never looks better than the venue books.
+
+mod test;
+
+use std::collections::BTreeMap;
+use futures::prelude::*;
+use serde_derive::{Serialize, Deserialize};
+use crate::Side;
+use crate::tick::{Tick, TickUnit};
+use crate::order_book::OrderBook;
+use crate::api::Notification;
+use crate::api::symbol::Symbol;
+use crate::api::order_book::{LiveOrderBook, BookState};
+
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+/// A limit of the consolidated book.
+pub struct Level {
+    /// Price, in ticks of the common grid.
+    pub price: TickUnit,
+
+    /// Total size, in size ticks of the common grid.
+    pub size: TickUnit,
+
+    /// Size contributed by each venue, in size ticks of the common grid, in the order the
+    /// venues were added.
+    pub venues: Vec<(String, TickUnit)>,
+}
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+/// A cross-venue arbitrage opportunity: the best bid of one venue is above the best ask
+/// of another one. Fees are not taken into account.
+pub struct Arbitrage {
+    /// Venue whose best ask can be bought.
+    pub buy_venue: String,
+
+    /// Best ask of `buy_venue`, in asset units.
+    pub ask_price: f64,
+
+    /// Venue whose best bid can be sold to.
+    pub sell_venue: String,
+
+    /// Best bid of `sell_venue`, in asset units.
+    pub bid_price: f64,
+
+    /// Size available at both limits, in asset units.
+    pub size: f64,
+}
+
+#[derive(Clone, PartialEq, Debug, Default, Serialize, Deserialize)]
+/// A snapshot of a consolidated book.
+pub struct Snapshot {
+    /// Bid limits, best first.
+    pub bid: Vec<Level>,
+
+    /// Ask limits, best first.
+    pub ask: Vec<Level>,
+
+    /// Arbitrage opportunities between the venues.
+    pub arbitrages: Vec<Arbitrage>,
+
+    /// Venues left out because their order book has disconnected.
+    pub disconnected: Vec<String>,
+}
+
+struct Venue {
+    name: String,
+    symbol: Symbol,
+    order_book: LiveOrderBook,
+}
+
+/// Best limit of a venue, as `(price, size)` in venue ticks.
+type Best = Option<(TickUnit, TickUnit)>;
+
+/// A consolidated order book, merging the live order books of several venues.
+pub struct ConsolidatedBook {
+    price_tick: Tick,
+    size_tick: Tick,
+    venues: Vec<Venue>,
+}
+
+/// Convert `value` from `from` ticks to `to` ticks, rounding up if `round_up` and down
+/// otherwise.
+fn convert(value: TickUnit, from: Tick, to: Tick, round_up: bool) -> TickUnit {
+    let num = value as u128 * to.ticks_per_unit() as u128;
+    let den = from.ticks_per_unit() as u128;
+    let converted = if round_up { (num + den - 1) / den } else { num / den };
+    converted as TickUnit
+}
+
+impl ConsolidatedBook {
+    /// Return a new consolidated book without any venue, normalising prices and sizes to
+    /// `price_tick` and `size_tick`.
+    pub fn new(price_tick: Tick, size_tick: Tick) -> Self {
+        ConsolidatedBook {
+            price_tick,
+            size_tick,
+            venues: Vec::new(),
+        }
+    }
+
+    /// Add the venue named `name`, whose order book of `symbol` is `order_book`.
+    pub fn add_order_book(&mut self, name: &str, symbol: Symbol, order_book: LiveOrderBook) {
+        self.venues.push(Venue {
+            name: name.to_owned(),
+            symbol,
+            order_book,
+        });
+    }
+
+    /// Add the venue named `name`, maintaining its order book of `symbol` from `stream`.
+    ///
+    /// # Note
+    /// The call will block until the initial snapshot of the order book has been
+    /// received.
+    pub fn add_stream<S>(&mut self, name: &str, symbol: Symbol, stream: S)
+        where S: Stream<Item = Notification, Error = ()> + Send + 'static
+    {
+        self.add_order_book(name, symbol, LiveOrderBook::from_stream(stream));
+    }
+
+    /// Merge the limits of `order_book` into `levels`.
+    fn merge(
+        &self,
+        levels: &mut BTreeMap<TickUnit, Level>,
+        venue: &Venue,
+        order_book: &OrderBook,
+        side: Side
+    ) {
+        let limits: Box<dyn Iterator<Item = (&TickUnit, &TickUnit)>> = match side {
+            Side::Bid => Box::new(order_book.bid()),
+            Side::Ask => Box::new(order_book.ask()),
+        };
+
+        for (&price, &size) in limits {
+            let symbol = venue.symbol;
+            let price = convert(price, symbol.price_tick(), self.price_tick, side == Side::Ask);
+            let size = convert(size, symbol.size_tick(), self.size_tick, false);
+            if size == 0 {
+                continue;
+            }
+
+            let level = levels.entry(price).or_insert_with(|| Level {
+                price,
+                size: 0,
+                venues: Vec::new(),
+            });
+            level.size += size;
+            match level.venues.iter_mut().find(|(name, _)| *name == venue.name) {
+                Some((_, venue_size)) => *venue_size += size,
+                None => level.venues.push((venue.name.clone(), size)),
+            }
+        }
+    }
+
+    /// Return a snapshot of the consolidated book.
+    pub fn snapshot(&self) -> Snapshot {
+        let mut bid = BTreeMap::new();
+        let mut ask = BTreeMap::new();
+        let mut best = Vec::new();
+        let mut disconnected = Vec::new();
+
+        for venue in &self.venues {
+            match venue.order_book.order_book() {
+                BookState::Live(order_book) => {
+                    self.merge(&mut bid, venue, &order_book, Side::Bid);
+                    self.merge(&mut ask, venue, &order_book, Side::Ask);
+
+                    let best_bid: Best = order_book.bid().next().map(|(&p, &s)| (p, s));
+                    let best_ask: Best = order_book.ask().next().map(|(&p, &s)| (p, s));
+                    best.push((venue, best_bid, best_ask));
+                }
+                BookState::Disconnected => disconnected.push(venue.name.clone()),
+            }
+        }
+
+        Snapshot {
+            bid: bid.into_iter().rev().map(|(_, level)| level).collect(),
+            ask: ask.into_iter().map(|(_, level)| level).collect(),
+            arbitrages: arbitrages(&best),
+            disconnected,
+        }
+    }
+}
+
+/// Detect the pairs of venues where the best bid of one is above the best ask of the other.
+fn arbitrages(best: &[(&Venue, Best, Best)]) -> Vec<Arbitrage> {
+    let mut arbitrages = Vec::new();
+    for &(seller, best_bid, _) in best {
+        for &(buyer, _, best_ask) in best {
+            let ((bid, bid_size), (ask, ask_size)) = match (best_bid, best_ask) {
+                (Some(bid), Some(ask)) if seller.name != buyer.name => (bid, ask),
+                _ => continue,
+            };
+
+            let (bid_tick, ask_tick) = (seller.symbol.price_tick(), buyer.symbol.price_tick());
+            let crossed = bid as u128 * ask_tick.ticks_per_unit() as u128
+                > ask as u128 * bid_tick.ticks_per_unit() as u128;
+            if !crossed {
+                continue;
+            }
+
+            let bid_size = bid_size as f64 / seller.symbol.size_tick().ticks_per_unit() as f64;
+            let ask_size = ask_size as f64 / buyer.symbol.size_tick().ticks_per_unit() as f64;
+            arbitrages.push(Arbitrage {
+                buy_venue: buyer.name.clone(),
+                ask_price: ask as f64 / ask_tick.ticks_per_unit() as f64,
+                sell_venue: seller.name.clone(),
+                bid_price: bid as f64 / bid_tick.ticks_per_unit() as f64,
+                size: bid_size.min(ask_size),
+            });
+        }
+    }
+    arbitrages
+}
@@ -0,0 +1,73 @@
+#![cfg(test)]
+
+use futures::prelude::*;
+use futures::sync::mpsc::{self, UnboundedSender};
+use crate::Side;
+use crate::tick::Tick;
+use crate::order_book::LimitUpdate;
+use crate::api::Notification;
+use crate::api::symbol::Symbol;
+use crate::api::timestamp::IntoTimestamped;
+use crate::api::consolidated::{ConsolidatedBook, Level, Arbitrage};
+
+/// Add a venue whose order book is stuck on the given limits.
+fn add_venue(
+    book: &mut ConsolidatedBook,
+    name: &str,
+    symbol: Symbol,
+    limits: &[(u64, u64, Side)]
+) -> UnboundedSender<Notification>
+{
+    let (snd, rcv) = mpsc::unbounded();
+    let updates = limits.iter()
+        .map(|&(price, size, side)| LimitUpdate::new(price, size, side).with_timestamp(0))
+        .collect();
+    let stream = futures::stream::iter_ok(vec![Notification::LimitUpdates(updates)]).chain(rcv);
+    book.add_stream(name, symbol, stream);
+    snd
+}
+
+fn level(price: u64, venues: &[(&str, u64)]) -> Level {
+    Level {
+        price,
+        size: venues.iter().map(|(_, size)| size).sum(),
+        venues: venues.iter().map(|&(name, size)| (name.to_owned(), size)).collect(),
+    }
+}
+
+#[test]
+fn consolidated_book() {
+    let mut book = ConsolidatedBook::new(Tick::new(10), Tick::new(100));
+    let symbol_a = Symbol::new("BTCUSDT", Tick::new(100), Tick::new(1000)).unwrap();
+    let symbol_b = Symbol::new("BTC-USD", Tick::new(10), Tick::new(100)).unwrap();
+
+    let _a = add_venue(&mut book, "a", symbol_a, &[
+        (10_005, 1_500, Side::Bid),
+        (9_990, 1_000, Side::Bid),
+        (10_015, 500, Side::Ask),
+    ]);
+    let _b = add_venue(&mut book, "b", symbol_b, &[
+        (1_003, 200, Side::Bid),
+        (1_000, 100, Side::Bid),
+        (1_004, 300, Side::Ask),
+    ]);
+
+    let snapshot = book.snapshot();
+    assert_eq!(snapshot.bid, vec![
+        level(1_003, &[("b", 200)]),
+        level(1_000, &[("a", 150), ("b", 100)]),
+        level(999, &[("a", 100)]),
+    ]);
+
+    // Asks are rounded up to the grid.
+    assert_eq!(snapshot.ask, vec![level(1_002, &[("a", 50)]), level(1_004, &[("b", 300)])]);
+
+    assert_eq!(snapshot.arbitrages, vec![Arbitrage {
+        buy_venue: "a".to_owned(),
+        ask_price: 100.15,
+        sell_venue: "b".to_owned(),
+        bid_price: 100.3,
+        size: 0.5,
+    }]);
+    assert!(snapshot.disconnected.is_empty());
+}
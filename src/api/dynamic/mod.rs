@@ -0,0 +1,251 @@
+//! A module defining an object-safe version of `ApiClient`, and a registry building clients
+//! from an exchange name chosen at runtime.
+//!
+//! `ApiClient` has an associated stream type and generates order ids through a static method,
+//! so it cannot be used as a trait object. `DynApiClient` is implemented by every
+//! `ApiClient`, boxes the stream and generates order ids through a method:
+//!
+//! ```no_run
+//! use trade::api::{binance, Order};
+//! use trade::api::dynamic::Registry;
+//! use trade::Side;
+//!
+//! # fn main() -> Result<(), failure::Error> {
+//! let params = binance::params::production();
+//! let client = Registry::default().build("binance", params, None)?;
+//!
+//! let order = Order::new("100.00", "1", Side::Bid).with_dyn_order_id(&*client, "my_order");
+//! # Ok(())
+//! # }
+//! ```
+
+mod test;
+
+use std::collections::BTreeMap;
+use futures::prelude::*;
+use serde::de::DeserializeOwned;
+use failure_derive::Fail;
+use crate::order_book::OrderBook;
+use crate::api::{
+    binance,
+    gdax,
+    hitbtc,
+    errors,
+    ApiClient,
+    Params,
+    Order,
+    OrderAck,
+    Cancel,
+    CancelAck,
+    Notification,
+    NotificationFlags,
+    Balances,
+};
+use crate::api::symbol::{Symbol, WithSymbol};
+use crate::api::timestamp::Timestamped;
+
+/// A boxed notification stream, as returned by `DynApiClient::stream_with_flags`.
+pub type DynStream = Box<dyn Stream<Item = Notification, Error = ()> + Send + 'static>;
+
+/// An object-safe version of `ApiClient`, implemented by all `ApiClient` implementors. See
+/// the methods of `ApiClient` for the documentation.
+pub trait DynApiClient: Send + Sync {
+    /// Find a symbol by name.
+    fn find_symbol(&self, symbol: &str) -> Option<Symbol>;
+
+    /// Start streaming notifications, only forward those indicated by `flags`.
+    fn stream_with_flags(&self, symbol: Symbol, flags: NotificationFlags) -> DynStream;
+
+    /// Start streaming notifications.
+    fn stream(&self, symbol: Symbol) -> DynStream {
+        self.stream_with_flags(symbol, NotificationFlags::ALL)
+    }
+
+    /// Send an order to the exchange.
+    fn order(&self, order: WithSymbol<&Order>)
+        -> Box<dyn Future<Item = Timestamped<OrderAck>, Error = errors::OrderError> + Send + 'static>;
+
+    /// Send a cancel order to the exchange.
+    fn cancel(&self, cancel: WithSymbol<&Cancel>)
+        -> Box<dyn Future<Item = Timestamped<CancelAck>, Error = errors::CancelError> + Send + 'static>;
+
+    /// Send a ping to the exchange.
+    fn ping(&self)
+        -> Box<dyn Future<Item = Timestamped<()>, Error = errors::Error> + Send + 'static>;
+
+    /// Retrieve a snapshot of the order book through the REST API.
+    fn order_book(&self, symbol: Symbol)
+        -> Box<dyn Future<Item = Timestamped<OrderBook>, Error = errors::Error> + Send + 'static>;
+
+    /// Retrieve balances for this account.
+    fn balances(&self)
+        -> Box<dyn Future<Item = Balances, Error = errors::Error> + Send + 'static>;
+
+    /// Use `hint` for generating an order id, see `GenerateOrderId::new_order_id`.
+    fn new_order_id(&self, hint: &str) -> String;
+}
+
+impl<C: ApiClient + Send + Sync> DynApiClient for C {
+    fn find_symbol(&self, symbol: &str) -> Option<Symbol> {
+        ApiClient::find_symbol(self, symbol)
+    }
+
+    fn stream_with_flags(&self, symbol: Symbol, flags: NotificationFlags) -> DynStream {
+        Box::new(ApiClient::stream_with_flags(self, symbol, flags))
+    }
+
+    fn order(&self, order: WithSymbol<&Order>)
+        -> Box<dyn Future<Item = Timestamped<OrderAck>, Error = errors::OrderError> + Send + 'static>
+    {
+        ApiClient::order(self, order)
+    }
+
+    fn cancel(&self, cancel: WithSymbol<&Cancel>)
+        -> Box<dyn Future<Item = Timestamped<CancelAck>, Error = errors::CancelError> + Send + 'static>
+    {
+        ApiClient::cancel(self, cancel)
+    }
+
+    fn ping(&self)
+        -> Box<dyn Future<Item = Timestamped<()>, Error = errors::Error> + Send + 'static>
+    {
+        ApiClient::ping(self)
+    }
+
+    fn order_book(&self, symbol: Symbol)
+        -> Box<dyn Future<Item = Timestamped<OrderBook>, Error = errors::Error> + Send + 'static>
+    {
+        ApiClient::order_book(self, symbol)
+    }
+
+    fn balances(&self)
+        -> Box<dyn Future<Item = Balances, Error = errors::Error> + Send + 'static>
+    {
+        ApiClient::balances(self)
+    }
+
+    fn new_order_id(&self, hint: &str) -> String {
+        C::new_order_id(hint)
+    }
+}
+
+impl DynApiClient for Box<dyn DynApiClient> {
+    fn find_symbol(&self, symbol: &str) -> Option<Symbol> {
+        (**self).find_symbol(symbol)
+    }
+
+    fn stream_with_flags(&self, symbol: Symbol, flags: NotificationFlags) -> DynStream {
+        (**self).stream_with_flags(symbol, flags)
+    }
+
+    fn order(&self, order: WithSymbol<&Order>)
+        -> Box<dyn Future<Item = Timestamped<OrderAck>, Error = errors::OrderError> + Send + 'static>
+    {
+        (**self).order(order)
+    }
+
+    fn cancel(&self, cancel: WithSymbol<&Cancel>)
+        -> Box<dyn Future<Item = Timestamped<CancelAck>, Error = errors::CancelError> + Send + 'static>
+    {
+        (**self).cancel(cancel)
+    }
+
+    fn ping(&self)
+        -> Box<dyn Future<Item = Timestamped<()>, Error = errors::Error> + Send + 'static>
+    {
+        (**self).ping()
+    }
+
+    fn order_book(&self, symbol: Symbol)
+        -> Box<dyn Future<Item = Timestamped<OrderBook>, Error = errors::Error> + Send + 'static>
+    {
+        (**self).order_book(symbol)
+    }
+
+    fn balances(&self)
+        -> Box<dyn Future<Item = Balances, Error = errors::Error> + Send + 'static>
+    {
+        (**self).balances()
+    }
+
+    fn new_order_id(&self, hint: &str) -> String {
+        (**self).new_order_id(hint)
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Fail)]
+/// An error returned by `Registry::build`.
+pub enum RegistryError {
+    #[fail(display = "unknown exchange `{}`", _0)]
+    /// No exchange was registered under this name.
+    UnknownExchange(String),
+}
+
+type Builder = dyn Fn(Params, Option<serde_json::Value>)
+    -> Result<Box<dyn DynApiClient>, failure::Error> + Send + Sync;
+
+/// A registry of exchanges, building clients from an exchange name, `Params` and a key
+/// configuration given as JSON.
+///
+/// `Registry::default()` knows about `binance`, `gdax` (also registered as `coinbase_pro`)
+/// and `hitbtc`. Supporting another exchange only requires a call to `Registry::register`.
+pub struct Registry {
+    builders: BTreeMap<String, Box<Builder>>,
+}
+
+impl Default for Registry {
+    fn default() -> Self {
+        let mut registry = Registry::new();
+        registry.register("binance", binance::Client::new);
+        registry.register("gdax", gdax::Client::new);
+        registry.register("coinbase_pro", gdax::Client::new);
+        registry.register("hitbtc", hitbtc::Client::new);
+        registry
+    }
+}
+
+impl Registry {
+    /// Return an empty registry, see `Registry::default` for a registry knowing about all
+    /// the supported exchanges.
+    pub fn new() -> Self {
+        Registry {
+            builders: BTreeMap::new(),
+        }
+    }
+
+    /// Register the exchange `name`, whose clients are built by `build`. The key
+    /// configuration is deserialized into a `K` before being passed to `build`, e.g.
+    /// `binance::KeyPair`. An exchange previously registered under the same name is
+    /// replaced.
+    pub fn register<C, K, F>(&mut self, name: &str, build: F)
+        where C: ApiClient + Send + Sync + 'static,
+              K: DeserializeOwned,
+              F: Fn(Params, Option<K>) -> Result<C, failure::Error> + Send + Sync + 'static
+    {
+        let builder = move |params, keys: Option<serde_json::Value>| {
+            let keys = match keys {
+                Some(keys) => Some(serde_json::from_value(keys)?),
+                None => None,
+            };
+            let client: Box<dyn DynApiClient> = Box::new(build(params, keys)?);
+            Ok(client)
+        };
+        self.builders.insert(name.to_owned(), Box::new(builder));
+    }
+
+    /// Names of the registered exchanges, in alphabetical order.
+    pub fn exchanges(&self) -> impl Iterator<Item = &str> {
+        self.builders.keys().map(|name| name.as_ref())
+    }
+
+    /// Build a client of the exchange `name` with the given `params`. If `keys` is not
+    /// `None`, it must deserialize into the key pair type of the exchange.
+    pub fn build(&self, name: &str, params: Params, keys: Option<serde_json::Value>)
+        -> Result<Box<dyn DynApiClient>, failure::Error>
+    {
+        match self.builders.get(name) {
+            Some(build) => build(params, keys),
+            None => Err(RegistryError::UnknownExchange(name.to_owned()).into()),
+        }
+    }
+}
@@ -0,0 +1,56 @@
+#![cfg(test)]
+
+use futures::prelude::*;
+use crate::Side;
+use crate::tick::Tick;
+use crate::api::{Params, Order, Notification};
+use crate::api::symbol::IntoWithSymbol;
+use crate::api::sim::{Exchange, Settings};
+use crate::api::dynamic::{Registry, RegistryError};
+
+fn params() -> Params {
+    Params::new("", "")
+}
+
+#[test]
+fn registry() {
+    let exchange = Exchange::new(Settings::default());
+    exchange.add_symbol("BTCUSD", Tick::new(100), Tick::new(1000)).unwrap();
+
+    let mut registry = Registry::default();
+    registry.register("sim", move |_, _: Option<()>| Ok(exchange.client()));
+    assert_eq!(
+        registry.exchanges().collect::<Vec<_>>(),
+        vec!["binance", "coinbase_pro", "gdax", "hitbtc", "sim"]
+    );
+
+    let err = registry.build("unknown", params(), None).err().unwrap();
+    assert_eq!(
+        err.downcast_ref::<RegistryError>(),
+        Some(&RegistryError::UnknownExchange("unknown".to_owned()))
+    );
+
+    // The key configuration must match the key pair type of the exchange.
+    assert!(registry.build("sim", params(), Some(serde_json::json!({ "key": "" }))).is_err());
+
+    let client = registry.build("sim", params(), None).unwrap();
+    let symbol = client.find_symbol("BTCUSD").unwrap();
+    let stream = client.stream(symbol);
+
+    let order = Order::new("100.00", "1", Side::Bid).with_dyn_order_id(&*client, "order");
+    let ack = client.order(order.with_symbol(symbol)).wait().unwrap();
+    assert_eq!(ack.order_id, "order");
+
+    let confirmation = stream
+        .filter_map(|notif| match notif {
+            Notification::OrderConfirmation(confirmation) => Some(confirmation),
+            _ => None,
+        })
+        .into_future()
+        .wait()
+        .ok()
+        .unwrap()
+        .0
+        .unwrap();
+    assert_eq!(confirmation.order_id, "order");
+}
@@ -70,6 +70,43 @@ pub enum OrderErrorKind {
     #[fail(display = "order would take liquidity")]
     /// The order would take liquidity but was marked as liquidity maker only.
     WouldTakeLiquidity,
+
+    #[fail(display = "rejected by pre-trade risk checks: {}", _0)]
+    /// The order was rejected locally by a `risk::RiskClient`, without being sent.
+    RiskLimit(RiskViolation),
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Fail)]
+/// A pre-trade risk limit breached by an order.
+pub enum RiskViolation {
+    #[fail(display = "kill switch engaged")]
+    /// The kill switch is engaged, no order can be sent.
+    KillSwitch,
+
+    #[fail(display = "order size above limit")]
+    /// The order size is above the maximum order size.
+    OrderSize,
+
+    #[fail(display = "order notional above limit")]
+    /// The order notional is above the maximum notional.
+    Notional,
+
+    #[fail(display = "too many open orders")]
+    /// The maximum number of open orders has been reached.
+    OpenOrders,
+
+    #[fail(display = "position above limit")]
+    /// The order could bring the position on an asset above its maximum.
+    Position,
+
+    #[fail(display = "price outside of the collar")]
+    /// The order price is too far from the mid price of the order book.
+    PriceCollar,
+
+    #[fail(display = "no reference price")]
+    /// The order price cannot be checked against the collar, because the order book
+    /// is unknown, disconnected or has an empty side.
+    NoReferencePrice,
 }
 
 impl private::Sealed for OrderErrorKind { }
@@ -124,7 +161,8 @@ pub struct RequestError {
 }
 
 impl RequestError {
-    crate fn new<E: failure::Fail>(err: E) -> Self {
+    /// Wrap `err` into a `RequestError`.
+    pub fn new<E: failure::Fail>(err: E) -> Self {
         RequestError {
             inner: Box::new(err),
         }
@@ -0,0 +1,388 @@
+//! A module defining execution algorithms, slicing a parent order into child orders sent
+//! through any `ApiClient`.
+//!
+//! An `Execution` is a `Strategy`: it can be run live with `strategy::run`, or in the
+//! backtester. It maintains the order book of the symbol and the state of its child orders
+//! from the notifications it receives, so the stream must forward both the order book and
+//! the orders notifications. Time only advances with the timestamps of the notifications.
+
+mod test;
+
+use std::collections::{HashSet, VecDeque};
+use serde_derive::{Serialize, Deserialize};
+use crate::Side;
+use crate::tick::TickUnit;
+use crate::order_book::OrderBook;
+use crate::api::{ApiClient, Order, Cancel, Notification};
+use crate::api::symbol::{Symbol, IntoWithSymbol};
+use crate::api::timestamp::Timestamp;
+use crate::api::strategy::Strategy;
+use crate::api::order_manager::{OrderManager, OrderState};
+
+#[derive(Copy, Clone, PartialEq, Debug, Serialize, Deserialize)]
+/// Algorithm used for slicing a parent order.
+pub enum Algorithm {
+    /// Only show `show_size` at a time: a new child order is sent once the previous one
+    /// has terminated. Child orders rest at the limit price of the parent order, or join
+    /// the best price of the parent side if there is none.
+    Iceberg {
+        /// Maximum size of each child order.
+        show_size: TickUnit,
+    },
+
+    /// Spread the parent order evenly over `duration` ms, in `slices` child orders. A child
+    /// order still working at the end of its slice is cancelled, and its remaining size is
+    /// carried over to the next slice.
+    Twap {
+        /// Duration of the execution, in ms.
+        duration: u64,
+
+        /// Number of slices.
+        slices: u64,
+    },
+
+    /// Trade a fraction `participation` (between `0` and `1`) of the volume traded by the
+    /// rest of the market since the start of the execution. A trade with the same price and
+    /// size as a fill of a child order, received within `MATCH_WINDOW` ms of it, is
+    /// considered to be that fill and is not counted.
+    Vwap {
+        /// Target fraction of the market volume.
+        participation: f64,
+    },
+}
+
+/// Maximum delay between a fill of a child order and the corresponding trade, in ms.
+pub const MATCH_WINDOW: Timestamp = 1000;
+
+#[derive(Copy, Clone, PartialEq, Debug, Serialize, Deserialize)]
+/// An order to be executed through child orders.
+pub struct ParentOrder {
+    /// Side of the order.
+    pub side: Side,
+
+    /// Total size to execute, in ticks.
+    pub size: TickUnit,
+
+    /// Worst price of the child orders, in ticks, if any.
+    pub limit_price: Option<TickUnit>,
+
+    /// Slicing algorithm.
+    pub algorithm: Algorithm,
+}
+
+#[derive(Copy, Clone, PartialEq, Debug, Default, Serialize, Deserialize)]
+/// Progress of the execution of a parent order.
+pub struct Progress {
+    /// Total size to execute.
+    pub size: TickUnit,
+
+    /// Size executed so far.
+    pub filled: TickUnit,
+
+    /// Remaining size of the child orders not terminated yet.
+    pub working: TickUnit,
+
+    /// Number of child orders sent so far.
+    pub children: usize,
+
+    /// Average execution price, in ticks, or `None` if nothing was executed.
+    pub average_price: Option<f64>,
+
+    /// Whether the execution is over, i.e. the parent order was entirely executed, or it
+    /// was stopped and all the child orders have terminated.
+    pub done: bool,
+}
+
+type Callback = dyn FnMut(&Progress) + Send;
+
+/// A strategy executing a `ParentOrder`.
+pub struct Execution {
+    parent: ParentOrder,
+    symbol: Option<Symbol>,
+    orders: OrderManager,
+    order_book: OrderBook,
+    children: HashSet<String>,
+    filled: TickUnit,
+
+    /// Sum of the execution prices times the executed sizes, in ticks.
+    notional: f64,
+
+    start: Option<Timestamp>,
+    now: Timestamp,
+
+    /// Volume traded by the rest of the market since the start.
+    market_volume: TickUnit,
+
+    /// Fills of the child orders not matched with a trade yet: (time, price, size).
+    own_fills: VecDeque<(Timestamp, TickUnit, TickUnit)>,
+
+    /// Trades counted in `market_volume` not matched with a fill yet: (time, price, size).
+    trades: VecDeque<(Timestamp, TickUnit, TickUnit)>,
+
+    /// Last TWAP slice started.
+    slice: u64,
+
+    stopped: bool,
+    progress: Progress,
+    callback: Option<Box<Callback>>,
+}
+
+impl Execution {
+    /// Return a new `Execution` of `parent`.
+    pub fn new(parent: ParentOrder) -> Self {
+        Execution {
+            parent,
+            symbol: None,
+            orders: OrderManager::new(),
+            order_book: OrderBook::new(),
+            children: HashSet::new(),
+            filled: 0,
+            notional: 0.,
+            start: None,
+            now: 0,
+            market_volume: 0,
+            own_fills: VecDeque::new(),
+            trades: VecDeque::new(),
+            slice: 0,
+            stopped: false,
+            progress: Progress {
+                size: parent.size,
+                ..Default::default()
+            },
+            callback: None,
+        }
+    }
+
+    /// Call `callback` each time the progress changes.
+    pub fn on_progress<F: FnMut(&Progress) + Send + 'static>(&mut self, callback: F) {
+        self.callback = Some(Box::new(callback));
+    }
+
+    /// Parent order being executed.
+    pub fn parent(&self) -> &ParentOrder {
+        &self.parent
+    }
+
+    /// Current progress.
+    pub fn progress(&self) -> Progress {
+        self.progress
+    }
+
+    /// Order manager tracking the child orders.
+    pub fn order_manager(&self) -> &OrderManager {
+        &self.orders
+    }
+
+    /// Stop sending child orders, and cancel the working ones.
+    pub fn stop<C: ApiClient>(&mut self, client: &C) {
+        self.stopped = true;
+        self.cancel_working(client);
+        self.update_progress();
+    }
+
+    /// Child orders not terminated yet.
+    fn working_children(&self) -> Vec<(String, OrderState, TickUnit)> {
+        self.children.iter()
+            .filter_map(|order_id| self.orders.order_by_id(order_id))
+            .filter(|order| !order.state.is_terminal())
+            .map(|order| (order.order_id, order.state, order.remaining))
+            .collect()
+    }
+
+    fn cancel_working<C: ApiClient>(&self, client: &C) {
+        let symbol = match self.symbol {
+            Some(symbol) => symbol,
+            None => return,
+        };
+
+        for (order_id, state, _) in self.working_children() {
+            // Orders not confirmed yet cannot be cancelled.
+            if state == OrderState::PendingNew || state == OrderState::PendingCancel {
+                continue;
+            }
+            let cancel = Cancel::new(order_id);
+            let fut = self.orders.cancel(client, cancel.with_symbol(symbol));
+            tokio::executor::current_thread::spawn(futures::Future::then(fut, |_| Ok(())));
+        }
+    }
+
+    /// Price of the next child order, if one can be computed.
+    fn child_price(&self) -> Option<TickUnit> {
+        let (best_bid, best_ask) = (self.order_book.best_bid(), self.order_book.best_ask());
+        let best_bid = if best_bid > 0 { Some(best_bid) } else { None };
+        let best_ask = if best_ask < TickUnit::max_value() { Some(best_ask) } else { None };
+        let limit = self.parent.limit_price;
+
+        match (self.parent.algorithm, self.parent.side) {
+            (Algorithm::Iceberg { .. }, Side::Bid) => limit.or(best_bid),
+            (Algorithm::Iceberg { .. }, Side::Ask) => limit.or(best_ask),
+
+            // Marketable limit orders, capped by the limit price.
+            (_, Side::Bid) => match (best_ask, limit) {
+                (Some(best), Some(limit)) => Some(best.min(limit)),
+                (best, limit) => best.or(limit),
+            },
+            (_, Side::Ask) => match (best_bid, limit) {
+                (Some(best), Some(limit)) => Some(best.max(limit)),
+                (best, limit) => best.or(limit),
+            },
+        }
+    }
+
+    fn send_child<C: ApiClient>(&mut self, client: &C, size: TickUnit) {
+        let (symbol, price) = match (self.symbol, self.child_price()) {
+            (Some(symbol), Some(price)) => (symbol, price),
+            _ => return,
+        };
+
+        let hint = {
+            use uuid::Uuid;
+            Uuid::new_v4().simple().to_string()
+        };
+        let order = Order::new(price, size, self.parent.side).with_order_id::<C>(&hint);
+        self.children.insert(order.order_id().unwrap().to_owned());
+
+        let fut = self.orders.order(client, order.with_symbol(symbol));
+        tokio::executor::current_thread::spawn(futures::Future::then(fut, |_| Ok(())));
+    }
+
+    /// Send or cancel child orders according to the algorithm.
+    fn step<C: ApiClient>(&mut self, client: &C) {
+        if self.stopped || self.filled >= self.parent.size {
+            return;
+        }
+
+        let working = self.working_children();
+        let working_size: TickUnit = working.iter().map(|(_, _, remaining)| remaining).sum();
+        let unsent = self.parent.size.saturating_sub(self.filled + working_size);
+
+        let target = match self.parent.algorithm {
+            Algorithm::Iceberg { show_size } => {
+                if working.is_empty() && unsent > 0 {
+                    self.send_child(client, unsent.min(show_size));
+                }
+                return;
+            }
+
+            Algorithm::Twap { duration, slices } => {
+                let elapsed = self.now - self.start.unwrap_or(self.now);
+                let slice = if duration == 0 {
+                    slices
+                } else {
+                    (elapsed * slices / duration + 1).min(slices)
+                };
+
+                if slice > self.slice {
+                    self.slice = slice;
+                    if !working.is_empty() {
+                        self.cancel_working(client);
+                        return;
+                    }
+                }
+                (self.parent.size as u128 * slice as u128 / slices.max(1) as u128) as TickUnit
+            }
+
+            Algorithm::Vwap { participation } => {
+                (self.market_volume as f64 * participation) as TickUnit
+            }
+        };
+
+        if working.is_empty() {
+            let size = target.saturating_sub(self.filled).min(unsent);
+            if size > 0 {
+                self.send_child(client, size);
+            }
+        }
+    }
+
+    fn update_progress(&mut self) {
+        let working = self.working_children();
+        let progress = Progress {
+            size: self.parent.size,
+            filled: self.filled,
+            working: working.iter().map(|(_, _, remaining)| remaining).sum(),
+            children: self.children.len(),
+            average_price: if self.filled > 0 {
+                Some(self.notional / self.filled as f64)
+            } else {
+                None
+            },
+            done: self.filled >= self.parent.size || (self.stopped && working.is_empty()),
+        };
+
+        if progress != self.progress {
+            self.progress = progress;
+            if let Some(callback) = self.callback.as_mut() {
+                callback(&progress);
+            }
+        }
+    }
+}
+
+/// Remove the entry of `entries` with the given `price` and `size`, if any, after dropping
+/// the entries older than `MATCH_WINDOW`. Return whether an entry was removed.
+fn take_match(
+    entries: &mut VecDeque<(Timestamp, TickUnit, TickUnit)>,
+    now: Timestamp,
+    price: TickUnit,
+    size: TickUnit
+) -> bool
+{
+    entries.retain(|&(time, _, _)| now.saturating_sub(time) <= MATCH_WINDOW);
+    match entries.iter().position(|&(_, p, s)| p == price && s == size) {
+        Some(index) => {
+            entries.remove(index);
+            true
+        }
+        None => false,
+    }
+}
+
+impl<C: ApiClient> Strategy<C> for Execution {
+    fn start(&mut self, _client: &C, symbol: Symbol) {
+        self.symbol = Some(symbol);
+    }
+
+    fn on_notification(&mut self, client: &C, notification: &Notification) {
+        if let Some(timestamp) = notification.timestamp() {
+            self.now = self.now.max(timestamp);
+        }
+
+        self.orders.on_notification(notification);
+        match notification {
+            Notification::LimitUpdates(updates) => {
+                for update in updates {
+                    self.order_book.update(**update);
+                }
+            }
+            Notification::Trade(trade) if self.start.is_some() => {
+                if !take_match(&mut self.own_fills, self.now, trade.price, trade.size) {
+                    self.market_volume += trade.size;
+                    self.trades.push_back((self.now, trade.price, trade.size));
+                }
+            }
+            Notification::OrderUpdate(update) if self.children.contains(&update.order_id) => {
+                let (price, size) = (update.consumed_price, update.consumed_size);
+                self.filled += size;
+                self.notional += price as f64 * size as f64;
+
+                // The trade of this fill may have been received first.
+                if take_match(&mut self.trades, self.now, price, size) {
+                    self.market_volume -= size;
+                } else if size > 0 {
+                    self.own_fills.push_back((self.now, price, size));
+                }
+            }
+            _ => (),
+        }
+
+        // Wait for the order book before starting.
+        if self.start.is_none() && self.child_price().is_some() {
+            self.start = Some(self.now);
+        }
+        if self.start.is_some() {
+            self.step(client);
+        }
+        self.update_progress();
+    }
+}
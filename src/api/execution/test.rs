@@ -0,0 +1,84 @@
+#![cfg(test)]
+
+use crate::Side;
+use crate::tick::Tick;
+use crate::api::Notification;
+use crate::api::symbol::Symbol;
+use crate::api::notifications::{limits, trade};
+use crate::api::backtest::{Backtester, Settings};
+use crate::api::execution::{Execution, ParentOrder, Algorithm, Progress};
+
+fn execute(algorithm: Algorithm, data: Vec<Notification>)
+    -> (Progress, Vec<(u64, u64)>)
+{
+    let symbol = Symbol::new("BTCUSD", Tick::new(1), Tick::new(1)).unwrap();
+    let mut execution = Execution::new(ParentOrder {
+        side: Side::Bid,
+        size: 6,
+        limit_price: Some(102),
+        algorithm,
+    });
+    let data = data.into_iter().map(|notif| (notif.timestamp().unwrap(), notif));
+    let report = Backtester::new(symbol, Settings::default()).run(data, &mut execution);
+    let fills = report.fills.iter().map(|fill| (fill.timestamp, fill.size)).collect();
+    (execution.progress(), fills)
+}
+
+#[test]
+fn iceberg() {
+    let data = vec![limits(0, &[(100, 5, Side::Bid), (102, 100, Side::Ask)])];
+    let (progress, fills) = execute(Algorithm::Iceberg { show_size: 4 }, data);
+
+    assert_eq!(fills, vec![(0, 4), (0, 2)]);
+    assert_eq!(progress.filled, 6);
+    assert_eq!(progress.children, 2);
+    assert_eq!(progress.average_price, Some(102.));
+    assert!(progress.done);
+}
+
+#[test]
+fn twap() {
+    let data = vec![
+        limits(0, &[(100, 5, Side::Bid), (102, 100, Side::Ask)]),
+        limits(999, &[(100, 6, Side::Bid)]),
+        limits(1000, &[(100, 5, Side::Bid)]),
+        limits(2500, &[(100, 6, Side::Bid)]),
+    ];
+    let (progress, fills) = execute(Algorithm::Twap { duration: 3000, slices: 3 }, data);
+
+    assert_eq!(fills, vec![(0, 2), (1000, 2), (2500, 2)]);
+    assert_eq!((progress.filled, progress.children), (6, 3));
+    assert!(progress.done);
+}
+
+#[test]
+fn vwap() {
+    let data = vec![
+        limits(0, &[(100, 5, Side::Bid), (102, 100, Side::Ask)]),
+        trade(10, 100, 4, Side::Bid),
+        trade(20, 100, 1, Side::Bid),
+        trade(30, 100, 5, Side::Bid),
+    ];
+    let (progress, fills) = execute(Algorithm::Vwap { participation: 0.5 }, data);
+
+    assert_eq!(fills, vec![(10, 2), (30, 3)]);
+    assert_eq!(progress.filled, 5);
+    assert!(!progress.done);
+}
+
+#[test]
+fn vwap_own_fills() {
+    let data = vec![
+        limits(0, &[(100, 5, Side::Bid), (102, 100, Side::Ask)]),
+        trade(10, 100, 4, Side::Bid),
+
+        // The fill of the first child order, which must not count as market volume.
+        trade(11, 102, 2, Side::Ask),
+        trade(20, 100, 1, Side::Bid),
+        trade(30, 100, 5, Side::Bid),
+    ];
+    let (progress, fills) = execute(Algorithm::Vwap { participation: 0.5 }, data);
+
+    assert_eq!(fills, vec![(10, 2), (30, 3)]);
+    assert_eq!(progress.filled, 5);
+}
@@ -0,0 +1,201 @@
+#![cfg(test)]
+
+//! A fixture-driven test harness for the exchange parsers.
+//!
+//! Fixtures are JSON files stored under `fixtures/<exchange>/<kind>/` at the root of
+//! the repository, so that covering a newly observed message shape only requires
+//! adding a new fixture file. Expected values are matched against actual values
+//! structurally, and keys missing from an expected object are not checked (useful
+//! e.g. for timestamps which are computed locally).
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use futures::prelude::*;
+use serde_json::Value;
+use serde_derive::Deserialize;
+use hyper::StatusCode;
+use crate::tick::Tick;
+use crate::api::{Notification, NotificationFlags};
+use crate::api::symbol::Symbol;
+use crate::api::channel::{channel, ChannelSettings};
+use crate::api::errors::{RestErrorKind, ErrorKinded, OrderErrorKind, CancelErrorKind};
+use crate::api::wss::NotifSender;
+
+/// Return the path and the content of all the fixtures for the given exchange and kind,
+/// sorted by path.
+fn fixtures(exchange: &str, kind: &str) -> Vec<(PathBuf, String)> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures").join(exchange).join(kind);
+    let mut fixtures: Vec<_> = fs::read_dir(&dir)
+        .unwrap_or_else(|err| panic!("cannot read fixture directory {:?}: {}", dir, err))
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().map(|ext| ext == "json").unwrap_or(false))
+        .map(|path| {
+            let content = fs::read_to_string(&path).unwrap();
+            (path, content)
+        })
+        .collect();
+    fixtures.sort();
+    assert!(!fixtures.is_empty(), "no fixture found in {:?}", dir);
+    fixtures
+}
+
+/// Check that `actual` matches `expected`, see module documentation.
+fn assert_matches(expected: &Value, actual: &Value, at: &str) {
+    match (expected, actual) {
+        (Value::Object(expected), Value::Object(actual)) => {
+            for (key, value) in expected {
+                match actual.get(key) {
+                    Some(actual) => assert_matches(value, actual, &format!("{}.{}", at, key)),
+                    None => panic!("missing key `{}` at `{}`", key, at),
+                }
+            }
+        }
+        (Value::Array(expected), Value::Array(actual)) => {
+            assert_eq!(expected.len(), actual.len(), "array lengths differ at `{}`", at);
+            for (i, (expected, actual)) in expected.iter().zip(actual).enumerate() {
+                assert_matches(expected, actual, &format!("{}[{}]", at, i));
+            }
+        }
+        (expected, actual) => {
+            assert_eq!(expected, actual, "values differ at `{}`", at);
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct SymbolFixture {
+    name: String,
+    price_tick: u64,
+    size_tick: u64,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct StreamFixture {
+    symbol: SymbolFixture,
+    #[serde(default)]
+    flags: Option<Vec<String>>,
+    messages: Vec<Value>,
+    notifications: Vec<Value>,
+}
+
+fn parse_flags(flags: &Option<Vec<String>>) -> NotificationFlags {
+    let flags = match flags {
+        Some(flags) => flags,
+        None => return NotificationFlags::ALL,
+    };
+
+    flags.iter().fold(NotificationFlags::empty(), |acc, flag| {
+        acc | match flag.as_ref() {
+            "ORDER_BOOK" => NotificationFlags::ORDER_BOOK,
+            "TRADES" => NotificationFlags::TRADES,
+            "ORDERS" => NotificationFlags::ORDERS,
+            "ALL" => NotificationFlags::ALL,
+            "PARTIAL_ORDER_BOOK" => NotificationFlags::PARTIAL_ORDER_BOOK,
+            "BEST_BID_OFFER" => NotificationFlags::BEST_BID_OFFER,
+            other => panic!("unknown notification flag `{}`", other),
+        }
+    })
+}
+
+/// Replay all the stream fixtures of `exchange`: a new handler is built for each
+/// fixture with `new_handler`, then each message is fed to `parse` and the
+/// resulting notifications are checked against the expected ones.
+///
+/// A stream fixture has the following shape:
+/// ```json
+/// {
+///     "symbol": { "name": "BTCUSDT", "price_tick": 100, "size_tick": 1000000 },
+///     "flags": ["TRADES"],
+///     "messages": [ ...raw messages... ],
+///     "notifications": [ ...expected notifications, serialized... ]
+/// }
+/// ```
+/// `flags` is optional and defaults to `NotificationFlags::ALL`.
+crate fn replay_stream_fixtures<H, F, P>(exchange: &str, mut new_handler: F, mut parse: P)
+    where F: FnMut(Symbol, NotificationFlags) -> H,
+          P: FnMut(&mut H, &str, &NotifSender) -> Result<(), failure::Error>
+{
+    for (path, content) in fixtures(exchange, "stream") {
+        let fixture: StreamFixture = serde_json::from_str(&content)
+            .unwrap_or_else(|err| panic!("invalid fixture {:?}: {}", path, err));
+
+        let symbol = Symbol::new(
+            &fixture.symbol.name,
+            Tick::new(fixture.symbol.price_tick),
+            Tick::new(fixture.symbol.size_tick)
+        ).unwrap();
+
+        let mut handler = new_handler(symbol, parse_flags(&fixture.flags));
+        let (snd, rcv) = channel(ChannelSettings::default());
+        let snd = NotifSender::new(snd);
+        for message in &fixture.messages {
+            let message = serde_json::to_string(message).unwrap();
+            if let Err(err) = parse(&mut handler, &message, &snd) {
+                panic!("{:?}: failed to parse `{}`: {}", path, message, err);
+            }
+        }
+        drop(snd);
+
+        let notifications: Vec<Notification> = rcv.wait().map(|notif| notif.unwrap()).collect();
+        let actual = serde_json::to_value(&notifications).unwrap();
+        let expected = Value::Array(fixture.notifications);
+        assert_matches(&expected, &actual, &format!("{}", path.display()));
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct RestErrorFixture {
+    status: u16,
+    body: Value,
+    kind: Option<String>,
+    order_kind: Option<String>,
+    cancel_kind: Option<String>,
+}
+
+/// Replay all the REST error fixtures of `exchange`: each response is converted to an
+/// exchange specific error with `to_error`, then the error kinds it maps to are checked
+/// against the expected ones.
+///
+/// A REST error fixture has the following shape:
+/// ```json
+/// {
+///     "status": 400,
+///     "body": { ...response body... },
+///     "kind": "InvalidRequest",
+///     "order_kind": "Specific(InsufficientBalance)",
+///     "cancel_kind": "InvalidRequest"
+/// }
+/// ```
+/// where each of `kind`, `order_kind` and `cancel_kind` is optional and is compared
+/// with the `Debug` representation of the corresponding `RestErrorKind`.
+crate fn replay_rest_error_fixtures<E, F>(exchange: &str, mut to_error: F)
+    where F: FnMut(StatusCode, &[u8]) -> E,
+          E: ErrorKinded<!> + ErrorKinded<OrderErrorKind> + ErrorKinded<CancelErrorKind>
+{
+    for (path, content) in fixtures(exchange, "rest_errors") {
+        let fixture: RestErrorFixture = serde_json::from_str(&content)
+            .unwrap_or_else(|err| panic!("invalid fixture {:?}: {}", path, err));
+
+        let status = StatusCode::from_u16(fixture.status).unwrap();
+        let body = match &fixture.body {
+            Value::String(body) => body.clone(),
+            body => serde_json::to_string(body).unwrap(),
+        };
+        let error = to_error(status, body.as_bytes());
+
+        if let Some(expected) = &fixture.kind {
+            let kind: RestErrorKind<!> = ErrorKinded::<!>::kind(&error);
+            assert_eq!(expected, &format!("{:?}", kind), "{:?}", path);
+        }
+
+        if let Some(expected) = &fixture.order_kind {
+            let kind: RestErrorKind<OrderErrorKind> = ErrorKinded::<OrderErrorKind>::kind(&error);
+            assert_eq!(expected, &format!("{:?}", kind), "{:?}", path);
+        }
+
+        if let Some(expected) = &fixture.cancel_kind {
+            let kind: RestErrorKind<CancelErrorKind> = ErrorKinded::<CancelErrorKind>::kind(&error);
+            assert_eq!(expected, &format!("{:?}", kind), "{:?}", path);
+        }
+    }
+}
@@ -1,10 +1,11 @@
 //! Implementation of `ApiClient` for the GDAX API.
 
 pub mod errors;
+pub mod params;
 mod wss;
 mod rest;
+mod test;
 
-use openssl::pkey::{PKey, Private};
 use chashmap::CHashMap;
 use std::collections::HashMap;
 use std::sync::Arc;
@@ -16,7 +17,6 @@ use crate::api::{
     Params,
     ApiClient,
     GenerateOrderId,
-    Notification,
     NotificationFlags,
     Order,
     OrderAck,
@@ -24,15 +24,18 @@ use crate::api::{
     CancelAck,
     Balances
 };
+use crate::order_book::OrderBook;
 use crate::api::symbol::{Symbol, WithSymbol};
 use crate::api::timestamp::{Timestamped, IntoTimestamped};
+use crate::api::keys::{Secret, SecretKey, RequestSigner, KeyProvider};
+use crate::api::transport::{self, RestTransport, StreamingTransport, HttpsTransport, WsTransport};
 
-#[derive(Clone, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 /// A GDAX key pair: api key + secret key, along with a pass phrase.
 pub struct KeyPair {
     api_key: String,
-    secret_key: String,
-    pass_phrase: String,
+    secret_key: SecretKey,
+    pass_phrase: Secret,
 }
 
 impl KeyPair {
@@ -40,21 +43,43 @@ impl KeyPair {
     pub fn new(api_key: String, secret_key: String, pass_phrase: String) -> Self {
         KeyPair {
             api_key,
-            secret_key,
-            pass_phrase,
+            secret_key: secret_key.into(),
+            pass_phrase: pass_phrase.into(),
         }
     }
+
+    /// Return a new key pair whose requests are signed by `signer` instead of a secret key,
+    /// e.g. a `keys::SocketSigner`. The signer must use the base64-decoded secret key.
+    pub fn with_signer(api_key: String, signer: Arc<dyn RequestSigner>, pass_phrase: String)
+        -> Self
+    {
+        KeyPair {
+            api_key,
+            secret_key: SecretKey::Signer(signer),
+            pass_phrase: pass_phrase.into(),
+        }
+    }
+
+    /// Load a key pair from the secrets `api_key`, `secret_key` and `pass_phrase` of
+    /// `provider`.
+    pub fn from_provider(provider: &dyn KeyProvider) -> Result<Self, failure::Error> {
+        Ok(KeyPair {
+            api_key: provider.secret("api_key")?.expose().to_owned(),
+            secret_key: provider.secret("secret_key")?.into(),
+            pass_phrase: provider.secret("pass_phrase")?,
+        })
+    }
 }
 
 #[derive(Clone)]
 struct Keys {
     api_key: String,
-    secret_key: Arc<PKey<Private>>,
-    pass_phrase: String,
+    signer: Arc<dyn RequestSigner>,
+    pass_phrase: Secret,
 }
 
 /// A GDAX API client.
-pub struct Client {
+pub struct Client<R = HttpsTransport, S = WsTransport> {
     params: Params,
     keys: Option<Keys>,
 
@@ -62,7 +87,8 @@ pub struct Client {
     order_ids: Arc<CHashMap<String, String>>,
 
     symbols: HashMap<String, Symbol>,
-    http_client: hyper::Client<hyper_tls::HttpsConnector<hyper::client::HttpConnector>>,
+    rest: R,
+    streaming: S,
 }
 
 impl Client {
@@ -73,29 +99,38 @@ impl Client {
     /// # Note
     /// This method will block, fetching the available symbols from GDAX.
     pub fn new(params: Params, key_pair: Option<KeyPair>) -> Result<Self, failure::Error> {
+        let rest = transport::https(&params.transport)?;
+        let streaming = WsTransport::new(&params.transport);
+        Client::with_transport(params, key_pair, rest, streaming)
+    }
+}
+
+impl<R: RestTransport, S: StreamingTransport> Client<R, S> {
+    /// Create a new GDAX API client with given `params`, performing REST requests
+    /// through `rest` and streaming notifications through `streaming`. See `Client::new`.
+    pub fn with_transport(params: Params, key_pair: Option<KeyPair>, rest: R, streaming: S)
+        -> Result<Self, failure::Error>
+    {
         let keys = match key_pair {
             Some(pair) => {
-                let secret_key = PKey::hmac(&base64::decode(&pair.secret_key)?)?;
+                let signer = pair.secret_key.signer(|key| Ok(base64::decode(key)?))?;
 
                 Some(Keys {
                     api_key: pair.api_key,
-                    secret_key: Arc::new(secret_key),
+                    signer,
                     pass_phrase: pair.pass_phrase,
                 })
             },
             None => None,
         };
 
-        let http_client = hyper::Client::builder().build::<_, hyper::Body>(
-            hyper_tls::HttpsConnector::new(2)?
-        );
-
         let mut client = Client {
             params,
             keys,
             order_ids: Arc::new(CHashMap::new()),
             symbols: HashMap::new(),
-            http_client,
+            rest,
+            streaming,
         };
 
         use tokio::runtime::current_thread;
@@ -108,8 +143,8 @@ impl Client {
     }
 }
 
-impl ApiClient for Client {
-    type Stream = futures::sync::mpsc::UnboundedReceiver<Notification>;
+impl<R: RestTransport, S: StreamingTransport> ApiClient for Client<R, S> {
+    type Stream = crate::api::channel::Receiver;
 
     fn find_symbol(&self, symbol: &str) -> Option<Symbol> {
         self.symbols.get(&symbol.to_lowercase()).cloned()
@@ -137,6 +172,12 @@ impl ApiClient for Client {
         Box::new(Ok(().timestamped()).into_future())
     }
 
+    fn order_book(&self, symbol: Symbol)
+        -> Box<dyn Future<Item = Timestamped<OrderBook>, Error = api::errors::Error> + Send + 'static>
+    {
+        Box::new(self.order_book_impl(symbol))
+    }
+
     fn balances(&self)
         -> Box<dyn Future<Item = Balances, Error = api::errors::Error> + Send + 'static>
     {
@@ -144,7 +185,7 @@ impl ApiClient for Client {
     }
 }
 
-impl GenerateOrderId for Client {
+impl<R, S> GenerateOrderId for Client<R, S> {
     fn new_order_id(_: &str) -> String {
         use uuid::Uuid;
         Uuid::new_v4().to_string()
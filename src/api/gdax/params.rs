@@ -0,0 +1,17 @@
+//! Known endpoints of Coinbase Pro.
+
+use crate::api::Params;
+
+/// Params for the production environment.
+pub fn production() -> Params {
+    Params::new("wss://ws-feed.pro.coinbase.com", "https://api.pro.coinbase.com")
+}
+
+/// Params for the sandbox environment, see https://public.sandbox.pro.coinbase.com. The
+/// sandbox requires its own key pair.
+pub fn sandbox() -> Params {
+    Params::new(
+        "wss://ws-feed-public.sandbox.pro.coinbase.com",
+        "https://api-public.sandbox.pro.coinbase.com"
+    )
+}
@@ -1,12 +1,14 @@
-use openssl::{sign::Signer, hash::MessageDigest};
 use hyper::{Method, Request};
 use futures::prelude::*;
+use futures::future::{self, Either};
 use failure::Fail;
 use log::{warn, debug, error};
 use std::collections::HashMap;
 use serde_derive::{Serialize, Deserialize};
+use serde::de::IgnoredAny;
 use crate::Side;
-use crate::tick::Tick;
+use crate::tick::{Tick, TickUnit};
+use crate::order_book::{OrderBook, LimitUpdate};
 use crate::api::{
     self,
     TimeInForce,
@@ -21,6 +23,7 @@ use crate::api::{
 use crate::api::errors::ErrorKinded;
 use crate::api::symbol::{Symbol, WithSymbol};
 use crate::api::timestamp::{convert_str_timestamp, timestamp_ms, Timestamped, IntoTimestamped};
+use crate::api::transport::{RestTransport, StreamingTransport};
 use crate::api::gdax::Client;
 use crate::api::gdax::errors::RestError;
 
@@ -51,6 +54,14 @@ struct GdaxAccount<'a> {
     hold: &'a str,
 }
 
+#[derive(Debug, Deserialize)]
+struct GdaxBook<'a> {
+    #[serde(borrow)]
+    bids: Vec<(&'a str, &'a str, IgnoredAny)>,
+    #[serde(borrow)]
+    asks: Vec<(&'a str, &'a str, IgnoredAny)>,
+}
+
 #[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Deserialize)]
 struct GdaxProduct<'a> {
     id: &'a str,
@@ -87,7 +98,7 @@ impl AsStr for TimeInForce {
     }
 }
 
-impl Client {
+impl<R: RestTransport, S: StreamingTransport> Client<R, S> {
     fn request<K: api::errors::ErrorKind>(
         &self,
         path: &str,
@@ -106,31 +117,28 @@ impl Client {
 
         if let Some(keys) = self.keys.as_ref() {
             let timestamp = timestamp_ms() as f64 / 1000.;
-            let mut signer = Signer::new(MessageDigest::sha256(), &keys.secret_key).unwrap();
             let what = format!("{}{}/{}{}", timestamp, method, path, body);
-            signer.update(what.as_bytes()).unwrap();
-            let signature = base64::encode(&signer.sign_to_vec().unwrap());
+            let signature = match keys.signer.sign(what.as_bytes()) {
+                Ok(signature) => base64::encode(&signature),
+                Err(err) => {
+                    let err = api::errors::RequestError::new(err.compat());
+                    return Either::A(future::err(api::errors::ApiError::RequestError(err)));
+                }
+            };
 
             request.header("CB-ACCESS-KEY", keys.api_key.as_bytes())
                 .header("CB-ACCESS-SIGN", signature.as_bytes())
                 .header("CB-ACCESS-TIMESTAMP", format!("{}", timestamp).as_bytes())
-                .header("CB-ACCESS-PASSPHRASE", keys.pass_phrase.as_bytes());
+                .header("CB-ACCESS-PASSPHRASE", keys.pass_phrase.expose().as_bytes());
         }
 
         request.method(method)
             .uri(&address)
-            .header("User-Agent", &b"hyper"[..])
             .header("Content-Type", &b"application/json"[..]);
         
         // Unwrap because it is a bug if this fails (header failed to parse or something)
         let request = request.body(body.into()).unwrap();
-        self.http_client.request(request).and_then(|res| {
-            let status = res.status();
-            res.into_body().concat2().and_then(move |body| {
-                Ok((status, body))
-            })
-        })
-        .map_err(api::errors::RequestError::new)
+        Either::B(self.rest.request(request)
         .map_err(api::errors::ApiError::RequestError).and_then(|(status, body)| {
             if status != hyper::StatusCode::OK {
                 let gdax_error = serde_json::from_slice(&body);
@@ -141,7 +149,7 @@ impl Client {
                 )?;
             }
             Ok(body)
-        })
+        }))
     }
 
     crate fn order_impl(&self, order: WithSymbol<&Order>)
@@ -251,6 +259,36 @@ impl Client {
         })
     }
 
+    crate fn order_book_impl(&self, symbol: Symbol)
+        -> impl Future<Item = Timestamped<OrderBook>, Error = api::errors::Error> + Send + 'static
+    {
+        let endpoint = format!("products/{}/book?level=2", symbol.name());
+
+        self.request(&endpoint, Method::GET, String::new()).and_then(move |body| {
+            let book: GdaxBook<'_> = serde_json::from_slice(&body)
+                .map_err(api::errors::RequestError::new)
+                .map_err(api::errors::ApiError::RequestError)?;
+
+            let ticked = |value, tick: Tick| -> Result<TickUnit, api::errors::Error> {
+                tick.ticked(value)
+                    .map_err(api::errors::RequestError::new)
+                    .map_err(api::errors::ApiError::RequestError)
+            };
+
+            let mut order_book = OrderBook::new();
+            let bid = book.bids.iter().map(|limit| (limit, Side::Bid));
+            let ask = book.asks.iter().map(|limit| (limit, Side::Ask));
+            for (&(price, size, _), side) in bid.chain(ask) {
+                order_book.update(LimitUpdate::new(
+                    ticked(price, symbol.price_tick())?,
+                    ticked(size, symbol.size_tick())?,
+                    side
+                ));
+            }
+            Ok(order_book.timestamped())
+        })
+    }
+
     crate fn get_symbols(&self)
         -> impl Future<Item = HashMap<String, Symbol>, Error = api::errors::Error> + Send + 'static
     {
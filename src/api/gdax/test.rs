@@ -0,0 +1,208 @@
+#![cfg(test)]
+
+use std::sync::Arc;
+use chashmap::CHashMap;
+use futures::prelude::*;
+use hyper::StatusCode;
+use crate::Side;
+use crate::tick::Tick;
+use crate::order_book::l3::{L3OrderBook, L3Update};
+use crate::api::{Params, Notification, NotificationFlags};
+use crate::api::symbol::Symbol;
+use crate::api::timestamp::Sequence;
+use crate::api::transport::memory::{MemoryRest, RecordedRequest};
+use crate::api::fixtures::{replay_stream_fixtures, replay_rest_error_fixtures};
+use crate::api::wss::NotifSender;
+use crate::api::gdax::wss::HandlerImpl;
+use crate::api::gdax::errors::RestError;
+
+fn params() -> Params {
+    Params::new("ws://mock", "http://mock")
+}
+
+fn respond(request: &RecordedRequest) -> (StatusCode, String) {
+    match request.path.as_ref() {
+        "/products/BTC-USD/book" => (
+            StatusCode::OK,
+            r#"{"sequence":11,"bids":[["6400.00","1.00","a"],["6400.00","0.50","b"]],
+                "asks":[["6401.00","2.00","c"]]}"#.to_owned()
+        ),
+        _ => (StatusCode::NOT_FOUND, String::new()),
+    }
+}
+
+fn handler(symbol: Symbol, flags: NotificationFlags) -> HandlerImpl<MemoryRest> {
+    HandlerImpl::new(
+        symbol,
+        flags,
+        params(),
+        None,
+        Arc::new(CHashMap::new()),
+        MemoryRest::new(respond)
+    )
+}
+
+#[test]
+fn stream_fixtures() {
+    replay_stream_fixtures(
+        "gdax",
+        handler,
+        |handler, json, out| handler.parse_message(json, out)
+    );
+}
+
+#[test]
+fn full_channel() {
+    use std::{thread, time::Duration};
+    use crate::api::channel::{channel, ChannelSettings};
+
+    let symbol = Symbol::new("BTC-USD", Tick::new(100), Tick::new(100000000)).unwrap();
+    let mut handler = handler(symbol, NotificationFlags::ORDER_BOOK_L3);
+    let (snd, rcv) = channel(ChannelSettings::default());
+    let snd = NotifSender::new(snd);
+
+    let messages = [
+        // Already included in the snapshot.
+        r#"{"type":"open","time":"2018-09-03T18:40:00Z","sequence":10,"order_id":"b",
+            "price":"6400.00","remaining_size":"0.50","side":"buy"}"#,
+        r#"{"type":"match","time":"2018-09-03T18:40:01Z","sequence":12,"maker_order_id":"a",
+            "taker_order_id":"t","size":"0.25","price":"6400.00","side":"buy"}"#,
+        r#"{"type":"change","time":"2018-09-03T18:40:02Z","sequence":13,"order_id":"c",
+            "new_size":"1.00","old_size":"2.00","price":"6401.00","side":"sell"}"#,
+        r#"{"type":"received","time":"2018-09-03T18:40:03Z","sequence":14,"order_id":"d",
+            "size":"1.00","price":"6399.00","side":"buy","order_type":"limit"}"#,
+        r#"{"type":"open","time":"2018-09-03T18:40:03Z","sequence":15,"order_id":"d",
+            "price":"6399.00","remaining_size":"1.00","side":"buy"}"#,
+        r#"{"type":"done","time":"2018-09-03T18:40:04Z","sequence":16,"order_id":"b",
+            "price":"6400.00","remaining_size":"0.50","reason":"canceled","side":"buy"}"#,
+    ];
+
+    for (i, message) in messages.iter().enumerate() {
+        handler.parse_message(message, &snd).unwrap();
+        if i == 0 {
+            // Leave some time for the snapshot request to complete.
+            thread::sleep(Duration::from_millis(100));
+        }
+    }
+    drop(snd);
+
+    let notifs: Vec<_> = rcv.wait().map(Result::unwrap).collect();
+    let mut book = L3OrderBook::new();
+    for notif in &notifs {
+        match notif {
+            Notification::L3Updates(updates) => for update in updates {
+                book.update(update);
+            },
+            other => panic!("unexpected notification: {:?}", other),
+        }
+    }
+
+    assert_eq!(notifs.len(), 4);
+    assert_eq!(book.order("a"), Some((Side::Bid, 640000, 75000000)));
+    assert_eq!(book.order("b"), None);
+    assert_eq!(book.order("c"), Some((Side::Ask, 640100, 100000000)));
+    assert_eq!(book.queue_position("d"), Some(0));
+    match &notifs[3] {
+        Notification::L3Updates(updates) => {
+            assert_eq!(*updates[0], L3Update::Remove { order_id: "b".to_owned() });
+            assert_eq!(updates[0].sequence(), Some(Sequence::single(16)));
+        }
+        _ => unreachable!(),
+    }
+}
+
+#[test]
+fn own_orders() {
+    use std::{thread, time::Duration};
+    use crate::api::channel::{channel, ChannelSettings};
+
+    let symbol = Symbol::new("BTC-USD", Tick::new(100), Tick::new(100000000)).unwrap();
+    let flags = NotificationFlags::ORDER_BOOK_L3 | NotificationFlags::ORDERS;
+    let mut handler = handler(symbol, flags);
+    let (snd, rcv) = channel(ChannelSettings::default());
+    let snd = NotifSender::new(snd);
+
+    // Each message is received from both the `full` channel and the `user` channel.
+    let messages = [
+        r#"{"type":"received","time":"2018-09-03T18:40:00Z","sequence":12,"order_id":"o",
+            "size":"1.00","price":"6399.00","side":"buy","order_type":"limit",
+            "client_oid":"mine","profile_id":"p"}"#,
+        r#"{"type":"open","time":"2018-09-03T18:40:00Z","sequence":13,"order_id":"o",
+            "price":"6399.00","remaining_size":"1.00","side":"buy","profile_id":"p"}"#,
+        r#"{"type":"match","time":"2018-09-03T18:40:01Z","sequence":14,"maker_order_id":"o",
+            "taker_order_id":"t","size":"0.25","price":"6399.00","side":"buy",
+            "profile_id":"p"}"#,
+    ];
+
+    for (i, message) in messages.iter().enumerate() {
+        handler.parse_message(message, &snd).unwrap();
+        handler.parse_message(message, &snd).unwrap();
+        if i == 1 {
+            // Leave some time for the snapshot request to complete.
+            thread::sleep(Duration::from_millis(100));
+        }
+    }
+    drop(snd);
+
+    let notifs: Vec<_> = rcv.wait().map(Result::unwrap).collect();
+    assert_eq!(notifs.len(), 3);
+    match &notifs[0] {
+        Notification::OrderConfirmation(order) => assert_eq!(order.order_id, "mine"),
+        other => panic!("unexpected notification: {:?}", other),
+    }
+    match &notifs[1] {
+        Notification::OrderUpdate(update) => assert_eq!(update.remaining_size, 75000000),
+        other => panic!("unexpected notification: {:?}", other),
+    }
+
+    let mut book = L3OrderBook::new();
+    match &notifs[2] {
+        Notification::L3Updates(updates) => for update in updates {
+            book.update(update);
+        },
+        other => panic!("unexpected notification: {:?}", other),
+    }
+    assert_eq!(book.order("o"), Some((Side::Bid, 639900, 75000000)));
+}
+
+#[test]
+fn l3_snapshot_error() {
+    use std::{thread, time::Duration};
+    use crate::api::channel::{channel, ChannelSettings};
+    use crate::api::integrity::IntegrityIssue;
+
+    let symbol = Symbol::new("BTC-USD", Tick::new(100), Tick::new(100000000)).unwrap();
+    let mut handler = HandlerImpl::new(
+        symbol,
+        NotificationFlags::ORDER_BOOK_L3,
+        params(),
+        None,
+        Arc::new(CHashMap::new()),
+        MemoryRest::new(|_| (StatusCode::INTERNAL_SERVER_ERROR, String::new()))
+    );
+    let (snd, rcv) = channel(ChannelSettings::default());
+    let snd = NotifSender::new(snd);
+
+    let message = r#"{"type":"open","time":"2018-09-03T18:40:00Z","sequence":10,
+        "order_id":"b","price":"6400.00","remaining_size":"0.50","side":"buy"}"#;
+    for _ in 0..3 {
+        handler.parse_message(message, &snd).unwrap();
+        thread::sleep(Duration::from_millis(100));
+    }
+    drop(snd);
+
+    // The snapshot is requested again after a failure.
+    let notifs: Vec<_> = rcv.wait().map(Result::unwrap).collect();
+    assert_eq!(notifs.len(), 1);
+    match &notifs[0] {
+        Notification::Integrity(issue) => assert_eq!(**issue, IntegrityIssue::SnapshotFailed),
+        other => panic!("unexpected notification: {:?}", other),
+    }
+}
+
+#[test]
+fn rest_error_fixtures() {
+    replay_rest_error_fixtures("gdax", |status, body| {
+        RestError::from_gdax_error(status, serde_json::from_slice(body).ok())
+    });
+}
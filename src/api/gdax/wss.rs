@@ -1,6 +1,7 @@
-use futures::sync::mpsc::{unbounded, UnboundedReceiver};
-use std::thread;
-use std::collections::HashMap;
+use futures::prelude::*;
+use std::{mem, thread};
+use std::sync::mpsc;
+use std::collections::{HashMap, VecDeque};
 use chashmap::CHashMap;
 use std::sync::Arc;
 use log::{debug, error};
@@ -8,7 +9,10 @@ use failure::bail;
 use serde_derive::{Serialize, Deserialize};
 use crate::{tick, Side};
 use crate::order_book::LimitUpdate;
+use crate::order_book::l3::L3Update;
 use crate::api::{
+    Params,
+    BestBidOffer,
     Notification,
     NotificationFlags,
     OrderConfirmation,
@@ -16,32 +20,45 @@ use crate::api::{
     Trade,
     OrderExpiration,
 };
+use crate::api::integrity::IntegrityIssue;
 use crate::api::symbol::Symbol;
 use crate::api::wss;
-use crate::api::timestamp::{convert_str_timestamp, timestamp_ms, IntoTimestamped};
+use crate::api::channel::{self, Disconnected};
+use crate::api::timestamp::{
+    convert_str_timestamp,
+    timestamp_ms,
+    Timestamped,
+    IntoTimestamped,
+    Sequence,
+};
+use crate::api::transport::{KeepAlive, RestTransport, StreamSink, StreamingTransport};
 use crate::api::gdax::{Keys, Client};
+use crate::api::gdax::errors::RestError;
 
-impl Client {
+impl<R: RestTransport, S: StreamingTransport> Client<R, S> {
     crate fn new_stream(&self, symbol: Symbol, flags: NotificationFlags)
-        -> UnboundedReceiver<Notification>
+        -> channel::Receiver
     {
-        let streaming_endpoint = self.params.streaming_endpoint.clone();
+        let params = self.params.clone();
         let keys = self.keys.clone();
         let order_ids = self.order_ids.clone();
-        let (snd, rcv) = unbounded();
+        let rest = self.rest.clone();
+        let streaming = self.streaming.clone();
+        let (snd, rcv) = channel::channel(self.params.channel);
         thread::spawn(move || {
-            debug!("initiating WebSocket connection at {}", streaming_endpoint);
+            debug!("initiating WebSocket connection at {}", params.streaming_endpoint);
+
+            let streaming_endpoint = params.streaming_endpoint.clone();
+            let handler = wss::Handler::new(
+                snd,
+                HandlerImpl::new(symbol, flags, params, keys, order_ids, rest)
+            );
             
-            if let Err(err) = ws::connect(streaming_endpoint, |out| {
-                wss::Handler::new(out, snd.clone(), wss::KeepAlive::False, HandlerImpl {
-                    symbol,
-                    flags,
-                    state: SubscriptionState::NotSubscribed,
-                    keys: keys.clone(),
-                    orders: HashMap::new(),
-                    order_ids: order_ids.clone(),
-                })
-            })
+            if let Err(err) = streaming.connect(
+                &streaming_endpoint,
+                KeepAlive::False,
+                Box::new(handler)
+            )
             {
                 error!("WebSocket connection terminated with error: `{}`", err);
             }
@@ -57,17 +74,50 @@ enum SubscriptionState {
     Subscribed,
 }
 
-struct HandlerImpl {
+/// Number of sequence numbers of messages about our orders remembered in order to
+/// drop the copies received from the `user` channel.
+const OWN_SEQUENCES_CAPACITY: usize = 256;
+
+type L3BookReceiver = mpsc::Receiver<Result<GdaxL3Snapshot, failure::Error>>;
+
+#[derive(Debug)]
+struct L3BookWaitingState {
+    rcv: L3BookReceiver,
+
+    /// (sequence number, update)
+    events: Vec<(u64, Timestamped<L3Update>)>,
+}
+
+#[derive(Debug)]
+/// State of the level 3 book snapshot request:
+/// * `None`: the request has not been made yet
+/// * `Waiting(state)`: the request has started, in the meantime we buffer the updates
+///   received from the `full` channel
+/// * `Ok(sequence)`: the request was completed already, `sequence` is the sequence number
+///   of the last update forwarded
+enum L3BookSnapshotState {
+    None,
+    Waiting(L3BookWaitingState),
+    Ok(u64),
+}
+
+pub(super) struct HandlerImpl<R> {
     symbol: Symbol,
     flags: NotificationFlags,
+    params: Params,
     state: SubscriptionState,
     keys: Option<Keys>,
+    rest: R,
+    l3_book_snapshot_state: L3BookSnapshotState,
 
     /// server order id => client order
     orders: HashMap<String, OrderConfirmation>,
 
     /// client order id => server order id (shared with `Client`)
     order_ids: Arc<CHashMap<String, String>>,
+
+    /// Sequence numbers of the last messages about our orders.
+    own_sequences: VecDeque<u64>,
 }
 
 #[derive(Clone, PartialEq, Eq, Hash, Debug, Serialize)]
@@ -113,9 +163,41 @@ struct GdaxLimitUpdate<'a> {
     changes: Vec<(&'a str, &'a str, &'a str)>,
 }
 
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Deserialize)]
+struct GdaxL3Snapshot {
+    sequence: u64,
+
+    /// (price, size, order id)
+    bids: Vec<(String, String, String)>,
+    asks: Vec<(String, String, String)>,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Deserialize)]
+struct GdaxOpen<'a> {
+    time: &'a str,
+    sequence: u64,
+    profile_id: Option<&'a str>,
+    order_id: &'a str,
+    price: &'a str,
+    remaining_size: &'a str,
+    side: &'a str,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Deserialize)]
+struct GdaxChange<'a> {
+    time: &'a str,
+    sequence: u64,
+    profile_id: Option<&'a str>,
+    order_id: &'a str,
+
+    /// Only present for limit orders.
+    new_size: Option<&'a str>,
+}
+
 #[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Deserialize)]
 struct GdaxMatch<'a> {
     time: &'a str,
+    sequence: Option<u64>,
     size: &'a str,
     price: &'a str,
     side: &'a str,
@@ -124,9 +206,21 @@ struct GdaxMatch<'a> {
     profile_id: Option<&'a str>,
 }
 
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Deserialize)]
+struct GdaxTicker<'a> {
+    time: Option<&'a str>,
+    sequence: u64,
+    best_bid: &'a str,
+    best_bid_size: &'a str,
+    best_ask: &'a str,
+    best_ask_size: &'a str,
+}
+
 #[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Deserialize)]
 struct GdaxReceived<'a> {
     time: &'a str,
+    sequence: Option<u64>,
+    profile_id: Option<&'a str>,
     client_oid: Option<&'a str>,
     order_id: &'a str,
     size: &'a str,
@@ -139,6 +233,8 @@ struct GdaxDone<'a> {
     reason: &'a str,
     order_id: &'a str,
     time: &'a str,
+    sequence: Option<u64>,
+    profile_id: Option<&'a str>,
 }
 
 #[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Deserialize)]
@@ -153,7 +249,59 @@ struct EventType<'a> {
     type_: &'a str,
 }
 
-impl HandlerImpl {
+impl<R: RestTransport> HandlerImpl<R> {
+    pub(super) fn new(
+        symbol: Symbol,
+        flags: NotificationFlags,
+        params: Params,
+        keys: Option<Keys>,
+        order_ids: Arc<CHashMap<String, String>>,
+        rest: R
+    ) -> Self
+    {
+        HandlerImpl {
+            symbol,
+            flags,
+            params,
+            state: SubscriptionState::NotSubscribed,
+            keys,
+            rest,
+            l3_book_snapshot_state: L3BookSnapshotState::None,
+            orders: HashMap::new(),
+            order_ids,
+            own_sequences: VecDeque::new(),
+        }
+    }
+
+    /// The `full` channel sends the messages about all orders, ours being the ones with
+    /// a `profile_id`.
+    fn is_private(&self, profile_id: Option<&str>) -> bool {
+        profile_id.is_some() || !self.is_l3()
+    }
+
+    fn is_l3(&self) -> bool {
+        self.flags.contains(NotificationFlags::ORDER_BOOK_L3)
+    }
+
+    /// When subscribed to the `full` channel, messages about our orders are received twice,
+    /// with the same sequence number: once from the `full` channel, and once from the `user`
+    /// channel. Return `true` for the second copy.
+    fn is_duplicate(&mut self, sequence: Option<u64>, profile_id: Option<&str>) -> bool {
+        let sequence = match sequence {
+            Some(sequence) if profile_id.is_some() && self.is_l3() => sequence,
+            _ => return false,
+        };
+
+        if self.own_sequences.contains(&sequence) {
+            return true;
+        }
+        if self.own_sequences.len() == OWN_SEQUENCES_CAPACITY {
+            self.own_sequences.pop_front();
+        }
+        self.own_sequences.push_back(sequence);
+        false
+    }
+
     fn convert_gdax_update(&self, l: (&str, &str), side: Side)
         -> Result<LimitUpdate, tick::ConversionError>
     {
@@ -175,7 +323,9 @@ impl HandlerImpl {
         Ok(side)
     }
 
-    fn parse_message(&mut self, json: &str, out: &wss::NotifSender) -> Result<(), failure::Error> {
+    pub(super) fn parse_message(&mut self, json: &str, out: &wss::NotifSender)
+        -> Result<(), failure::Error>
+    {
         let event_type: EventType<'_> = serde_json::from_str(json)?;
 
         match event_type.type_ {
@@ -186,7 +336,11 @@ impl HandlerImpl {
                 self.state = SubscriptionState::Subscribed;
             },
 
-            "snapshot" if self.flags.contains(NotificationFlags::ORDER_BOOK) => {
+            "snapshot"
+                if self.flags.intersects(
+                    NotificationFlags::ORDER_BOOK | NotificationFlags::PARTIAL_ORDER_BOOK
+                ) =>
+            {
                 let snapshot: GdaxBookSnapshot<'_> = serde_json::from_str(json)?;
 
                 let bid = snapshot.bids
@@ -202,10 +356,14 @@ impl HandlerImpl {
                 let notif = Notification::LimitUpdates(
                     bid.chain(ask).collect::<Result<Vec<_>, tick::ConversionError>>()?
                 );
-                out.unbounded_send(notif).unwrap();
+                out.send(notif)?;
             },
 
-            "l2update" if self.flags.contains(NotificationFlags::ORDER_BOOK) => {
+            "l2update"
+                if self.flags.intersects(
+                    NotificationFlags::ORDER_BOOK | NotificationFlags::PARTIAL_ORDER_BOOK
+                ) =>
+            {
                 let update: GdaxLimitUpdate<'_> = serde_json::from_str(json)?;
 
                 let updates = update.changes
@@ -219,14 +377,41 @@ impl HandlerImpl {
                 let updates = updates.collect::<Result<Vec<_>, failure::Error>>()?;
                 if !updates.is_empty() {
                     let notif = Notification::LimitUpdates(updates);
-                    out.unbounded_send(notif).unwrap();
+                    out.send(notif)?;
                 }
             },
 
-            "match"
-                if self.flags.contains(NotificationFlags::TRADES | NotificationFlags::ORDERS) =>
+            "ticker" if self.flags.contains(NotificationFlags::BEST_BID_OFFER) => {
+                let ticker: GdaxTicker<'_> = serde_json::from_str(json)?;
+
+                let bbo = BestBidOffer {
+                    bid_price: self.symbol.price_tick().ticked(ticker.best_bid)?,
+                    bid_size: self.symbol.size_tick().ticked(ticker.best_bid_size)?,
+                    ask_price: self.symbol.price_tick().ticked(ticker.best_ask)?,
+                    ask_size: self.symbol.size_tick().ticked(ticker.best_ask_size)?,
+                };
+
+                // The first ticker message, sent upon subscription, has no time.
+                let bbo = match ticker.time {
+                    Some(time) => bbo.with_timestamp(convert_str_timestamp(time)?),
+                    None => bbo.timestamped(),
+                };
+                let notif = Notification::BestBidOffer(
+                    bbo.with_sequence(Sequence::single(ticker.sequence))
+                );
+                out.send(notif)?;
+            }
+
+            "match" if self.flags.intersects(
+                NotificationFlags::TRADES |
+                NotificationFlags::ORDERS |
+                NotificationFlags::ORDER_BOOK_L3
+            ) =>
             {
                 let trade: GdaxMatch<'_> = serde_json::from_str(json)?;
+                if self.is_duplicate(trade.sequence, trade.profile_id) {
+                    return Ok(());
+                }
                 let timestamp = convert_str_timestamp(trade.time)?;
                 
                 let size = self.symbol.size_tick().ticked(trade.size)?;
@@ -237,7 +422,7 @@ impl HandlerImpl {
                     let update_order = |order: &mut OrderConfirmation| {
                         order.size -= size;
 
-                        out.unbounded_send(
+                        out.send(
                             Notification::OrderUpdate(OrderUpdate {
                                 order_id: order.order_id.clone(),
                                 consumed_size: size,
@@ -245,31 +430,86 @@ impl HandlerImpl {
                                 remaining_size: order.size,
                                 commission: 0,
                             }.with_timestamp(timestamp))
-                        ).unwrap();
+                        )
                     };
 
                     // These two conditions are exclusive.
                     if let Some(order) = self.orders.get_mut(trade.taker_order_id) {
-                        update_order(order);
+                        update_order(order)?;
                     }
                     if let Some(order) = self.orders.get_mut(trade.maker_order_id) {
-                        update_order(order);
+                        update_order(order)?;
                     }
                 }
 
-                if self.flags.contains(NotificationFlags::TRADES) {
-                    out.unbounded_send(
-                        Notification::Trade(Trade {
-                            size,
-                            price,
-                            maker_side: self.convert_gdax_side(trade.side)?,
-                        }.with_timestamp(timestamp))
-                    ).unwrap();
+                // Without the `full` channel, matches about us are received from both the
+                // `matches` channel and the `user` channel.
+                if self.flags.contains(NotificationFlags::TRADES) &&
+                    (trade.profile_id.is_none() || self.is_l3())
+                {
+                    let mut notif = Trade {
+                        size,
+                        price,
+                        maker_side: self.convert_gdax_side(trade.side)?,
+                    }.with_timestamp(timestamp);
+                    if let Some(sequence) = trade.sequence {
+                        notif = notif.with_sequence(Sequence::single(sequence));
+                    }
+                    out.send(Notification::Trade(notif))?;
+                }
+
+                if let (true, Some(sequence)) = (self.is_l3(), trade.sequence) {
+                    let update = L3Update::Reduce {
+                        order_id: trade.maker_order_id.to_owned(),
+                        size,
+                    };
+                    self.on_l3_update(sequence, update.with_timestamp(timestamp), out)?;
+                }
+            },
+
+            "open" if self.flags.contains(NotificationFlags::ORDER_BOOK_L3) => {
+                let open: GdaxOpen<'_> = serde_json::from_str(json)?;
+                if self.is_duplicate(Some(open.sequence), open.profile_id) {
+                    return Ok(());
+                }
+
+                let update = L3Update::Insert {
+                    order_id: open.order_id.to_owned(),
+                    price: self.symbol.price_tick().ticked(open.price)?,
+                    size: self.symbol.size_tick().ticked(open.remaining_size)?,
+                    side: self.convert_gdax_side(open.side)?,
+                };
+                let timestamp = convert_str_timestamp(open.time)?;
+                self.on_l3_update(open.sequence, update.with_timestamp(timestamp), out)?;
+            },
+
+            "change" if self.flags.contains(NotificationFlags::ORDER_BOOK_L3) => {
+                let change: GdaxChange<'_> = serde_json::from_str(json)?;
+                if self.is_duplicate(Some(change.sequence), change.profile_id) {
+                    return Ok(());
                 }
+                let new_size = match change.new_size {
+                    Some(new_size) => new_size,
+
+                    // Market orders never rest on the book.
+                    _ => return Ok(()),
+                };
+
+                let update = L3Update::Resize {
+                    order_id: change.order_id.to_owned(),
+                    size: self.symbol.size_tick().ticked(new_size)?,
+                };
+                let timestamp = convert_str_timestamp(change.time)?;
+                self.on_l3_update(change.sequence, update.with_timestamp(timestamp), out)?;
             },
 
             "received" if self.flags.contains(NotificationFlags::ORDERS) => {
                 let received: GdaxReceived<'_> = serde_json::from_str(json)?;
+                if !self.is_private(received.profile_id) ||
+                    self.is_duplicate(received.sequence, received.profile_id)
+                {
+                    return Ok(());
+                }
                 let timestamp = convert_str_timestamp(received.time)?;
 
                 let size = self.symbol.size_tick().ticked(received.size)?;
@@ -295,16 +535,32 @@ impl HandlerImpl {
 
                 self.orders.insert(received.order_id.to_owned(), order.clone());
 
-                out.unbounded_send(
+                out.send(
                     Notification::OrderConfirmation(order.with_timestamp(timestamp))
-                ).unwrap();
+                )?;
             }
 
-            "done" if self.flags.contains(NotificationFlags::ORDERS) => {
+            "done" if self.flags.intersects(
+                NotificationFlags::ORDERS | NotificationFlags::ORDER_BOOK_L3
+            ) =>
+            {
                 let done: GdaxDone<'_> = serde_json::from_str(json)?;
+                if self.is_duplicate(done.sequence, done.profile_id) {
+                    return Ok(());
+                }
                 let timestamp = convert_str_timestamp(done.time)?;
 
-                if done.reason != "canceled" {
+                if let (true, Some(sequence)) = (self.is_l3(), done.sequence) {
+                    let update = L3Update::Remove {
+                        order_id: done.order_id.to_owned(),
+                    };
+                    self.on_l3_update(sequence, update.with_timestamp(timestamp), out)?;
+                }
+
+                if !self.flags.contains(NotificationFlags::ORDERS) ||
+                    !self.is_private(done.profile_id) ||
+                    done.reason != "canceled"
+                {
                     return Ok(());
                 }
 
@@ -313,11 +569,11 @@ impl HandlerImpl {
                     None => return Ok(()),
                 };
 
-                out.unbounded_send(
+                out.send(
                     Notification::OrderExpiration(OrderExpiration {
                         order_id,
                     }.with_timestamp(timestamp))
-                ).unwrap();
+                )?;
             }
 
             "error" => {
@@ -329,37 +585,190 @@ impl HandlerImpl {
         };
         Ok(())
     }
+
+    /// Forward `update` once the snapshot of the book has been received, buffering it
+    /// in the meantime.
+    fn on_l3_update(
+        &mut self,
+        sequence: u64,
+        update: Timestamped<L3Update>,
+        out: &wss::NotifSender
+    ) -> Result<(), Disconnected>
+    {
+        let update = update.with_sequence(Sequence::single(sequence));
+        let state = mem::replace(&mut self.l3_book_snapshot_state, L3BookSnapshotState::None);
+        match state {
+            L3BookSnapshotState::None => {
+                self.request_l3_book_snapshot(vec![(sequence, update)]);
+            }
+
+            L3BookSnapshotState::Waiting(mut state) => {
+                state.events.push((sequence, update));
+                if let Some(notif) = self.maybe_recv_l3_book(state) {
+                    out.send(notif)?;
+                }
+            }
+
+            L3BookSnapshotState::Ok(last_sequence) => {
+                // Ignore updates already included in the snapshot.
+                if sequence > last_sequence {
+                    out.send(Notification::L3Updates(vec![update]))?;
+                    self.l3_book_snapshot_state = L3BookSnapshotState::Ok(sequence);
+                } else {
+                    self.l3_book_snapshot_state = L3BookSnapshotState::Ok(last_sequence);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn process_l3_book_snapshot(
+        &self,
+        snapshot: GdaxL3Snapshot,
+        buffered_events: Vec<(u64, Timestamped<L3Update>)>
+    ) -> Result<(u64, Notification), failure::Error>
+    {
+        let mut updates = Vec::new();
+        let limits = snapshot.bids.iter().map(|l| (l, Side::Bid))
+            .chain(snapshot.asks.iter().map(|l| (l, Side::Ask)));
+
+        for ((price, size, order_id), side) in limits {
+            updates.push(L3Update::Insert {
+                order_id: order_id.to_owned(),
+                price: self.symbol.price_tick().ticked(price)?,
+                size: self.symbol.size_tick().ticked(size)?,
+                side,
+            }.timestamped().with_sequence(Sequence::single(snapshot.sequence)));
+        }
+
+        let mut last_sequence = snapshot.sequence;
+        for (sequence, update) in buffered_events {
+            if sequence > snapshot.sequence {
+                updates.push(update);
+                last_sequence = sequence;
+            }
+        }
+
+        Ok((last_sequence, Notification::L3Updates(updates)))
+    }
+
+    fn maybe_recv_l3_book(&mut self, state: L3BookWaitingState) -> Option<Notification> {
+        match state.rcv.try_recv() {
+            Ok(book) => {
+                debug!("received L3 book snapshot");
+                match book.and_then(|book| self.process_l3_book_snapshot(book, state.events)) {
+                    Ok((last_sequence, notif)) => {
+                        self.l3_book_snapshot_state = L3BookSnapshotState::Ok(last_sequence);
+                        Some(notif)
+                    },
+                    Err(err) => {
+                        error!("L3 book processing encountered error: `{}`", err);
+                        Some(Self::snapshot_failed())
+                    }
+                }
+            },
+
+            // The snapshot request has not completed yet, we wait some more.
+            Err(mpsc::TryRecvError::Empty) => {
+                self.l3_book_snapshot_state = L3BookSnapshotState::Waiting(state);
+                None
+            },
+
+            // The only `Sender` has somehow disconnected, we won't receive the book.
+            Err(mpsc::TryRecvError::Disconnected) => {
+                error!("L3 book sender has disconnected");
+                Some(Self::snapshot_failed())
+            }
+        }
+    }
+
+    /// The state is left to `None`, so that a new snapshot is requested upon the next
+    /// update.
+    fn snapshot_failed() -> Notification {
+        Notification::Integrity(IntegrityIssue::SnapshotFailed.timestamped())
+    }
+
+    fn request_l3_book_snapshot(&mut self, events: Vec<(u64, Timestamped<L3Update>)>) {
+        let (snd, rcv) = mpsc::sync_channel(1);
+
+        self.l3_book_snapshot_state = L3BookSnapshotState::Waiting(
+            L3BookWaitingState {
+                rcv,
+                events,
+            }
+        );
+
+        let address = format!(
+            "{}/products/{}/book?level=3",
+            self.params.rest_endpoint,
+            self.symbol.name()
+        );
+
+        debug!("initiating L3 book request at `{}`", address);
+
+        let request = hyper::Request::get(address)
+            .body(hyper::Body::empty())
+            .expect("invalid address");
+        let rest = self.rest.clone();
+
+        thread::spawn(move || {
+            let fut = rest.request(request).map_err(From::from).and_then(move |(status, body)| {
+                if status != hyper::StatusCode::OK {
+                    let gdax_error = serde_json::from_slice(&body);
+                    Err(RestError::from_gdax_error(status, gdax_error.ok()))?;
+                }
+
+                let snapshot: GdaxL3Snapshot = serde_json::from_slice(&body)?;
+                Ok(snapshot)
+            }).then(move |res| {
+                let _ = snd.send(res);
+                Ok::<(), !>(())
+            });
+
+            use tokio::runtime::current_thread;
+            current_thread::block_on_all(fut).unwrap();
+        });
+    }
 }
 
-impl wss::HandlerImpl for HandlerImpl {
-    fn on_open(&mut self, out: &ws::Sender) -> ws::Result<()> {
+impl<R: RestTransport> wss::HandlerImpl for HandlerImpl<R> {
+    fn on_open(&mut self, out: &dyn StreamSink) -> Result<(), failure::Error> {
         let product_ids = [self.symbol.name()];
         let mut channels = vec![
             GdaxChannel::Channel("level2"),
-            GdaxChannel::Channel("matches"),
+
+            // The `full` channel also sends the matches.
+            if self.flags.contains(NotificationFlags::ORDER_BOOK_L3) {
+                GdaxChannel::Channel("full")
+            } else {
+                GdaxChannel::Channel("matches")
+            },
             GdaxChannel::WithProducts {
                 name: "heartbeat",
                 product_ids: &product_ids,
             },
         ];
 
-        let auth = self.keys.as_ref().map(|keys| {
-            use openssl::{sign::Signer, hash::MessageDigest};
-
-            let timestamp = timestamp_ms() as f64 / 1000.;
-            let mut signer = Signer::new(MessageDigest::sha256(), &keys.secret_key).unwrap();
-            let what = format!("{}GET/users/self/verify", timestamp);
-            signer.update(what.as_bytes()).unwrap();
-            let signature = base64::encode(&signer.sign_to_vec().unwrap());
-
-            channels.push(GdaxChannel::Channel("user"));
-            GdaxAuth {
-                key: &keys.api_key,
-                signature,
-                timestamp,
-                passphrase: &keys.pass_phrase,
+        if self.flags.contains(NotificationFlags::BEST_BID_OFFER) {
+            channels.push(GdaxChannel::Channel("ticker"));
+        }
+
+        let auth = match self.keys.as_ref() {
+            Some(keys) => {
+                let timestamp = timestamp_ms() as f64 / 1000.;
+                let what = format!("{}GET/users/self/verify", timestamp);
+                let signature = base64::encode(&keys.signer.sign(what.as_bytes())?);
+
+                channels.push(GdaxChannel::Channel("user"));
+                Some(GdaxAuth {
+                    key: &keys.api_key,
+                    signature,
+                    timestamp,
+                    passphrase: keys.pass_phrase.expose(),
+                })
             }
-        });
+            None => None,
+        };
 
         let subscription = GdaxSubscription {
             type_: "subscribe",
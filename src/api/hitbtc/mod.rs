@@ -1,19 +1,21 @@
 //! Implementation of `ApiClient` for the HitBTC API.
 
 pub mod errors;
+pub mod params;
 mod rest;
 mod wss;
+mod test;
 
 use serde_derive::{Serialize, Deserialize};
 use std::collections::HashMap;
 use log::debug;
 use futures::prelude::*;
+use futures::future::Either;
 use crate::api::{
     self,
     Params,
     ApiClient,
     GenerateOrderId,
-    Notification,
     NotificationFlags,
     Order,
     OrderAck,
@@ -21,14 +23,23 @@ use crate::api::{
     CancelAck,
     Balances,
 };
+use crate::order_book::OrderBook;
 use crate::api::symbol::{Symbol, WithSymbol};
 use crate::api::timestamp::{Timestamped, IntoTimestamped};
+use crate::api::keys::{Secret, KeyProvider};
+use crate::api::channel::Receiver;
+use crate::api::top_of_book::TopOfBook;
+use crate::api::transport::{self, RestTransport, StreamingTransport, HttpsTransport, WsTransport};
 
 #[derive(Clone, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
 /// An HitBTC key pair: public key + secret key.
+///
+/// # Note
+/// HitBTC authenticates requests with the secret key itself rather than with a signature,
+/// so the secret key cannot be delegated to a `keys::RequestSigner`.
 pub struct KeyPair {
     public_key: String,
-    secret_key: String,
+    secret_key: Secret,
 }
 
 impl KeyPair {
@@ -36,24 +47,33 @@ impl KeyPair {
     pub fn new(public_key: String, secret_key: String) -> Self {
         KeyPair {
             public_key,
-            secret_key,
+            secret_key: secret_key.into(),
         }
     }
+
+    /// Load a key pair from the secrets `public_key` and `secret_key` of `provider`.
+    pub fn from_provider(provider: &dyn KeyProvider) -> Result<Self, failure::Error> {
+        Ok(KeyPair {
+            public_key: provider.secret("public_key")?.expose().to_owned(),
+            secret_key: provider.secret("secret_key")?,
+        })
+    }
 }
 
 #[derive(Clone, PartialEq, Eq, Hash, Debug)]
 struct Keys {
     public_key: String,
-    secret_key: String,
-    auth_header: String,
+    secret_key: Secret,
+    auth_header: Secret,
 }
 
 /// An HitBTC API client.
-pub struct Client {
+pub struct Client<R = HttpsTransport, S = WsTransport> {
     params: Params,
     keys: Option<Keys>,
     symbols: HashMap<String, Symbol>,
-    http_client: hyper::Client<hyper_tls::HttpsConnector<hyper::client::HttpConnector>>,
+    rest: R,
+    streaming: S,
 }
 
 impl Client {
@@ -64,23 +84,34 @@ impl Client {
     /// # Note
     /// This method will block, fetching the available symbols from HitBTC.
     pub fn new(params: Params, key_pair: Option<KeyPair>) -> Result<Self, failure::Error> {
-        let http_client = hyper::Client::builder().build::<_, hyper::Body>(
-            hyper_tls::HttpsConnector::new(2)?
-        );
+        let rest = transport::https(&params.transport)?;
+        let streaming = WsTransport::new(&params.transport);
+        Client::with_transport(params, key_pair, rest, streaming)
+    }
+}
 
+impl<R: RestTransport, S: StreamingTransport> Client<R, S> {
+    /// Create a new HitBTC API client with given `params`, performing REST requests
+    /// through `rest` and streaming notifications through `streaming`. See `Client::new`.
+    pub fn with_transport(params: Params, key_pair: Option<KeyPair>, rest: R, streaming: S)
+        -> Result<Self, failure::Error>
+    {
         let mut client = Client {
             params,
             keys: key_pair.map(|key_pair| {
-                let pwd = format!("{}:{}", key_pair.public_key, key_pair.secret_key);
-                let pwd = base64::encode(pwd.as_bytes());
+                let pwd = Secret::new(
+                    format!("{}:{}", key_pair.public_key, key_pair.secret_key.expose())
+                );
+                let pwd = Secret::new(base64::encode(pwd.expose().as_bytes()));
                 Keys {
                     public_key: key_pair.public_key,
                     secret_key: key_pair.secret_key,
-                    auth_header: format!("Basic {}", pwd),
+                    auth_header: Secret::new(format!("Basic {}", pwd.expose())),
                 }
             }),
             symbols: HashMap::new(),
-            http_client,
+            rest,
+            streaming,
         };
 
         use tokio::runtime::current_thread;
@@ -93,15 +124,25 @@ impl Client {
     }
 }
 
-impl ApiClient for Client {
-    type Stream = futures::sync::mpsc::UnboundedReceiver<Notification>;
+impl<R: RestTransport, S: StreamingTransport> ApiClient for Client<R, S> {
+    type Stream = Either<Receiver, TopOfBook<Receiver>>;
 
     fn find_symbol(&self, symbol: &str) -> Option<Symbol> {
         self.symbols.get(&symbol.to_lowercase()).cloned()
     }
 
     fn stream_with_flags(&self, symbol: Symbol, flags: NotificationFlags) -> Self::Stream {
-        self.new_stream(symbol, flags)
+        let stream = self.new_stream(symbol, flags);
+
+        // HitBTC has no best bid and offer stream, compute it from the order book.
+        let book = NotificationFlags::ORDER_BOOK | NotificationFlags::PARTIAL_ORDER_BOOK;
+        if !flags.contains(NotificationFlags::BEST_BID_OFFER) {
+            Either::A(stream)
+        } else if flags.intersects(book) {
+            Either::B(TopOfBook::with_limit_updates(stream))
+        } else {
+            Either::B(TopOfBook::new(stream))
+        }
     }
 
     fn order(&self, order: WithSymbol<&Order>)
@@ -122,6 +163,12 @@ impl ApiClient for Client {
         Box::new(Ok(().timestamped()).into_future())
     }
 
+    fn order_book(&self, symbol: Symbol)
+        -> Box<dyn Future<Item = Timestamped<OrderBook>, Error = api::errors::Error> + Send + 'static>
+    {
+        Box::new(self.order_book_impl(symbol))
+    }
+
     fn balances(&self)
         -> Box<dyn Future<Item = Balances, Error = api::errors::Error> + Send + 'static>
     {
@@ -129,7 +176,7 @@ impl ApiClient for Client {
     }
 }
 
-impl GenerateOrderId for Client {
+impl<R, S> GenerateOrderId for Client<R, S> {
     fn new_order_id(hint: &str) -> String {
         hint.to_owned()
     }
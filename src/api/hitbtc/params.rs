@@ -0,0 +1,14 @@
+//! Known endpoints of HitBTC.
+
+use crate::api::Params;
+
+/// Params for the production environment.
+pub fn production() -> Params {
+    Params::new("wss://api.hitbtc.com", "https://api.hitbtc.com")
+}
+
+/// Params for the demo environment, see https://demo.hitbtc.com. The demo environment
+/// requires its own key pair.
+pub fn demo() -> Params {
+    Params::new("wss://api.demo.hitbtc.com", "https://api.demo.hitbtc.com")
+}
@@ -5,7 +5,8 @@ use std::collections::HashMap;
 use hyper::Method;
 use log::error;
 use crate::Side;
-use crate::tick::Tick;
+use crate::tick::{Tick, TickUnit};
+use crate::order_book::{OrderBook, LimitUpdate};
 use crate::api::{
     self,
     OrderType,
@@ -19,6 +20,7 @@ use crate::api::timestamp::{convert_str_timestamp, Timestamped, IntoTimestamped}
 use crate::api::query_string::QueryString;
 use crate::api::errors::ErrorKinded;
 use crate::api::symbol::{Symbol, WithSymbol};
+use crate::api::transport::{RestTransport, StreamingTransport};
 use crate::api::hitbtc::Client;
 use crate::api::hitbtc::errors::RestError;
 
@@ -83,13 +85,28 @@ struct HitBtcBalance<'a> {
     reserved: &'a str,
 }
 
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Deserialize)]
+struct HitBtcLimit<'a> {
+    price: &'a str,
+    size: &'a str,
+}
+
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Deserialize)]
+struct HitBtcOrderBook<'a> {
+    #[serde(borrow)]
+    ask: Vec<HitBtcLimit<'a>>,
+    #[serde(borrow)]
+    bid: Vec<HitBtcLimit<'a>>,
+    timestamp: &'a str,
+}
+
 #[derive(Clone, PartialEq, Eq, Hash, Debug, Deserialize)]
 struct HitBtcError<'a> {
     #[serde(borrow)]
     error: crate::api::hitbtc::errors::HitBtcRestError<'a>,
 }
 
-impl Client {
+impl<R: RestTransport, S: StreamingTransport> Client<R, S> {
     fn request<K: api::errors::ErrorKind>(
         &self,
         endpoint: &str,
@@ -103,7 +120,7 @@ impl Client {
         let mut request = Request::builder();
 
         if let Some(keys) = self.keys.as_ref() {
-            request.header("Authorization", keys.auth_header.as_bytes());
+            request.header("Authorization", keys.auth_header.expose().as_bytes());
         }
 
         let query = query.into_string();
@@ -115,19 +132,12 @@ impl Client {
         );
 
         request.method(method)
-            .header("User-Agent", &b"hyper"[..])
             .header("Content-Type", &b"application/x-www-form-urlencoded"[..])
             .uri(&address);
 
         // Unwrap because it is a bug if this fails (header failed to parse or something)
         let request = request.body(query.into()).unwrap();
-        self.http_client.request(request).and_then(|res| {
-            let status = res.status();
-            res.into_body().concat2().and_then(move |body| {
-                Ok((status, body))
-            })
-        })
-        .map_err(api::errors::RequestError::new)
+        self.rest.request(request)
         .map_err(api::errors::ApiError::RequestError)
         .and_then(|(status, body)| {
             if status != hyper::StatusCode::OK {
@@ -224,6 +234,41 @@ impl Client {
         })
     }
 
+    crate fn order_book_impl(&self, symbol: Symbol)
+        -> impl Future<Item = Timestamped<OrderBook>, Error = api::errors::Error> + Send + 'static
+    {
+        // `limit=0` requests the full book.
+        let endpoint = format!("api/2/public/orderbook/{}?limit=0", symbol.name());
+
+        self.request(&endpoint, Method::GET, QueryString::new()).and_then(move |body| {
+            let book: HitBtcOrderBook<'_> = serde_json::from_slice(&body)
+                .map_err(api::errors::RequestError::new)
+                .map_err(api::errors::ApiError::RequestError)?;
+
+            let timestamp = convert_str_timestamp(book.timestamp)
+                .map_err(api::errors::RequestError::new)
+                .map_err(api::errors::ApiError::RequestError)?;
+
+            let ticked = |value, tick: Tick| -> Result<TickUnit, api::errors::Error> {
+                tick.ticked(value)
+                    .map_err(api::errors::RequestError::new)
+                    .map_err(api::errors::ApiError::RequestError)
+            };
+
+            let mut order_book = OrderBook::new();
+            let bid = book.bid.iter().map(|limit| (limit, Side::Bid));
+            let ask = book.ask.iter().map(|limit| (limit, Side::Ask));
+            for (limit, side) in bid.chain(ask) {
+                order_book.update(LimitUpdate::new(
+                    ticked(limit.price, symbol.price_tick())?,
+                    ticked(limit.size, symbol.size_tick())?,
+                    side
+                ));
+            }
+            Ok(order_book.with_timestamp(timestamp))
+        })
+    }
+
     crate fn get_symbols(&self)
         -> impl Future<Item = HashMap<String, Symbol>, Error = api::errors::Error> + Send + 'static
     {
@@ -0,0 +1,29 @@
+#![cfg(test)]
+
+use serde_derive::Deserialize;
+use crate::api::fixtures::{replay_stream_fixtures, replay_rest_error_fixtures};
+use crate::api::hitbtc::wss::HandlerImpl;
+use crate::api::hitbtc::errors::{RestError, HitBtcRestError};
+
+#[derive(Clone, Debug, Deserialize)]
+struct HitBtcError<'a> {
+    #[serde(borrow)]
+    error: HitBtcRestError<'a>,
+}
+
+#[test]
+fn stream_fixtures() {
+    replay_stream_fixtures(
+        "hitbtc",
+        |symbol, flags| HandlerImpl::new(symbol, flags, None),
+        |handler, json, out| handler.parse_message(json, out)
+    );
+}
+
+#[test]
+fn rest_error_fixtures() {
+    replay_rest_error_fixtures("hitbtc", |status, body| {
+        let error: Option<HitBtcError<'_>> = serde_json::from_slice(body).ok();
+        RestError::from_hit_btc_error(status, error.map(|e| e.error))
+    });
+}
@@ -1,4 +1,3 @@
-use futures::sync::mpsc::{unbounded, UnboundedReceiver};
 use failure::{bail, format_err};
 use std::thread;
 use serde_derive::{Deserialize, Serialize};
@@ -7,6 +6,7 @@ use crate::Side;
 use crate::order_book::LimitUpdate;
 use crate::tick;
 use crate::api::{
+    PARTIAL_DEPTH,
     Notification,
     NotificationFlags,
     Trade,
@@ -15,17 +15,20 @@ use crate::api::{
     OrderUpdate,
 };
 use crate::api::wss;
+use crate::api::channel;
 use crate::api::symbol::Symbol;
-use crate::api::timestamp::{convert_str_timestamp, IntoTimestamped};
+use crate::api::timestamp::{convert_str_timestamp, IntoTimestamped, Sequence};
+use crate::api::transport::{KeepAlive, RestTransport, StreamSink, StreamingTransport};
 use crate::api::hitbtc::{Keys, Client};
 
-impl Client {
+impl<R: RestTransport, S: StreamingTransport> Client<R, S> {
     crate fn new_stream(&self, symbol: Symbol, flags: NotificationFlags)
-        -> UnboundedReceiver<Notification>
+        -> channel::Receiver
     {
         let streaming_endpoint = self.params.streaming_endpoint.clone();
         let keys = self.keys.clone();
-        let (snd, rcv) = unbounded();
+        let streaming = self.streaming.clone();
+        let (snd, rcv) = channel::channel(self.params.channel);
         thread::spawn(move || {
             let address = format!(
                "{}/api/2/ws",
@@ -34,16 +37,9 @@ impl Client {
 
             debug!("initiating WebSocket connection at {}", address);
             
-            if let Err(err) = ws::connect(address, |out| {
-                wss::Handler::new(out, snd.clone(), wss::KeepAlive::False, HandlerImpl {
-                    symbol,
-                    flags,
-                    state: SubscriptionState::new(),
-                    keys: keys.clone(),
-                    last_sequence: None,
-                })
-            })
-            {
+            let handler = wss::Handler::new(snd, HandlerImpl::new(symbol, flags, keys));
+
+            if let Err(err) = streaming.connect(&address, KeepAlive::False, Box::new(handler)) {
                 error!("WebSocket connection terminated with error: `{}`", err);
             }
         });
@@ -71,7 +67,7 @@ impl SubscriptionState {
 
 type SequenceNumber = u64;
 
-struct HandlerImpl {
+pub(super) struct HandlerImpl {
     symbol: Symbol,
     flags: NotificationFlags,
     keys: Option<Keys>,
@@ -85,6 +81,8 @@ struct HandlerImpl {
 #[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Serialize)]
 struct HitBtcSymbol<'a> {
     symbol: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    limit: Option<usize>,
 }
 
 #[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Serialize)]
@@ -137,6 +135,7 @@ struct HitBtcBookUpdate<'a> {
 
 #[derive(Clone, PartialEq, Eq, Hash, Debug, Deserialize)]
 struct HitBtcTradeData<'a> {
+    id: u64,
     price: &'a str,
     quantity: &'a str,
     side: &'a str,
@@ -184,6 +183,16 @@ struct MethodType<'a> {
 }
 
 impl HandlerImpl {
+    pub(super) fn new(symbol: Symbol, flags: NotificationFlags, keys: Option<Keys>) -> Self {
+        HandlerImpl {
+            symbol,
+            flags,
+            state: SubscriptionState::new(),
+            keys,
+            last_sequence: None,
+        }
+    }
+
     fn convert_hit_btc_update(&self, l: HitBtcLimitUpdate<'_>, side: Side)
         -> Result<LimitUpdate, tick::ConversionError>
     {
@@ -205,7 +214,9 @@ impl HandlerImpl {
         Ok(side)
     }
 
-    fn parse_message(&mut self, json: &str, out: &wss::NotifSender) -> Result<(), failure::Error> {
+    pub(super) fn parse_message(&mut self, json: &str, out: &wss::NotifSender)
+        -> Result<(), failure::Error>
+    {
         let method_type: MethodType<'_> = serde_json::from_str(json)?;
 
         let method = match method_type.method {
@@ -214,8 +225,14 @@ impl HandlerImpl {
         };
 
         match method {
+            // Hitbtc has no best bid and offer stream, the client computes it from the full
+            // book.
             "snapshotOrderbook" | "updateOrderbook"
-                if self.flags.contains(NotificationFlags::ORDER_BOOK) =>
+                if self.flags.intersects(
+                    NotificationFlags::ORDER_BOOK |
+                        NotificationFlags::PARTIAL_ORDER_BOOK |
+                        NotificationFlags::BEST_BID_OFFER
+                ) =>
             {
                 let snapshot: HitBtcBookUpdate<'_> = serde_json::from_str(json)?;
 
@@ -225,21 +242,22 @@ impl HandlerImpl {
 
                 self.state.order_book = true;
                 self.last_sequence = Some(snapshot.params.sequence);
+                let sequence = Sequence::single(snapshot.params.sequence);
 
                 let bid = snapshot.params.bid
                     .into_iter()
                     .map(|l| self.convert_hit_btc_update(l, Side::Bid))
-                    .map(|l| Ok(l?.timestamped()));
+                    .map(|l| Ok(l?.timestamped().with_sequence(sequence)));
 
                 let ask = snapshot.params.ask
                     .into_iter()
                     .map(|l| self.convert_hit_btc_update(l, Side::Ask))
-                    .map(|l| Ok(l?.timestamped()));
+                    .map(|l| Ok(l?.timestamped().with_sequence(sequence)));
                 
                 let updates = bid.chain(ask).collect::<Result<Vec<_>, tick::ConversionError>>()?;
                 if !updates.is_empty() {
                     let notif = Notification::LimitUpdates(updates);
-                    out.unbounded_send(notif).unwrap();
+                    out.send(notif)?;
                 }
             }
 
@@ -257,9 +275,9 @@ impl HandlerImpl {
                         size: self.symbol.size_tick().ticked(trade.quantity)?,
                         price: self.symbol.price_tick().ticked(trade.price)?,
                         maker_side: self.convert_hit_btc_side(trade.side)?,
-                    }.with_timestamp(timestamp));
+                    }.with_timestamp(timestamp).with_sequence(Sequence::single(trade.id)));
 
-                    out.unbounded_send(trade).unwrap();
+                    out.send(trade)?;
                 }
             }
 
@@ -279,7 +297,7 @@ impl HandlerImpl {
                             side: self.convert_hit_btc_side(report.params.side)?,
                             order_id: report.params.clientOrderId.to_owned(),
                         }.with_timestamp(timestamp);
-                        out.unbounded_send(Notification::OrderConfirmation(order)).unwrap();
+                        out.send(Notification::OrderConfirmation(order))?;
                     }
 
                     "partiallyFilled" | "filled" => {
@@ -297,14 +315,14 @@ impl HandlerImpl {
                                 - self.symbol.size_tick().ticked(report.params.cumQuantity)?,
                             commission: 0,
                         }.with_timestamp(timestamp);
-                        out.unbounded_send(Notification::OrderUpdate(update)).unwrap();
+                        out.send(Notification::OrderUpdate(update))?;
                     }
 
                     "canceled" | "expired" | "suspended" => {
                         let expiration = OrderExpiration {
                             order_id: report.params.clientOrderId.to_owned(),
                         }.with_timestamp(timestamp);
-                        out.unbounded_send(Notification::OrderExpiration(expiration)).unwrap();
+                        out.send(Notification::OrderExpiration(expiration))?;
                     }
 
                     _ => (),
@@ -318,14 +336,19 @@ impl HandlerImpl {
 }
 
 impl wss::HandlerImpl for HandlerImpl {
-    fn on_open(&mut self, out: &ws::Sender) -> ws::Result<()> {
+    fn on_open(&mut self, out: &dyn StreamSink) -> Result<(), failure::Error> {
+        let partial = self.flags.contains(NotificationFlags::PARTIAL_ORDER_BOOK);
         let params = HitBtcSymbol {
-            symbol: self.symbol.name()
+            symbol: self.symbol.name(),
+            limit: None,
         };
 
         let subscription = HitBtcSubscription {
             method: "subscribeOrderbook",
-            params,
+            params: HitBtcSymbol {
+                limit: if partial { Some(PARTIAL_DEPTH) } else { None },
+                ..params
+            },
         };
         
         match serde_json::to_string(&subscription) {
@@ -353,7 +376,7 @@ impl wss::HandlerImpl for HandlerImpl {
                 params: HitBtcAuthParams {
                     algo: "BASIC",
                     pKey: &keys.public_key,
-                    sKey: &keys.secret_key,
+                    sKey: keys.secret_key.expose(),
                 },
             };
 
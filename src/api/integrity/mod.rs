@@ -0,0 +1,338 @@
+//! A module defining integrity checks of order books: detection of crossed or locked
+//! books, of stale levels, verification of exchange checksums and comparison against
+//! a fresh REST snapshot.
+//!
+//! The checks are run by a `Checker`, a stream adaptor which maintains its own copy of
+//! the order book and inserts a `Notification::Integrity` right after the notification
+//! which revealed an issue.
+
+mod test;
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use futures::prelude::*;
+use futures::sync::mpsc::{unbounded, UnboundedReceiver, UnboundedSender};
+use serde_derive::{Serialize, Deserialize};
+use crate::Side;
+use crate::tick::TickUnit;
+use crate::order_book::{OrderBook, LimitUpdate};
+use crate::api::{self, ApiClient, Notification};
+use crate::api::symbol::Symbol;
+use crate::api::timestamp::{Timestamp, Timestamped, IntoTimestamped};
+
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+/// An integrity issue detected on an order book.
+pub enum IntegrityIssue {
+    /// The best bid is above the best ask.
+    Crossed {
+        /// Best bid price.
+        best_bid: TickUnit,
+
+        /// Best ask price.
+        best_ask: TickUnit,
+    },
+
+    /// The best bid is equal to the best ask.
+    Locked {
+        /// Best bid and ask price.
+        price: TickUnit,
+    },
+
+    /// A limit close to the top of the book has not been updated for a long time,
+    /// which may indicate that an update was missed.
+    StaleLevel {
+        /// Side of the limit.
+        side: Side,
+
+        /// Price of the limit.
+        price: TickUnit,
+
+        /// Time of the last update of the limit.
+        last_update: Timestamp,
+    },
+
+    /// The checksum published by the exchange does not match the book.
+    ChecksumMismatch {
+        /// Checksum published by the exchange.
+        expected: u32,
+
+        /// Checksum computed on the book.
+        computed: u32,
+    },
+
+    /// The book differs from a fresh snapshot. Contains the limit updates to apply to
+    /// the book in order to match the snapshot.
+    Discrepancies(Vec<LimitUpdate>),
+
+    /// The snapshot of the book could not be retrieved: the book is incomplete until a new
+    /// snapshot is received.
+    SnapshotFailed,
+}
+
+/// Return a `Crossed` or `Locked` issue if `order_book` is crossed or locked.
+///
+/// # Complexity
+/// `O(1)`.
+pub fn check_crossed(order_book: &OrderBook) -> Option<IntegrityIssue> {
+    let best_bid = order_book.best_bid();
+    let best_ask = order_book.best_ask();
+
+    if best_bid > best_ask {
+        Some(IntegrityIssue::Crossed { best_bid, best_ask })
+    } else if best_bid == best_ask {
+        Some(IntegrityIssue::Locked { price: best_bid })
+    } else {
+        None
+    }
+}
+
+/// Compare `checksum(order_book)` with the `expected` checksum published by the exchange.
+///
+/// # Note
+/// None of the exchanges currently supported by this crate publishes book checksums:
+/// this is meant for exchanges which do, most of which use a `crc32` over a string
+/// representation of the top levels.
+pub fn verify_checksum<F>(order_book: &OrderBook, expected: u32, checksum: F)
+    -> Option<IntegrityIssue>
+        where F: FnOnce(&OrderBook) -> u32
+{
+    let computed = checksum(order_book);
+    if computed != expected {
+        Some(IntegrityIssue::ChecksumMismatch { expected, computed })
+    } else {
+        None
+    }
+}
+
+/// Compute the CRC-32 (IEEE 802.3) checksum of `data`.
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= u32::from(byte);
+        for _ in 0..8 {
+            let mask = (!(crc & 1)).wrapping_add(1);
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+/// Compare `order_book` with a fresh `snapshot`, and return a `Discrepancies` issue if
+/// they differ.
+///
+/// Since exchanges usually only send the top of the book in REST snapshots, limits of
+/// `order_book` which lie beyond the worst price of the corresponding side of the
+/// snapshot are not compared.
+///
+/// # Complexity
+/// `O(n + m)` where `n` is `order_book`'s length and `m` is `snapshot`'s length.
+pub fn compare(order_book: &OrderBook, snapshot: &OrderBook) -> Option<IntegrityIssue> {
+    let worst_bid = snapshot.bid().last().map(|(price, _)| *price).unwrap_or(0);
+    let worst_ask = snapshot.ask().last()
+        .map(|(price, _)| *price)
+        .unwrap_or(TickUnit::max_value());
+
+    let mut truncated = OrderBook::new();
+    let bid = order_book.bid()
+        .take_while(|(price, _)| **price >= worst_bid)
+        .map(|(price, size)| LimitUpdate::new(*price, *size, Side::Bid));
+    let ask = order_book.ask()
+        .take_while(|(price, _)| **price <= worst_ask)
+        .map(|(price, size)| LimitUpdate::new(*price, *size, Side::Ask));
+    for update in bid.chain(ask) {
+        truncated.update(update);
+    }
+
+    let updates: Vec<_> = truncated.diff(snapshot).collect();
+    if updates.is_empty() {
+        None
+    } else {
+        Some(IntegrityIssue::Discrepancies(updates))
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+/// Settings of a `Checker`.
+pub struct Settings {
+    /// Limits which have not been updated for `stale_after` ms are reported as stale.
+    /// `None` disables the detection of stale levels.
+    pub stale_after: Option<u64>,
+
+    /// Only limits within `stale_depth` ticks of the best price of their side are checked
+    /// for staleness: deep limits legitimately stay untouched for long periods.
+    pub stale_depth: TickUnit,
+}
+
+/// A stream adaptor checking the integrity of the order book maintained from the
+/// `LimitUpdates` notifications of the underlying stream. All notifications are
+/// forwarded, and each detected issue is notified once with a `Notification::Integrity`.
+///
+/// Time is measured with the timestamps of the limit updates, so that checks behave the
+/// same when replaying a recording.
+pub struct Checker<S> {
+    stream: S,
+    settings: Settings,
+    order_book: OrderBook,
+
+    /// (side, price) => time of the last update, for limits present in the book.
+    last_updates: HashMap<(Side, TickUnit), Timestamp>,
+
+    /// Stale limits which were already reported and were not updated since.
+    reported_stale: HashSet<(Side, TickUnit)>,
+
+    /// Whether the book was crossed or locked after the previous limit updates.
+    crossed: bool,
+
+    pending: VecDeque<Notification>,
+    snapshots: UnboundedReceiver<Timestamped<OrderBook>>,
+    snapshots_sender: UnboundedSender<Timestamped<OrderBook>>,
+}
+
+#[derive(Clone)]
+/// A handle for requesting a comparison of the book maintained by a `Checker` with a
+/// fresh REST snapshot.
+pub struct Handle {
+    snapshots: UnboundedSender<Timestamped<OrderBook>>,
+}
+
+impl Handle {
+    /// Fetch a snapshot of the order book through `client`. Once received, the `Checker`
+    /// compares it with its book and notifies a `Discrepancies` issue in case of
+    /// mismatch. The returned future resolves once the snapshot has been received.
+    ///
+    /// # Note
+    /// The live book keeps being updated while the snapshot is in flight, so a transient
+    /// discrepancy can be reported on a fast moving book: only persistent discrepancies
+    /// indicate a corrupted book.
+    pub fn compare<C: ApiClient>(&self, client: &C, symbol: Symbol)
+        -> Box<dyn Future<Item = (), Error = api::errors::Error> + Send + 'static>
+    {
+        let snapshots = self.snapshots.clone();
+        Box::new(client.order_book(symbol).map(move |snapshot| {
+            // The `Checker` may have been dropped in the meantime.
+            let _ = snapshots.unbounded_send(snapshot);
+        }))
+    }
+}
+
+impl<S: Stream<Item = Notification>> Checker<S> {
+    /// Check the order book maintained from `stream` with the given `settings`.
+    pub fn new(stream: S, settings: Settings) -> Self {
+        let (snapshots_sender, snapshots) = unbounded();
+        Checker {
+            stream,
+            settings,
+            order_book: OrderBook::new(),
+            last_updates: HashMap::new(),
+            reported_stale: HashSet::new(),
+            crossed: false,
+            pending: VecDeque::new(),
+            snapshots,
+            snapshots_sender,
+        }
+    }
+
+    /// Return a handle for requesting comparisons with REST snapshots.
+    pub fn handle(&self) -> Handle {
+        Handle {
+            snapshots: self.snapshots_sender.clone(),
+        }
+    }
+
+    /// Order book maintained by `self`.
+    pub fn order_book(&self) -> &OrderBook {
+        &self.order_book
+    }
+
+    fn on_limit_updates(&mut self, updates: &[Timestamped<LimitUpdate>]) {
+        let now = match updates.iter().map(|update| update.timestamp()).max() {
+            Some(now) => now,
+            None => return,
+        };
+
+        for update in updates {
+            self.order_book.update(**update);
+
+            let key = (update.side, update.price);
+            self.reported_stale.remove(&key);
+            if update.size == 0 {
+                self.last_updates.remove(&key);
+            } else {
+                self.last_updates.insert(key, update.timestamp());
+            }
+        }
+
+        match check_crossed(&self.order_book) {
+            Some(issue) => {
+                if !self.crossed {
+                    self.pending.push_back(Notification::Integrity(issue.with_timestamp(now)));
+                }
+                self.crossed = true;
+            }
+            None => self.crossed = false,
+        }
+
+        if let Some(stale_after) = self.settings.stale_after {
+            self.check_stale(now, stale_after);
+        }
+    }
+
+    /// # Complexity
+    /// `O(k)` where `k` is the number of limits within `stale_depth` ticks of the top.
+    fn check_stale(&mut self, now: Timestamp, stale_after: u64) {
+        let depth = self.settings.stale_depth;
+        let best_bid = self.order_book.best_bid();
+        let best_ask = self.order_book.best_ask();
+
+        let bid = self.order_book.bid()
+            .take_while(|(price, _)| best_bid - **price <= depth)
+            .map(|(price, _)| (Side::Bid, *price));
+        let ask = self.order_book.ask()
+            .take_while(|(price, _)| **price - best_ask <= depth)
+            .map(|(price, _)| (Side::Ask, *price));
+
+        for key in bid.chain(ask) {
+            let last_update = match self.last_updates.get(&key) {
+                Some(last_update) => *last_update,
+                None => continue,
+            };
+
+            if now.saturating_sub(last_update) >= stale_after && self.reported_stale.insert(key) {
+                let (side, price) = key;
+                self.pending.push_back(Notification::Integrity(IntegrityIssue::StaleLevel {
+                    side,
+                    price,
+                    last_update,
+                }.with_timestamp(now)));
+            }
+        }
+    }
+}
+
+impl<S: Stream<Item = Notification>> Stream for Checker<S> {
+    type Item = Notification;
+    type Error = S::Error;
+
+    fn poll(&mut self) -> Poll<Option<Notification>, S::Error> {
+        // Cannot fail: `self` holds a sender.
+        while let Ok(Async::Ready(Some(snapshot))) = self.snapshots.poll() {
+            if let Some(issue) = compare(&self.order_book, &snapshot) {
+                let timestamp = snapshot.timestamp();
+                self.pending.push_back(Notification::Integrity(issue.with_timestamp(timestamp)));
+            }
+        }
+
+        if let Some(notif) = self.pending.pop_front() {
+            return Ok(Async::Ready(Some(notif)));
+        }
+
+        let notif = match futures::try_ready!(self.stream.poll()) {
+            Some(notif) => notif,
+            None => return Ok(Async::Ready(None)),
+        };
+
+        if let Notification::LimitUpdates(updates) = &notif {
+            self.on_limit_updates(updates);
+        }
+        Ok(Async::Ready(Some(notif)))
+    }
+}
@@ -0,0 +1,93 @@
+#![cfg(test)]
+
+use futures::prelude::*;
+use crate::Side;
+use crate::order_book::{OrderBook, LimitUpdate};
+use crate::api::Notification;
+use crate::api::notifications::limits;
+use crate::api::integrity::{self, Checker, Settings, IntegrityIssue};
+
+fn issues(notifications: Vec<Notification>, settings: Settings) -> Vec<IntegrityIssue> {
+    Checker::new(futures::stream::iter_ok::<_, ()>(notifications), settings)
+        .filter_map(|notif| match notif {
+            Notification::Integrity(issue) => Some(issue.into_inner()),
+            _ => None,
+        })
+        .collect()
+        .wait()
+        .unwrap()
+}
+
+#[test]
+fn crossed() {
+    let notifications = vec![
+        limits(0, &[(100, 5, Side::Bid), (101, 5, Side::Ask)]),
+        limits(1, &[(101, 1, Side::Bid)]),
+        limits(2, &[(102, 1, Side::Bid)]),
+        limits(3, &[(101, 0, Side::Bid), (102, 0, Side::Bid)]),
+        limits(4, &[(102, 1, Side::Bid)]),
+    ];
+
+    assert_eq!(issues(notifications, Settings::default()), vec![
+        IntegrityIssue::Locked { price: 101 },
+        IntegrityIssue::Crossed { best_bid: 102, best_ask: 101 },
+    ]);
+}
+
+#[test]
+fn stale() {
+    let settings = Settings {
+        stale_after: Some(1000),
+        stale_depth: 1,
+    };
+
+    let notifications = vec![
+        limits(0, &[(100, 5, Side::Bid), (98, 5, Side::Bid), (101, 5, Side::Ask)]),
+        limits(500, &[(101, 4, Side::Ask)]),
+        limits(1000, &[(101, 3, Side::Ask)]),
+
+        // Reported once only.
+        limits(1200, &[(101, 2, Side::Ask)]),
+
+        // The limit is updated hence stops being stale.
+        limits(1300, &[(100, 4, Side::Bid)]),
+        limits(2400, &[(101, 1, Side::Ask)]),
+    ];
+
+    assert_eq!(issues(notifications, settings), vec![
+        IntegrityIssue::StaleLevel { side: Side::Bid, price: 100, last_update: 0 },
+        IntegrityIssue::StaleLevel { side: Side::Bid, price: 100, last_update: 1300 },
+    ]);
+}
+
+#[test]
+fn compare() {
+    let mut order_book = OrderBook::new();
+    let mut snapshot = OrderBook::new();
+    for &(price, size, side) in &[(100, 5, Side::Bid), (99, 2, Side::Bid), (101, 5, Side::Ask)] {
+        order_book.update(LimitUpdate::new(price, size, side));
+        snapshot.update(LimitUpdate::new(price, size, side));
+    }
+
+    // Limits beyond the depth of the snapshot are not compared.
+    order_book.update(LimitUpdate::new(90, 1, Side::Bid));
+    assert_eq!(integrity::compare(&order_book, &snapshot), None);
+
+    order_book.update(LimitUpdate::new(101, 4, Side::Ask));
+    assert_eq!(
+        integrity::compare(&order_book, &snapshot),
+        Some(IntegrityIssue::Discrepancies(vec![LimitUpdate::new(101, 5, Side::Ask)]))
+    );
+}
+
+#[test]
+fn checksum() {
+    assert_eq!(integrity::crc32(b"123456789"), 0xCBF4_3926);
+
+    let order_book = OrderBook::new();
+    assert_eq!(integrity::verify_checksum(&order_book, 7, |_| 7), None);
+    assert_eq!(
+        integrity::verify_checksum(&order_book, 7, |_| 8),
+        Some(IntegrityIssue::ChecksumMismatch { expected: 7, computed: 8 })
+    );
+}
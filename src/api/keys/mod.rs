@@ -0,0 +1,440 @@
+//! A module defining how secrets are loaded and used for signing requests.
+//!
+//! Secrets are wrapped into `Secret`, which is zeroized on drop and never shows up in `Debug`
+//! output. They can be loaded through a `KeyProvider`, e.g. from environment variables with
+//! `EnvProvider` or from a file encrypted with a passphrase with `EncryptedFile`.
+//!
+//! Exchanges authenticating requests with an HMAC-SHA256 signature (binance and Coinbase Pro)
+//! sign through a `RequestSigner`: the secret key can be kept out of the process entirely by
+//! delegating signatures to an external signer process listening on a local socket, see
+//! `SocketSigner`.
+
+mod test;
+
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use openssl::hash::MessageDigest;
+use openssl::pkey::{PKey, Private};
+use openssl::symm::{self, Cipher};
+use serde::{Serialize, Serializer, Deserialize, Deserializer};
+use serde_derive::{Serialize, Deserialize};
+use failure_derive::Fail;
+
+/// Overwrite `bytes` with zeros, in a way which cannot be optimized away.
+crate fn zeroize(bytes: &mut [u8]) {
+    for byte in bytes.iter_mut() {
+        unsafe { std::ptr::write_volatile(byte, 0) };
+    }
+    std::sync::atomic::compiler_fence(std::sync::atomic::Ordering::SeqCst);
+}
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+/// A secret string, zeroized on drop and redacted in `Debug` output.
+///
+/// # Note
+/// Zeroizing is best effort: copies made before the secret was wrapped, e.g. by the
+/// deserializer, are not covered.
+pub struct Secret {
+    inner: String,
+}
+
+impl Secret {
+    /// Wrap `secret`.
+    pub fn new(secret: String) -> Self {
+        Secret {
+            inner: secret,
+        }
+    }
+
+    /// Return the secret value. Avoid keeping copies of it around.
+    pub fn expose(&self) -> &str {
+        &self.inner
+    }
+}
+
+impl From<String> for Secret {
+    fn from(secret: String) -> Self {
+        Secret::new(secret)
+    }
+}
+
+impl<'a> From<&'a str> for Secret {
+    fn from(secret: &'a str) -> Self {
+        Secret::new(secret.to_owned())
+    }
+}
+
+impl Drop for Secret {
+    fn drop(&mut self) {
+        zeroize(unsafe { self.inner.as_bytes_mut() });
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Secret(***)")
+    }
+}
+
+impl Serialize for Secret {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.inner)
+    }
+}
+
+impl<'de> Deserialize<'de> for Secret {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(Secret::new)
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Fail)]
+/// An error related to the loading or the use of secrets.
+pub enum KeyError {
+    #[fail(display = "missing secret `{}`", _0)]
+    /// The provider does not know about this secret.
+    Missing(String),
+
+    #[fail(display = "wrong passphrase or corrupted key file")]
+    /// An encrypted key file could not be decrypted.
+    Decryption,
+
+    #[fail(display = "external signer error: {}", _0)]
+    /// An external signer failed or answered with an invalid message.
+    Signer(String),
+
+    #[fail(display = "cannot serialize a key pair using an external signer")]
+    /// A key pair using an external signer cannot be serialized, since the secret
+    /// key is unknown.
+    Serialize,
+}
+
+/// A source of secrets.
+pub trait KeyProvider {
+    /// Return the secret named `name`, e.g. `api_key` or `secret_key`.
+    fn secret(&self, name: &str) -> Result<Secret, failure::Error>;
+}
+
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+/// A provider reading secrets from environment variables: the secret `name` is read from the
+/// variable `prefix` + `name` in upper case, e.g. `BINANCE_SECRET_KEY` for the prefix
+/// `BINANCE_` and the secret `secret_key`.
+pub struct EnvProvider {
+    prefix: String,
+}
+
+impl EnvProvider {
+    /// Return a new provider reading variables starting with `prefix`.
+    pub fn new(prefix: &str) -> Self {
+        EnvProvider {
+            prefix: prefix.to_owned(),
+        }
+    }
+}
+
+impl KeyProvider for EnvProvider {
+    fn secret(&self, name: &str) -> Result<Secret, failure::Error> {
+        let var = format!("{}{}", self.prefix, name).to_uppercase();
+        match std::env::var(&var) {
+            Ok(value) => Ok(Secret::new(value)),
+            Err(..) => Err(KeyError::Missing(var).into()),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+/// Content of an encrypted key file, binary fields are encoded in base64.
+struct EncryptedContent {
+    salt: String,
+    iterations: usize,
+    nonce: String,
+    ciphertext: String,
+    tag: String,
+}
+
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+const TAG_LEN: usize = 16;
+const ITERATIONS: usize = 100_000;
+
+/// Derive an AES-256 key from `passphrase`.
+fn derive_key(passphrase: &Secret, salt: &[u8], iterations: usize)
+    -> Result<[u8; 32], failure::Error>
+{
+    let mut key = [0; 32];
+    openssl::pkcs5::pbkdf2_hmac(
+        passphrase.expose().as_bytes(),
+        salt,
+        iterations,
+        MessageDigest::sha256(),
+        &mut key
+    )?;
+    Ok(key)
+}
+
+/// A provider reading secrets from a JSON file encrypted with a passphrase.
+///
+/// The secrets are a JSON object mapping names to values, encrypted with AES-256-GCM under
+/// a key derived from the passphrase with PBKDF2-HMAC-SHA256. Use `EncryptedFile::encrypt`
+/// for writing such a file.
+pub struct EncryptedFile {
+    secrets: HashMap<String, Secret>,
+}
+
+impl fmt::Debug for EncryptedFile {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("EncryptedFile")
+            .field("secrets", &self.secrets.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+impl EncryptedFile {
+    /// Read the file at `path` and decrypt it with `passphrase`.
+    pub fn open<P: AsRef<Path>>(path: P, passphrase: &Secret) -> Result<Self, failure::Error> {
+        let content = std::fs::read_to_string(path)?;
+        EncryptedFile::decrypt(&content, passphrase)
+    }
+
+    /// Decrypt `content`, the content of an encrypted key file, with `passphrase`.
+    pub fn decrypt(content: &str, passphrase: &Secret) -> Result<Self, failure::Error> {
+        let content: EncryptedContent = serde_json::from_str(content)?;
+        let salt = base64::decode(&content.salt)?;
+        let nonce = base64::decode(&content.nonce)?;
+        let ciphertext = base64::decode(&content.ciphertext)?;
+        let tag = base64::decode(&content.tag)?;
+
+        let mut key = derive_key(passphrase, &salt, content.iterations)?;
+        let plaintext = symm::decrypt_aead(
+            Cipher::aes_256_gcm(),
+            &key,
+            Some(&nonce),
+            &[],
+            &ciphertext,
+            &tag
+        );
+        zeroize(&mut key);
+
+        let mut plaintext = plaintext.map_err(|_| KeyError::Decryption)?;
+        let secrets = serde_json::from_slice(&plaintext);
+        zeroize(&mut plaintext);
+
+        Ok(EncryptedFile {
+            secrets: secrets.map_err(|_| KeyError::Decryption)?,
+        })
+    }
+
+    /// Encrypt `secrets` with `passphrase`, and return the content of the encrypted key
+    /// file.
+    pub fn encrypt(secrets: &HashMap<String, Secret>, passphrase: &Secret)
+        -> Result<String, failure::Error>
+    {
+        let mut salt = [0; SALT_LEN];
+        let mut nonce = [0; NONCE_LEN];
+        openssl::rand::rand_bytes(&mut salt)?;
+        openssl::rand::rand_bytes(&mut nonce)?;
+
+        let mut plaintext = serde_json::to_vec(secrets)?;
+        let mut key = derive_key(passphrase, &salt, ITERATIONS)?;
+        let mut tag = [0; TAG_LEN];
+        let ciphertext = symm::encrypt_aead(
+            Cipher::aes_256_gcm(),
+            &key,
+            Some(&nonce),
+            &[],
+            &plaintext,
+            &mut tag
+        );
+        zeroize(&mut key);
+        zeroize(&mut plaintext);
+
+        let content = EncryptedContent {
+            salt: base64::encode(&salt),
+            iterations: ITERATIONS,
+            nonce: base64::encode(&nonce),
+            ciphertext: base64::encode(&ciphertext?),
+            tag: base64::encode(&tag),
+        };
+        Ok(serde_json::to_string_pretty(&content)?)
+    }
+}
+
+impl KeyProvider for EncryptedFile {
+    fn secret(&self, name: &str) -> Result<Secret, failure::Error> {
+        match self.secrets.get(name) {
+            Some(secret) => Ok(secret.clone()),
+            None => Err(KeyError::Missing(name.to_owned()).into()),
+        }
+    }
+}
+
+/// An object signing requests on behalf of a client.
+pub trait RequestSigner: Send + Sync {
+    /// Return the HMAC-SHA256 of `payload` under the secret key.
+    fn sign(&self, payload: &[u8]) -> Result<Vec<u8>, failure::Error>;
+}
+
+/// A signer holding the secret key in memory.
+pub struct HmacSigner {
+    key: PKey<Private>,
+}
+
+impl HmacSigner {
+    /// Return a new signer using `key` as the HMAC key.
+    pub fn new(key: &[u8]) -> Result<Self, failure::Error> {
+        Ok(HmacSigner {
+            key: PKey::hmac(key)?,
+        })
+    }
+}
+
+impl fmt::Debug for HmacSigner {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "HmacSigner(***)")
+    }
+}
+
+impl RequestSigner for HmacSigner {
+    fn sign(&self, payload: &[u8]) -> Result<Vec<u8>, failure::Error> {
+        let mut signer = openssl::sign::Signer::new(MessageDigest::sha256(), &self.key)?;
+        signer.update(payload)?;
+        Ok(signer.sign_to_vec()?)
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+/// A signer delegating signatures to an external process listening on a Unix socket, so
+/// that the secret key never enters this process.
+///
+/// A connection is opened for each signature. The payload is sent hex-encoded on a single
+/// line, the signer answers with the hex-encoded HMAC-SHA256 on a single line, or with a
+/// line starting with `error:` followed by a description of the error.
+///
+/// A signer not answering within the timeout (5 seconds by default) fails the signature,
+/// hence the request being signed, with a `KeyError::Signer` error.
+pub struct SocketSigner {
+    path: PathBuf,
+    timeout: Duration,
+}
+
+impl SocketSigner {
+    /// Return a new signer connecting to the Unix socket at `path`.
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        SocketSigner {
+            path: path.as_ref().to_owned(),
+            timeout: Duration::from_secs(5),
+        }
+    }
+
+    /// Set the maximum duration of each read from and write to the signer.
+    ///
+    /// # Panics
+    /// Panic if `timeout` is zero.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        assert!(timeout > Duration::from_secs(0), "zero timeout");
+        self.timeout = timeout;
+        self
+    }
+}
+
+#[cfg(unix)]
+impl RequestSigner for SocketSigner {
+    fn sign(&self, payload: &[u8]) -> Result<Vec<u8>, failure::Error> {
+        use std::io::{self, BufRead, BufReader, Write};
+        use std::os::unix::net::UnixStream;
+
+        let timed_out = |err: io::Error| -> failure::Error {
+            match err.kind() {
+                io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut => {
+                    KeyError::Signer("timed out".to_owned()).into()
+                }
+                _ => err.into(),
+            }
+        };
+
+        let mut stream = UnixStream::connect(&self.path)?;
+        stream.set_read_timeout(Some(self.timeout))?;
+        stream.set_write_timeout(Some(self.timeout))?;
+        stream.write_all(format!("{}\n", hex::encode(payload)).as_bytes()).map_err(timed_out)?;
+
+        let mut answer = String::new();
+        BufReader::new(stream).read_line(&mut answer).map_err(timed_out)?;
+        let answer = answer.trim_end();
+        if answer.starts_with("error:") {
+            return Err(KeyError::Signer(answer["error:".len()..].trim().to_owned()).into());
+        }
+        hex::decode(answer).map_err(|_| {
+            KeyError::Signer(format!("invalid signature `{}`", answer)).into()
+        })
+    }
+}
+
+#[derive(Clone)]
+/// The secret part of a key pair: either the secret key itself, or a signer standing for
+/// it. Deserialized from the secret key, as a string.
+pub enum SecretKey {
+    /// The secret key.
+    Secret(Secret),
+
+    /// A signer, e.g. an external signer process.
+    Signer(Arc<dyn RequestSigner>),
+}
+
+impl SecretKey {
+    /// Return a signer for this secret key, `decode` being applied to the secret key before
+    /// using it as the HMAC key.
+    crate fn signer<F>(self, decode: F) -> Result<Arc<dyn RequestSigner>, failure::Error>
+        where F: FnOnce(&str) -> Result<Vec<u8>, failure::Error>
+    {
+        match self {
+            SecretKey::Secret(secret) => {
+                let mut key = decode(secret.expose())?;
+                let signer = HmacSigner::new(&key);
+                zeroize(&mut key);
+                Ok(Arc::new(signer?))
+            }
+            SecretKey::Signer(signer) => Ok(signer),
+        }
+    }
+}
+
+impl From<Secret> for SecretKey {
+    fn from(secret: Secret) -> Self {
+        SecretKey::Secret(secret)
+    }
+}
+
+impl From<String> for SecretKey {
+    fn from(secret: String) -> Self {
+        SecretKey::Secret(Secret::new(secret))
+    }
+}
+
+impl fmt::Debug for SecretKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SecretKey::Secret(secret) => write!(f, "{:?}", secret),
+            SecretKey::Signer(..) => write!(f, "Signer(..)"),
+        }
+    }
+}
+
+impl Serialize for SecretKey {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::Error;
+
+        match self {
+            SecretKey::Secret(secret) => secret.serialize(serializer),
+            SecretKey::Signer(..) => Err(S::Error::custom(KeyError::Serialize)),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for SecretKey {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Secret::deserialize(deserializer).map(SecretKey::Secret)
+    }
+}
@@ -0,0 +1,92 @@
+#![cfg(test)]
+
+use std::collections::HashMap;
+use crate::api::{binance, gdax};
+use crate::api::keys::*;
+
+#[test]
+fn redacted_debug() {
+    let key_pair = binance::KeyPair::new("my_api_key".to_owned(), "my_secret_key".to_owned());
+    let debug = format!("{:?}", key_pair);
+    assert!(debug.contains("my_api_key"));
+    assert!(!debug.contains("my_secret_key"));
+
+    let key_pair: gdax::KeyPair = serde_json::from_str(r#"{
+        "api_key": "my_api_key",
+        "secret_key": "my_secret_key",
+        "pass_phrase": "my_pass_phrase"
+    }"#).unwrap();
+    let debug = format!("{:?}", key_pair);
+    assert!(!debug.contains("my_secret_key"));
+    assert!(!debug.contains("my_pass_phrase"));
+}
+
+#[test]
+fn providers() {
+    std::env::set_var("TRADE_TEST_API_KEY", "my_api_key");
+    let provider = EnvProvider::new("trade_test_");
+    assert_eq!(provider.secret("api_key").unwrap().expose(), "my_api_key");
+    assert!(provider.secret("secret_key").is_err());
+
+    let mut secrets = HashMap::new();
+    secrets.insert("api_key".to_owned(), Secret::from("my_api_key"));
+    secrets.insert("secret_key".to_owned(), Secret::from("my_secret_key"));
+    let passphrase = Secret::from("passphrase");
+    let content = EncryptedFile::encrypt(&secrets, &passphrase).unwrap();
+    assert!(!content.contains("my_secret_key"));
+
+    let file = EncryptedFile::decrypt(&content, &passphrase).unwrap();
+    assert_eq!(file.secret("secret_key").unwrap().expose(), "my_secret_key");
+    assert!(binance::KeyPair::from_provider(&file).is_ok());
+
+    let err = EncryptedFile::decrypt(&content, &Secret::from("wrong")).unwrap_err();
+    assert_eq!(err.downcast_ref::<KeyError>(), Some(&KeyError::Decryption));
+}
+
+#[cfg(unix)]
+#[test]
+fn socket_signer() {
+    use std::io::{BufRead, BufReader, Write};
+    use std::os::unix::net::UnixListener;
+
+    let path = std::env::temp_dir().join(format!("trade-signer-{}", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let listener = UnixListener::bind(&path).unwrap();
+
+    let hmac = HmacSigner::new(b"my_secret_key").unwrap();
+    let expected = hmac.sign(b"payload").unwrap();
+
+    // An external signer answering two requests, the second one with an error, and stalling
+    // on the third one.
+    let (resume, stalled) = std::sync::mpsc::channel();
+    let server = std::thread::spawn(move || {
+        for answer in &[Some(hmac), None] {
+            let (stream, _) = listener.accept().unwrap();
+            let mut line = String::new();
+            BufReader::new(&stream).read_line(&mut line).unwrap();
+
+            let answer = match answer {
+                Some(hmac) => hex::encode(&hmac.sign(&hex::decode(line.trim()).unwrap()).unwrap()),
+                None => "error: locked".to_owned(),
+            };
+            (&stream).write_all(format!("{}\n", answer).as_bytes()).unwrap();
+        }
+
+        let _stream = listener.accept().unwrap();
+        stalled.recv().unwrap();
+    });
+
+    let signer = SocketSigner::new(&path);
+    assert_eq!(signer.sign(b"payload").unwrap(), expected);
+
+    let err = signer.sign(b"payload").unwrap_err();
+    assert_eq!(err.downcast_ref::<KeyError>(), Some(&KeyError::Signer("locked".to_owned())));
+
+    let signer = signer.with_timeout(std::time::Duration::from_millis(50));
+    let err = signer.sign(b"payload").unwrap_err();
+    assert_eq!(err.downcast_ref::<KeyError>(), Some(&KeyError::Signer("timed out".to_owned())));
+    resume.send(()).unwrap();
+
+    server.join().unwrap();
+    std::fs::remove_file(&path).unwrap();
+}
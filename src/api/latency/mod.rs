@@ -0,0 +1,191 @@
+//! A module defining a collector of latency statistics, per exchange: latency of the
+//! notification feed, as reported by `Timestamped::latency`, and round trip times of
+//! REST requests.
+//!
+//! Wrap a stream with `LatencyStats::observe`, and a request future, e.g. the one
+//! returned by `ApiClient::ping`, with `LatencyStats::time`.
+
+mod test;
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use futures::prelude::*;
+use crate::api::Notification;
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Default)]
+/// Summary of a series of latency samples, in µs.
+pub struct Summary {
+    /// Number of samples.
+    pub count: u64,
+
+    /// Smallest sample.
+    pub min: u64,
+
+    /// Largest sample.
+    pub max: u64,
+
+    /// Sum of all samples.
+    pub total: u64,
+
+    /// Most recent sample.
+    pub last: u64,
+}
+
+impl Summary {
+    /// Add `sample` to the summary.
+    pub fn record(&mut self, sample: u64) {
+        if self.count == 0 || sample < self.min {
+            self.min = sample;
+        }
+        if sample > self.max {
+            self.max = sample;
+        }
+        self.count += 1;
+        self.total += sample;
+        self.last = sample;
+    }
+
+    /// Mean of the samples, `None` if there are none.
+    pub fn mean(&self) -> Option<f64> {
+        if self.count == 0 {
+            None
+        } else {
+            Some(self.total as f64 / self.count as f64)
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+/// Latency statistics of one exchange.
+pub struct ExchangeLatencies {
+    /// Latency of the notification feed.
+    pub feed: Summary,
+
+    /// request name => round trip times of the request
+    pub requests: HashMap<String, Summary>,
+}
+
+#[derive(Clone, Default)]
+/// A collector of latency statistics, which can be shared by several streams and requests.
+pub struct LatencyStats {
+    exchanges: Arc<Mutex<HashMap<String, ExchangeLatencies>>>,
+}
+
+impl LatencyStats {
+    /// Return an empty collector.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a feed latency sample of `exchange`, in µs.
+    pub fn record_feed(&self, exchange: &str, latency: u64) {
+        let mut exchanges = self.exchanges.lock().unwrap();
+        exchanges.entry(exchange.to_owned()).or_default().feed.record(latency);
+    }
+
+    /// Record a round trip time of the `request` of `exchange`, in µs.
+    pub fn record_request(&self, exchange: &str, request: &str, round_trip: u64) {
+        let mut exchanges = self.exchanges.lock().unwrap();
+        exchanges.entry(exchange.to_owned())
+            .or_default()
+            .requests
+            .entry(request.to_owned())
+            .or_default()
+            .record(round_trip);
+    }
+
+    /// Record the latency of the notifications of `stream`, coming from `exchange`.
+    pub fn observe<S>(&self, exchange: &str, stream: S) -> Observe<S>
+        where S: Stream<Item = Notification>
+    {
+        Observe {
+            stream,
+            exchange: exchange.to_owned(),
+            stats: self.clone(),
+        }
+    }
+
+    /// Record the round trip time of `future`, a `request` of `exchange`, from now until
+    /// it resolves, successfully or not.
+    pub fn time<F: Future>(&self, exchange: &str, request: &str, future: F) -> Timed<F> {
+        Timed {
+            future,
+            exchange: exchange.to_owned(),
+            request: request.to_owned(),
+            start: Instant::now(),
+            stats: self.clone(),
+        }
+    }
+
+    /// Statistics of `exchange`, if any sample was recorded.
+    pub fn exchange(&self, exchange: &str) -> Option<ExchangeLatencies> {
+        self.exchanges.lock().unwrap().get(exchange).cloned()
+    }
+
+    /// Statistics of all the exchanges, indexed by exchange name.
+    pub fn report(&self) -> HashMap<String, ExchangeLatencies> {
+        self.exchanges.lock().unwrap().clone()
+    }
+}
+
+/// Feed latency of `notif`, i.e. the latency of its first item carrying one.
+fn feed_latency(notif: &Notification) -> Option<u64> {
+    match notif {
+        Notification::Trade(trade) => trade.latency(),
+        Notification::BestBidOffer(bbo) => bbo.latency(),
+        Notification::LimitUpdates(updates) => updates.iter().filter_map(|u| u.latency()).next(),
+        Notification::OrderConfirmation(order) => order.latency(),
+        Notification::OrderUpdate(update) => update.latency(),
+        Notification::OrderExpiration(expiration) => expiration.latency(),
+        Notification::L3Updates(updates) => updates.iter().filter_map(|u| u.latency()).next(),
+        Notification::Integrity(_) | Notification::Overflow(_) => None,
+    }
+}
+
+/// A stream adaptor recording the feed latency of each notification, see
+/// `LatencyStats::observe`.
+pub struct Observe<S> {
+    stream: S,
+    exchange: String,
+    stats: LatencyStats,
+}
+
+impl<S: Stream<Item = Notification>> Stream for Observe<S> {
+    type Item = Notification;
+    type Error = S::Error;
+
+    fn poll(&mut self) -> Poll<Option<Notification>, S::Error> {
+        let notif = futures::try_ready!(self.stream.poll());
+        if let Some(latency) = notif.as_ref().and_then(feed_latency) {
+            self.stats.record_feed(&self.exchange, latency);
+        }
+        Ok(Async::Ready(notif))
+    }
+}
+
+/// A future adaptor recording the round trip time of a request, see `LatencyStats::time`.
+pub struct Timed<F> {
+    future: F,
+    exchange: String,
+    request: String,
+    start: Instant,
+    stats: LatencyStats,
+}
+
+impl<F: Future> Future for Timed<F> {
+    type Item = F::Item;
+    type Error = F::Error;
+
+    fn poll(&mut self) -> Poll<F::Item, F::Error> {
+        let result = self.future.poll();
+        if let Ok(Async::NotReady) = result {
+            return result;
+        }
+
+        let elapsed = self.start.elapsed();
+        let round_trip = elapsed.as_secs() * 1_000_000 + u64::from(elapsed.subsec_micros());
+        self.stats.record_request(&self.exchange, &self.request, round_trip);
+        result
+    }
+}
@@ -0,0 +1,63 @@
+#![cfg(test)]
+
+use futures::prelude::*;
+use crate::Side;
+use crate::order_book::LimitUpdate;
+use crate::api::{Notification, Trade};
+use crate::api::timestamp::{IntoTimestamped, TimestampUs};
+use crate::api::latency::LatencyStats;
+
+#[test]
+fn latency_stats() {
+    let notifications = vec![
+        // Snapshots are timestamped locally, hence have no latency.
+        Notification::LimitUpdates(vec![
+            LimitUpdate::new(100, 5, Side::Bid).timestamped().with_received(TimestampUs(0)),
+        ]),
+        Notification::Trade(Trade {
+            price: 100,
+            size: 1,
+            maker_side: Side::Bid,
+        }.with_timestamp(10).with_sent(12).with_received(TimestampUs(12_500))),
+        Notification::LimitUpdates(vec![
+            LimitUpdate::new(100, 4, Side::Bid)
+                .with_timestamp(20)
+                .with_received(TimestampUs(21_300)),
+        ]),
+    ];
+
+    let stats = LatencyStats::new();
+    let count = stats.observe("sim", futures::stream::iter_ok::<_, ()>(notifications))
+        .collect()
+        .wait()
+        .unwrap()
+        .len();
+    assert_eq!(count, 3);
+
+    stats.time("sim", "ping", futures::future::ok::<_, ()>(())).wait().unwrap();
+    stats.time("sim", "ping", futures::future::err::<(), _>(())).wait().unwrap_err();
+
+    let latencies = stats.exchange("sim").unwrap();
+    assert_eq!(latencies.feed.count, 2);
+    assert_eq!(latencies.feed.min, 500);
+    assert_eq!(latencies.feed.max, 1300);
+    assert_eq!(latencies.feed.mean(), Some(900.));
+    assert_eq!(latencies.requests["ping"].count, 2);
+    assert!(stats.exchange("binance").is_none());
+}
+
+#[test]
+fn same_exchange_data() {
+    let trade = Trade {
+        price: 100,
+        size: 1,
+        maker_side: Side::Bid,
+    }.with_timestamp(10);
+
+    // The same trade received by two connections.
+    let first = trade.clone().with_sent(12).with_received(TimestampUs(12_500));
+    let second = trade.with_received(TimestampUs(13_000));
+    assert_ne!(first, second);
+    assert!(first.same_exchange_data(&second));
+    assert!(!first.same_exchange_data(&Trade { size: 2, ..*first }.with_timestamp(10)));
+}
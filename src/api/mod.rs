@@ -7,8 +7,28 @@ pub mod errors;
 pub mod timestamp;
 pub mod symbol;
 pub mod order_book;
+pub mod transport;
+pub mod sim;
+pub mod recording;
+pub mod strategy;
+pub mod backtest;
+pub mod integrity;
+pub mod latency;
+pub mod channel;
+pub mod top_of_book;
+pub mod order_manager;
+pub mod portfolio;
+pub mod risk;
+pub mod watchdog;
+pub mod execution;
+pub mod router;
+pub mod consolidated;
+pub mod dynamic;
+pub mod keys;
 mod query_string;
 mod wss;
+mod fixtures;
+mod notifications;
 
 use futures::prelude::*;
 use std::collections::HashMap;
@@ -16,7 +36,8 @@ use serde_derive::{Serialize, Deserialize};
 use bitflags::bitflags;
 use crate::Side;
 use crate::tick::{TickUnit, Tickable};
-use crate::order_book::LimitUpdate;
+use crate::order_book::{OrderBook, LimitUpdate};
+use crate::order_book::l3::L3Update;
 
 use self::timestamp::Timestamped;
 use self::symbol::{Symbol, WithSymbol};
@@ -31,6 +52,27 @@ pub struct Params {
 
     /// REST API endpoint (usually over HTTP).
     pub rest_endpoint: String,
+
+    /// Settings of the channel through which stream notifications are forwarded.
+    #[serde(default)]
+    pub channel: channel::ChannelSettings,
+
+    /// Settings of the default transports: timeouts, proxy and user agent.
+    #[serde(default)]
+    pub transport: transport::TransportSettings,
+}
+
+impl Params {
+    /// Return new params with the given endpoints and default settings. See the `params`
+    /// module of each exchange for the known endpoints.
+    pub fn new(streaming_endpoint: &str, rest_endpoint: &str) -> Self {
+        Params {
+            streaming_endpoint: streaming_endpoint.to_owned(),
+            rest_endpoint: rest_endpoint.to_owned(),
+            channel: Default::default(),
+            transport: Default::default(),
+        }
+    }
 }
 
 #[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
@@ -120,6 +162,12 @@ impl Order {
         self
     }
 
+    /// Same as `with_order_id`, for a client only known as a `dynamic::DynApiClient`.
+    pub fn with_dyn_order_id(mut self, client: &dyn dynamic::DynApiClient, hint: &str) -> Self {
+        self.order_id = Some(client.new_order_id(hint));
+        self
+    }
+
     /// Return the order id if one was provided.
     pub fn order_id(&self) -> Option<&str> {
         self.order_id.as_ref().map(|s| s.as_ref())
@@ -260,6 +308,23 @@ pub struct OrderConfirmation {
     pub side: Side,
 }
 
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+/// Best limit of each side of the order book. An empty bid side has a `0` price, an empty
+/// ask side has a `TickUnit::max_value()` price, both with a `0` size.
+pub struct BestBidOffer {
+    /// Best bid price.
+    pub bid_price: TickUnit,
+
+    /// Size available at the best bid.
+    pub bid_size: TickUnit,
+
+    /// Best ask price.
+    pub ask_price: TickUnit,
+
+    /// Size available at the best ask.
+    pub ask_size: TickUnit,
+}
+
 #[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
 /// A notification that some event happened.
 pub enum Notification {
@@ -269,6 +334,9 @@ pub enum Notification {
     /// The limit order book has changed and should be updated.
     LimitUpdates(Vec<Timestamped<LimitUpdate>>),
 
+    /// The best bid or the best ask has changed.
+    BestBidOffer(Timestamped<BestBidOffer>),
+
     /// An order has been inserted.
     OrderConfirmation(Timestamped<OrderConfirmation>),
 
@@ -277,8 +345,63 @@ pub enum Notification {
 
     /// An order has expired or was canceled.
     OrderExpiration(Timestamped<OrderExpiration>),
+
+    /// Individual orders of the order book have changed. The first such notification
+    /// is a snapshot of the book.
+    L3Updates(Vec<Timestamped<L3Update>>),
+
+    /// An integrity issue was detected on the order book, see the `integrity` module.
+    Integrity(Timestamped<integrity::IntegrityIssue>),
+
+    /// The consumer did not keep up with the stream, and some notifications were dropped
+    /// or conflated, see the `channel` module.
+    Overflow(Timestamped<channel::Overflow>),
+}
+
+impl Notification {
+    /// Timestamp of the notification, i.e. of its last item for batches, or `None` for an
+    /// empty batch.
+    pub fn timestamp(&self) -> Option<timestamp::Timestamp> {
+        match self {
+            Notification::Trade(trade) => Some(trade.timestamp()),
+            Notification::BestBidOffer(bbo) => Some(bbo.timestamp()),
+            Notification::LimitUpdates(updates) => updates.last().map(|update| update.timestamp()),
+            Notification::OrderConfirmation(order) => Some(order.timestamp()),
+            Notification::OrderUpdate(update) => Some(update.timestamp()),
+            Notification::OrderExpiration(expiration) => Some(expiration.timestamp()),
+            Notification::L3Updates(updates) => updates.last().map(|update| update.timestamp()),
+            Notification::Integrity(issue) => Some(issue.timestamp()),
+            Notification::Overflow(overflow) => Some(overflow.timestamp()),
+        }
+    }
+
+    /// Set the local receive time of every timestamped item which does not have one yet.
+    crate fn set_received(&mut self, received: timestamp::TimestampUs) {
+        match self {
+            Notification::Trade(trade) => trade.set_received(received),
+            Notification::BestBidOffer(bbo) => bbo.set_received(received),
+            Notification::LimitUpdates(updates) => {
+                for update in updates {
+                    update.set_received(received);
+                }
+            }
+            Notification::OrderConfirmation(order) => order.set_received(received),
+            Notification::OrderUpdate(update) => update.set_received(received),
+            Notification::OrderExpiration(expiration) => expiration.set_received(received),
+            Notification::L3Updates(updates) => {
+                for update in updates {
+                    update.set_received(received);
+                }
+            }
+            Notification::Integrity(issue) => issue.set_received(received),
+            Notification::Overflow(overflow) => overflow.set_received(received),
+        }
+    }
 }
 
+/// Number of limits per side forwarded with `NotificationFlags::PARTIAL_ORDER_BOOK`.
+pub const PARTIAL_DEPTH: usize = 20;
+
 bitflags! {
     /// Bit flags indicating which type of notification to forward.
     pub struct NotificationFlags: u8 {
@@ -291,8 +414,25 @@ bitflags! {
         /// Forward order confirmations and updates.
         const ORDERS = 0b0100;
 
-        /// Forward all notifications.
+        /// Forward all notifications, except order-level updates of the order book.
         const ALL = Self::ORDER_BOOK.bits | Self::TRADES.bits | Self::ORDERS.bits;
+
+        /// Forward order-level updates of the order book. Only supported by GDAX, and
+        /// must be requested explicitly.
+        const ORDER_BOOK_L3 = 0b1000;
+
+        /// Forward limit updates of the `PARTIAL_DEPTH` best limits of each side only,
+        /// through the partial depth stream of the exchange (binance `@depth20`, hitbtc
+        /// `subscribeOrderbook` with a limit). Exchanges without such a stream forward
+        /// the full book instead: truncate it with `OrderBook::with_window`. Must be
+        /// requested explicitly.
+        const PARTIAL_ORDER_BOOK = 0b1_0000;
+
+        /// Forward changes of the best bid and offer, through the native stream of the
+        /// exchange (binance `@bookTicker`, gdax `ticker` channel). Exchanges without such
+        /// a stream (hitbtc) compute them from the full book with `top_of_book::TopOfBook`.
+        /// Must be requested explicitly.
+        const BEST_BID_OFFER = 0b10_0000;
     }
 }
 
@@ -353,6 +493,11 @@ pub trait ApiClient: GenerateOrderId {
     fn ping(&self)
         -> Box<dyn Future<Item = Timestamped<()>, Error = errors::Error> + Send + 'static>;
 
+    /// Retrieve a snapshot of the order book through the REST API. Depending on the
+    /// exchange, the snapshot may only contain the limits closest to the top of the book.
+    fn order_book(&self, symbol: Symbol)
+        -> Box<dyn Future<Item = Timestamped<OrderBook>, Error = errors::Error> + Send + 'static>;
+
     /// Retrieve balances for this account.
     fn balances(&self)
         -> Box<dyn Future<Item = Balances, Error = errors::Error> + Send + 'static>;
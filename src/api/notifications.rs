@@ -0,0 +1,29 @@
+#![cfg(test)]
+
+//! Notification factories shared by the tests of the `api` modules.
+
+use crate::Side;
+use crate::order_book::LimitUpdate;
+use crate::api::{Notification, Trade};
+use crate::api::timestamp::{Timestamp, IntoTimestamped};
+
+/// Build a `LimitUpdates` notification out of `(price, size, side)` updates, all stamped
+/// with `timestamp`.
+crate fn limits(timestamp: Timestamp, updates: &[(u64, u64, Side)]) -> Notification {
+    Notification::LimitUpdates(
+        updates.iter()
+            .map(|&(price, size, side)| {
+                LimitUpdate::new(price, size, side).with_timestamp(timestamp)
+            })
+            .collect()
+    )
+}
+
+/// Build a `Trade` notification stamped with `timestamp`.
+crate fn trade(timestamp: Timestamp, price: u64, size: u64, maker_side: Side) -> Notification {
+    Notification::Trade(Trade {
+        price,
+        size,
+        maker_side,
+    }.with_timestamp(timestamp))
+}
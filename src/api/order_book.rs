@@ -1,8 +1,9 @@
 //! A module defining an helper data structure maintaining a live order book.
 
 use std::sync::{Arc, Mutex, MutexGuard};
-use crate::order_book::OrderBook;
-use crate::api::ApiClient;
+use crate::order_book::{OrderBook, Window};
+use futures::prelude::*;
+use crate::api::{ApiClient, Notification};
 
 /// A self-maintained live order book, updated in the background each time
 /// the underlying exchange stream sends an update.
@@ -17,7 +18,7 @@ pub enum BookState<'a> {
     Live(MutexGuard<'a, OrderBook>),
 
     /// The exchange stream has disconnected (due to e.g. an error or a forced
-    /// disconnection), hence the order book has gone out of sync and will never
+    /// disconnection) or has dropped some notifications, hence the order book has gone out of sync and will never
     /// be live again. A new `LiveOrderBook` must be created.
     Disconnected,
 }
@@ -29,11 +30,39 @@ impl LiveOrderBook {
     /// The call will block until the initial snapshot of the order book has been
     /// received.
     pub fn new<C: ApiClient>(stream: C::Stream) -> LiveOrderBook {
+        Self::from_stream(stream)
+    }
+
+    /// Build a self-maintained live order book from any notification stream, e.g.
+    /// a `Replayer`.
+    ///
+    /// # Note
+    /// The call will block until the initial snapshot of the order book has been
+    /// received.
+    pub fn from_stream<S>(stream: S) -> LiveOrderBook
+        where S: Stream<Item = Notification, Error = ()> + Send + 'static
+    {
+        Self::with_order_book(stream, OrderBook::new())
+    }
+
+    /// Build a self-maintained live order book from any notification stream, only keeping
+    /// the limits inside `window`, see `OrderBook::with_window`.
+    ///
+    /// # Note
+    /// The call will block until the initial snapshot of the order book has been
+    /// received.
+    pub fn from_stream_with_window<S>(stream: S, window: Window) -> LiveOrderBook
+        where S: Stream<Item = Notification, Error = ()> + Send + 'static
+    {
+        Self::with_order_book(stream, OrderBook::with_window(window))
+    }
+
+    fn with_order_book<S>(stream: S, order_book: OrderBook) -> LiveOrderBook
+        where S: Stream<Item = Notification, Error = ()> + Send + 'static
+    {
         use std::thread;
-        use futures::prelude::*;
-        use crate::api::Notification;
 
-        let order_book = Arc::new(Mutex::new(OrderBook::new()));
+        let order_book = Arc::new(Mutex::new(order_book));
         let weak = order_book.clone();
 
         let (sender, receiver) = std::sync::mpsc::sync_channel(0);
@@ -43,6 +72,13 @@ impl LiveOrderBook {
             let mut snapshot = false;
 
             let fut = stream.for_each(|notif| {
+                // Some limit updates may have been lost: the book is out of sync.
+                if let Notification::Overflow(overflow) = &notif {
+                    if overflow.dropped > 0 {
+                        return Err(());
+                    }
+                }
+
                 if let Notification::LimitUpdates(updates) = notif {
                     if let Some(order_book) = weak.upgrade() {
                         let mut order_book = order_book.lock().unwrap();
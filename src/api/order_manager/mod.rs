@@ -0,0 +1,496 @@
+//! A module defining an order manager, tracking the state of each order by combining the
+//! acknowledgments of REST requests and the notifications of a stream.
+//!
+//! REST responses and stream notifications travel through different connections, so they
+//! may arrive in any order: e.g. a fill can be received before the acknowledgment of the
+//! order which was filled, or a cancellation before the confirmation of the order. The
+//! state of an order therefore only moves forward: late events never bring an order back
+//! to an earlier state, and terminal states (filled, cancelled, rejected) are final.
+
+mod test;
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use futures::prelude::*;
+use serde_derive::{Serialize, Deserialize};
+use crate::Side;
+use crate::tick::TickUnit;
+use crate::api::{
+    ApiClient,
+    Order,
+    OrderAck,
+    Cancel,
+    CancelAck,
+    Notification,
+    OrderConfirmation,
+    OrderUpdate,
+};
+use crate::api::symbol::{Symbol, WithSymbol};
+use crate::api::timestamp::{Timestamp, Timestamped, timestamp_ms};
+use crate::api::errors::{ApiError, RestErrorKind, OrderError, CancelError};
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+/// State of an order.
+pub enum OrderState {
+    /// The order has been sent, but neither acknowledged nor confirmed yet.
+    PendingNew,
+
+    /// The order is in the book, without any fill.
+    Open,
+
+    /// The order is in the book and has been partially filled.
+    PartiallyFilled,
+
+    /// The order has been entirely filled.
+    Filled,
+
+    /// A cancel request has been sent, the order may still get filled until it is
+    /// acknowledged.
+    PendingCancel,
+
+    /// The order has been cancelled or has expired.
+    Cancelled,
+
+    /// The order has been rejected by the exchange.
+    Rejected,
+}
+
+impl OrderState {
+    /// Return whether the order cannot change anymore.
+    pub fn is_terminal(self) -> bool {
+        match self {
+            OrderState::Filled | OrderState::Cancelled | OrderState::Rejected => true,
+            _ => false,
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+/// An order tracked by an `OrderManager`.
+pub struct ManagedOrder {
+    /// Order id.
+    pub order_id: String,
+
+    /// Symbol the order was sent on, `None` for an order only known through notifications,
+    /// which do not carry the symbol.
+    pub symbol: Option<Symbol>,
+
+    /// Side of the order.
+    pub side: Side,
+
+    /// Price of the order, in ticks.
+    pub price: TickUnit,
+
+    /// Size of the order at insertion time, in ticks.
+    pub size: TickUnit,
+
+    /// Total size filled so far.
+    pub filled: TickUnit,
+
+    /// Size remaining in the book.
+    pub remaining: TickUnit,
+
+    /// Total commission paid so far.
+    pub commission: TickUnit,
+
+    /// Current state.
+    pub state: OrderState,
+
+    /// State the order was in when the cancel request was sent, restored if the request
+    /// fails.
+    before_cancel: Option<OrderState>,
+
+    /// Time of the last change.
+    pub updated: Timestamp,
+}
+
+impl ManagedOrder {
+    fn new(
+        order_id: String,
+        symbol: Option<Symbol>,
+        side: Side,
+        price: TickUnit,
+        size: TickUnit,
+        updated: Timestamp
+    ) -> Self
+    {
+        ManagedOrder {
+            order_id,
+            symbol,
+            side,
+            price,
+            size,
+            filled: 0,
+            remaining: size,
+            commission: 0,
+            state: OrderState::PendingNew,
+            before_cancel: None,
+            updated,
+        }
+    }
+
+    fn confirm(&mut self) {
+        if self.state == OrderState::PendingNew {
+            self.state = OrderState::Open;
+        }
+    }
+
+    fn fill(&mut self, update: &OrderUpdate) {
+        self.filled += update.consumed_size;
+        self.remaining = update.remaining_size;
+        self.commission += update.commission;
+
+        if self.remaining == 0 {
+            self.state = OrderState::Filled;
+        } else if self.state == OrderState::PendingCancel {
+            self.before_cancel = Some(OrderState::PartiallyFilled);
+        } else {
+            self.state = OrderState::PartiallyFilled;
+        }
+    }
+}
+
+/// An event not applied yet because the order it refers to is still unknown.
+#[derive(Clone, Debug)]
+enum Orphan {
+    Update(Timestamped<OrderUpdate>),
+    Expiration(Timestamp),
+}
+
+/// Events of an unknown order are dropped after this delay, in milliseconds: the order was
+/// most probably not sent by this process.
+const ORPHAN_EXPIRY: Timestamp = 60_000;
+
+type Callback = dyn FnMut(&ManagedOrder, Option<OrderState>) + Send;
+
+#[derive(Default)]
+struct State {
+    orders: HashMap<String, ManagedOrder>,
+    /// order id => (local time of the first event, events)
+    orphans: HashMap<String, (Timestamp, Vec<Orphan>)>,
+}
+
+impl State {
+    /// Apply `change` to the order `order_id` if it is known and not terminal, return the
+    /// changed order along with its previous state if anything changed.
+    fn change<F>(&mut self, order_id: &str, timestamp: Timestamp, change: F)
+        -> Option<(ManagedOrder, Option<OrderState>)>
+        where F: FnOnce(&mut ManagedOrder)
+    {
+        let order = self.orders.get_mut(order_id)?;
+        if order.state.is_terminal() {
+            return None;
+        }
+
+        let before = order.clone();
+        change(order);
+        if *order == before {
+            return None;
+        }
+        order.updated = timestamp;
+        Some((order.clone(), Some(before.state)))
+    }
+
+    /// Start tracking `order`, and apply the events which were received before.
+    fn insert(&mut self, order: ManagedOrder) -> Vec<(ManagedOrder, Option<OrderState>)> {
+        let order_id = order.order_id.clone();
+        let mut changes = vec![(order.clone(), None)];
+        self.orders.insert(order_id.clone(), order);
+
+        let orphans = self.orphans.remove(&order_id).map(|(_, orphans)| orphans);
+        for orphan in orphans.unwrap_or_default() {
+            changes.extend(self.apply(&order_id, orphan));
+        }
+        changes
+    }
+
+    /// Drop the events of unknown orders received more than `ORPHAN_EXPIRY` before `now`.
+    fn expire_orphans(&mut self, now: Timestamp) {
+        self.orphans.retain(|_, (received, _)| now.saturating_sub(*received) < ORPHAN_EXPIRY);
+    }
+
+    fn apply(&mut self, order_id: &str, orphan: Orphan)
+        -> Option<(ManagedOrder, Option<OrderState>)>
+    {
+        if !self.orders.contains_key(order_id) {
+            let now = timestamp_ms();
+            self.expire_orphans(now);
+            self.orphans.entry(order_id.to_owned())
+                .or_insert_with(|| (now, Vec::new()))
+                .1
+                .push(orphan);
+            return None;
+        }
+
+        match orphan {
+            Orphan::Update(update) => self.change(
+                order_id,
+                update.timestamp(),
+                |order| {
+                    order.confirm();
+                    order.fill(&update);
+                }
+            ),
+            Orphan::Expiration(timestamp) => self.change(
+                order_id,
+                timestamp,
+                |order| order.state = OrderState::Cancelled
+            ),
+        }
+    }
+}
+
+#[derive(Clone, Default)]
+/// Track the state of orders. An `OrderManager` is a cheap handle which can be cloned
+/// and shared between threads.
+///
+/// Orders are sent and cancelled through the `order` and `cancel` methods, which wrap the
+/// corresponding `ApiClient` requests, while the notifications of the stream are fed
+/// through `on_notification` (or by wrapping the stream with `track`). Orders confirmed by
+/// the stream without having been sent through the manager are tracked as well.
+pub struct OrderManager {
+    state: Arc<Mutex<State>>,
+    callbacks: Arc<Mutex<Vec<Box<Callback>>>>,
+}
+
+impl OrderManager {
+    /// Return a new `OrderManager` without any order.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a callback called each time an order changes, with the changed order and
+    /// its previous state (`None` for a newly tracked order). Callbacks are called in
+    /// registration order, and may query the manager but must not register callbacks.
+    pub fn on_change<F>(&self, callback: F)
+        where F: FnMut(&ManagedOrder, Option<OrderState>) + Send + 'static
+    {
+        self.callbacks.lock().unwrap().push(Box::new(callback));
+    }
+
+    fn notify<I>(&self, changes: I)
+        where I: IntoIterator<Item = (ManagedOrder, Option<OrderState>)>
+    {
+        let mut callbacks = self.callbacks.lock().unwrap();
+        for (order, previous) in changes {
+            for callback in callbacks.iter_mut() {
+                callback(&order, previous);
+            }
+        }
+    }
+
+    /// Start tracking `order` as a pending new order.
+    ///
+    /// # Panics
+    /// Panic if the order has no id, see `Order::with_order_id`.
+    pub fn order_sent(&self, order: &WithSymbol<&Order>) {
+        let order_id = order.order_id().expect("order without an id").to_owned();
+        let symbol = order.symbol();
+        let order = ManagedOrder::new(
+            order_id,
+            Some(symbol),
+            order.side,
+            order.price().ticked(symbol.price_tick()),
+            order.size().ticked(symbol.size_tick()),
+            timestamp_ms(),
+        );
+
+        let changes = self.state.lock().unwrap().insert(order);
+        self.notify(changes);
+    }
+
+    /// Apply the result of an order request: the order is open once acknowledged, and
+    /// rejected if the exchange refused it.
+    ///
+    /// Other errors (e.g. an unknown execution status or a network error) do not tell
+    /// whether the order reached the book, so the order stays pending and is updated by the
+    /// notifications of the stream, if any.
+    pub fn order_result(
+        &self,
+        order_id: &str,
+        result: Result<&Timestamped<OrderAck>, &OrderError>
+    ) {
+        let change = self.state.lock().unwrap().change(order_id, timestamp_ms(), |order| {
+            match result {
+                Ok(_) => order.confirm(),
+                Err(ApiError::RestError(err)) if order.state == OrderState::PendingNew => {
+                    match err.kind() {
+                        RestErrorKind::Specific(_) | RestErrorKind::InvalidRequest => {
+                            order.state = OrderState::Rejected;
+                        }
+                        _ => (),
+                    }
+                }
+                Err(_) => (),
+            }
+        });
+        self.notify(change);
+    }
+
+    /// Mark the order targeted by `cancel` as pending cancel.
+    pub fn cancel_sent(&self, cancel: &Cancel) {
+        let mut state = self.state.lock().unwrap();
+        let change = state.change(cancel.order_id(), timestamp_ms(), |order| {
+            if order.state != OrderState::PendingCancel {
+                order.before_cancel = Some(order.state);
+                order.state = OrderState::PendingCancel;
+            }
+        });
+        drop(state);
+        self.notify(change);
+    }
+
+    /// Apply the result of a cancel request: the order is cancelled once acknowledged, and
+    /// goes back to its previous state if the request failed.
+    pub fn cancel_result(
+        &self,
+        order_id: &str,
+        result: Result<&Timestamped<CancelAck>, &CancelError>
+    ) {
+        let change = self.state.lock().unwrap().change(order_id, timestamp_ms(), |order| {
+            if order.state != OrderState::PendingCancel {
+                return;
+            }
+            match result {
+                Ok(_) => order.state = OrderState::Cancelled,
+                Err(_) => order.state = order.before_cancel.take().unwrap_or(OrderState::Open),
+            }
+        });
+        self.notify(change);
+    }
+
+    /// Apply an order-related notification, other notifications are ignored.
+    ///
+    /// Updates and expirations of an order not tracked yet are kept until the order gets
+    /// tracked, either by being sent through the manager or by being confirmed, for at most
+    /// one minute.
+    pub fn on_notification(&self, notif: &Notification) {
+        let mut state = self.state.lock().unwrap();
+        let changes = match notif {
+            Notification::OrderConfirmation(confirmation) => {
+                let OrderConfirmation { order_id, price, size, side } = &**confirmation;
+                if state.orders.contains_key(order_id) {
+                    state.change(order_id, confirmation.timestamp(), |order| order.confirm())
+                        .into_iter()
+                        .collect()
+                } else {
+                    let mut order = ManagedOrder::new(
+                        order_id.clone(),
+                        None,
+                        *side,
+                        *price,
+                        *size,
+                        confirmation.timestamp()
+                    );
+                    order.state = OrderState::Open;
+                    state.insert(order)
+                }
+            }
+            Notification::OrderUpdate(update) => {
+                let order_id = update.order_id.clone();
+                state.apply(&order_id, Orphan::Update(update.clone())).into_iter().collect()
+            }
+            Notification::OrderExpiration(expiration) => {
+                let orphan = Orphan::Expiration(expiration.timestamp());
+                state.apply(&expiration.order_id, orphan).into_iter().collect()
+            }
+            _ => Vec::new(),
+        };
+        drop(state);
+        self.notify(changes);
+    }
+
+    /// Send `order` through `client`, tracking it from now on.
+    ///
+    /// # Panics
+    /// Panic if the order has no id, see `Order::with_order_id`.
+    pub fn order<C: ApiClient>(&self, client: &C, order: WithSymbol<&Order>)
+        -> Box<dyn Future<Item = Timestamped<OrderAck>, Error = OrderError> + Send + 'static>
+    {
+        self.order_sent(&order);
+        let order_id = order.order_id().unwrap().to_owned();
+        let manager = self.clone();
+        Box::new(client.order(order).then(move |result| {
+            manager.order_result(&order_id, result.as_ref());
+            result
+        }))
+    }
+
+    /// Send `cancel` through `client`, tracking the cancellation.
+    pub fn cancel<C: ApiClient>(&self, client: &C, cancel: WithSymbol<&Cancel>)
+        -> Box<dyn Future<Item = Timestamped<CancelAck>, Error = CancelError> + Send + 'static>
+    {
+        self.cancel_sent(&cancel);
+        let order_id = cancel.order_id().to_owned();
+        let manager = self.clone();
+        Box::new(client.cancel(cancel).then(move |result| {
+            manager.cancel_result(&order_id, result.as_ref());
+            result
+        }))
+    }
+
+    /// Wrap `stream` so that its notifications are applied to `self` before being
+    /// forwarded.
+    pub fn track<S: Stream<Item = Notification>>(&self, stream: S) -> Track<S> {
+        Track {
+            stream,
+            manager: self.clone(),
+        }
+    }
+
+    /// Return the order with id `order_id`, if tracked.
+    pub fn order_by_id(&self, order_id: &str) -> Option<ManagedOrder> {
+        self.state.lock().unwrap().orders.get(order_id).cloned()
+    }
+
+    /// Return all the tracked orders in the given `state`.
+    pub fn orders_in(&self, state: OrderState) -> Vec<ManagedOrder> {
+        self.filter(|order| order.state == state)
+    }
+
+    /// Return all the tracked orders which may still change, i.e. not in a terminal state.
+    pub fn open_orders(&self) -> Vec<ManagedOrder> {
+        self.filter(|order| !order.state.is_terminal())
+    }
+
+    /// Return all the tracked orders.
+    pub fn orders(&self) -> Vec<ManagedOrder> {
+        self.filter(|_| true)
+    }
+
+    fn filter<F: Fn(&ManagedOrder) -> bool>(&self, f: F) -> Vec<ManagedOrder> {
+        self.state.lock().unwrap().orders.values().filter(|order| f(order)).cloned().collect()
+    }
+
+    /// Stop tracking orders in a terminal state, and return them. Expired events of unknown
+    /// orders are dropped as well.
+    pub fn remove_terminated(&self) -> Vec<ManagedOrder> {
+        let mut state = self.state.lock().unwrap();
+        state.expire_orphans(timestamp_ms());
+        let terminated: Vec<_> = state.orders.values()
+            .filter(|order| order.state.is_terminal())
+            .map(|order| order.order_id.clone())
+            .collect();
+        terminated.iter().filter_map(|order_id| state.orders.remove(order_id)).collect()
+    }
+}
+
+/// A stream adaptor feeding the notifications of the underlying stream to an
+/// `OrderManager`, see `OrderManager::track`.
+pub struct Track<S> {
+    stream: S,
+    manager: OrderManager,
+}
+
+impl<S: Stream<Item = Notification>> Stream for Track<S> {
+    type Item = Notification;
+    type Error = S::Error;
+
+    fn poll(&mut self) -> Poll<Option<Notification>, S::Error> {
+        let notif = futures::try_ready!(self.stream.poll());
+        if let Some(notif) = &notif {
+            self.manager.on_notification(notif);
+        }
+        Ok(Async::Ready(notif))
+    }
+}
@@ -0,0 +1,154 @@
+#![cfg(test)]
+
+use std::sync::{Arc, Mutex};
+use crate::Side;
+use crate::tick::Tick;
+use crate::api::{
+    Notification,
+    Order,
+    OrderAck,
+    Cancel,
+    CancelAck,
+    OrderConfirmation,
+    OrderUpdate,
+    OrderExpiration,
+};
+use crate::api::symbol::{Symbol, IntoWithSymbol};
+use crate::api::timestamp::IntoTimestamped;
+use crate::api::errors::{
+    ApiError,
+    RestErrorKind,
+    OrderErrorKind,
+    RequestError,
+    OrderError,
+    CancelError,
+};
+use crate::api::channel::Disconnected;
+use crate::api::sim::Client as SimClient;
+use crate::api::order_manager::{OrderManager, OrderState};
+
+fn symbol() -> Symbol {
+    Symbol::new("BTCUSD", Tick::new(1), Tick::new(1)).unwrap()
+}
+
+fn update(order_id: &str, consumed_size: u64, remaining_size: u64) -> Notification {
+    Notification::OrderUpdate(OrderUpdate {
+        order_id: order_id.to_owned(),
+        consumed_size,
+        remaining_size,
+        consumed_price: 1000,
+        commission: 1,
+    }.with_timestamp(0))
+}
+
+fn confirmation(order_id: &str) -> Notification {
+    Notification::OrderConfirmation(OrderConfirmation {
+        order_id: order_id.to_owned(),
+        price: 1000,
+        size: 10,
+        side: Side::Bid,
+    }.with_timestamp(0))
+}
+
+#[test]
+fn fill_before_ack() {
+    let manager = OrderManager::new();
+    let states = Arc::new(Mutex::new(Vec::new()));
+    let recorded = states.clone();
+    manager.on_change(move |order, _| recorded.lock().unwrap().push(order.state));
+
+    let order = Order::new(1000, 10, Side::Bid).with_order_id::<SimClient>("a");
+    manager.order_sent(&order.with_symbol(symbol()));
+    manager.on_notification(&update("a", 4, 6));
+    manager.order_result("a", Ok(&OrderAck { order_id: "a".to_owned() }.timestamped()));
+    manager.on_notification(&confirmation("a"));
+    manager.on_notification(&update("a", 6, 0));
+    manager.on_notification(&Notification::OrderExpiration(OrderExpiration {
+        order_id: "a".to_owned(),
+    }.with_timestamp(0)));
+
+    let order = manager.order_by_id("a").unwrap();
+    assert_eq!((order.price, order.size), (1000, 10));
+    assert_eq!((order.filled, order.remaining, order.commission), (10, 0, 2));
+    assert_eq!(
+        *states.lock().unwrap(),
+        [OrderState::PendingNew, OrderState::PartiallyFilled, OrderState::Filled]
+    );
+    assert!(manager.open_orders().is_empty());
+    assert_eq!(manager.remove_terminated().len(), 1);
+    assert!(manager.orders().is_empty());
+}
+
+#[test]
+fn update_before_confirmation() {
+    let manager = OrderManager::new();
+    manager.on_notification(&update("b", 3, 7));
+    assert!(manager.order_by_id("b").is_none());
+
+    manager.on_notification(&confirmation("b"));
+    let order = manager.order_by_id("b").unwrap();
+    assert_eq!(order.state, OrderState::PartiallyFilled);
+    assert_eq!(order.remaining, 7);
+}
+
+#[test]
+fn cancel() {
+    let manager = OrderManager::new();
+    manager.on_notification(&confirmation("c"));
+
+    let cancel = Cancel::new("c".to_owned());
+    manager.cancel_sent(&cancel);
+    manager.on_notification(&update("c", 2, 8));
+    assert_eq!(manager.orders_in(OrderState::PendingCancel).len(), 1);
+
+    let error: CancelError = ApiError::RequestError(RequestError::new(Disconnected));
+    manager.cancel_result("c", Err(&error));
+    assert_eq!(manager.order_by_id("c").unwrap().state, OrderState::PartiallyFilled);
+
+    manager.cancel_sent(&cancel);
+    manager.cancel_result("c", Ok(&CancelAck.timestamped()));
+    assert_eq!(manager.order_by_id("c").unwrap().state, OrderState::Cancelled);
+
+    // Late events do not revive a terminated order.
+    manager.on_notification(&update("c", 1, 7));
+    assert_eq!(manager.order_by_id("c").unwrap().remaining, 8);
+}
+
+#[test]
+fn unknown_status() {
+    let manager = OrderManager::new();
+    for order_id in &["a", "b"] {
+        let order = Order::new(1000, 10, Side::Bid).with_order_id::<SimClient>(order_id);
+        manager.order_sent(&order.with_symbol(symbol()));
+    }
+
+    // The order may have reached the book: later stream events still apply.
+    let error: OrderError = ApiError::RestError(RestErrorKind::UnknownStatus.into());
+    manager.order_result("a", Err(&error));
+    assert_eq!(manager.order_by_id("a").unwrap().state, OrderState::PendingNew);
+    manager.on_notification(&update("a", 10, 0));
+    assert_eq!(manager.order_by_id("a").unwrap().state, OrderState::Filled);
+
+    let kind = RestErrorKind::Specific(OrderErrorKind::InsufficientBalance);
+    manager.order_result("b", Err(&ApiError::RestError(kind.into())));
+    assert_eq!(manager.order_by_id("b").unwrap().state, OrderState::Rejected);
+}
+
+#[test]
+fn expired_orphans() {
+    use crate::api::order_manager::ORPHAN_EXPIRY;
+
+    let manager = OrderManager::new();
+    manager.on_notification(&update("a", 4, 6));
+    manager.on_notification(&update("b", 4, 6));
+    manager.state.lock().unwrap().orphans.get_mut("b").unwrap().0 -= ORPHAN_EXPIRY;
+    manager.remove_terminated();
+
+    // The fill of `a` is still applied, the one of `b` expired.
+    for order_id in &["a", "b"] {
+        let order = Order::new(1000, 10, Side::Bid).with_order_id::<SimClient>(order_id);
+        manager.order_sent(&order.with_symbol(symbol()));
+    }
+    assert_eq!(manager.order_by_id("a").unwrap().filled, 4);
+    assert_eq!(manager.order_by_id("b").unwrap().filled, 0);
+}
@@ -0,0 +1,321 @@
+//! A module defining a portfolio, tracking positions and profit and loss from the order
+//! updates of one or several exchanges.
+//!
+//! Positions are kept per exchange and per symbol, and aggregated by asset once the base
+//! and quote assets of the symbols are known, see `Portfolio::set_assets`.
+
+mod test;
+
+use std::collections::HashMap;
+use crate::Side;
+use crate::tick::TickUnit;
+use crate::api::{Notification, OrderUpdate, Balances};
+use crate::api::symbol::Symbol;
+use crate::api::order_book::{LiveOrderBook, BookState};
+
+#[derive(Clone, PartialEq, Debug)]
+/// Position held on one symbol of one exchange.
+pub struct Position {
+    symbol: Symbol,
+
+    /// Net position, in size ticks: positive when long, negative when short.
+    net: i64,
+
+    /// Average entry price of the net position, in price ticks.
+    average_price: f64,
+
+    /// Realised profit, fees not deducted, in quote asset units.
+    realized: f64,
+
+    /// Total fees paid, in quote asset units.
+    fees: f64,
+
+    /// Last mark price, in quote asset units.
+    mark: Option<f64>,
+}
+
+impl Position {
+    fn new(symbol: Symbol) -> Self {
+        Position {
+            symbol,
+            net: 0,
+            average_price: 0.,
+            realized: 0.,
+            fees: 0.,
+            mark: None,
+        }
+    }
+
+    /// Symbol of the position.
+    pub fn symbol(&self) -> Symbol {
+        self.symbol
+    }
+
+    /// Net position in base asset units: positive when long, negative when short.
+    pub fn net(&self) -> f64 {
+        self.net as f64 / self.symbol.size_tick().ticks_per_unit() as f64
+    }
+
+    /// Average entry price of the net position, in quote asset units, `0` when flat.
+    pub fn average_price(&self) -> f64 {
+        self.average_price / self.symbol.price_tick().ticks_per_unit() as f64
+    }
+
+    /// Realised profit and loss, fees deducted, in quote asset units.
+    pub fn realized(&self) -> f64 {
+        self.realized - self.fees
+    }
+
+    /// Total fees paid, in quote asset units.
+    pub fn fees(&self) -> f64 {
+        self.fees
+    }
+
+    /// Last mark price, in quote asset units, if the position has been marked.
+    pub fn mark(&self) -> Option<f64> {
+        self.mark
+    }
+
+    /// Unrealised profit and loss of the net position against the last mark price, in
+    /// quote asset units, or `None` if the position has not been marked.
+    pub fn unrealized(&self) -> Option<f64> {
+        self.mark.map(|mark| (mark - self.average_price()) * self.net())
+    }
+
+    /// Convert a price in ticks times a size in ticks to quote asset units.
+    fn quote(&self, price: f64, size: i64) -> f64 {
+        let price_tick = self.symbol.price_tick().ticks_per_unit() as f64;
+        let size_tick = self.symbol.size_tick().ticks_per_unit() as f64;
+        price * size as f64 / (price_tick * size_tick)
+    }
+
+    /// Apply a fill of `size` at `price` on the given `side`, paying `commission`.
+    ///
+    /// # Note
+    /// The commission is assumed to be paid in the quote asset, which may not be the case
+    /// on binance.
+    fn fill(&mut self, side: Side, price: TickUnit, size: TickUnit, commission: TickUnit) {
+        let size = size as i64;
+        let signed = match side {
+            Side::Bid => size,
+            Side::Ask => -size,
+        };
+        let price = price as f64;
+
+        if self.net == 0 || (self.net > 0) == (signed > 0) {
+            let total = self.net.abs() + size;
+            self.average_price = (self.average_price * self.net.abs() as f64
+                + price * size as f64) / total as f64;
+        } else {
+            let closed = size.min(self.net.abs());
+            let direction = self.net.signum() as f64;
+            self.realized += self.quote((price - self.average_price) * direction, closed);
+
+            if size > self.net.abs() {
+                // The position was flipped.
+                self.average_price = price;
+            } else if size == self.net.abs() {
+                self.average_price = 0.;
+            }
+        }
+        self.net += signed;
+
+        self.fees += commission as f64 / self.symbol.commission_tick().ticks_per_unit() as f64;
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Debug, Default)]
+/// Holdings and profit and loss of one asset, aggregated across exchanges.
+pub struct AssetSummary {
+    /// Sum of the balances reported by the exchanges, see `Portfolio::set_balances`.
+    pub balance: f64,
+
+    /// Net position on the symbols having this asset as base asset.
+    pub position: f64,
+
+    /// Realised profit and loss, fees deducted, on the symbols having this asset as quote
+    /// asset.
+    pub realized: f64,
+
+    /// Unrealised profit and loss on the symbols having this asset as quote asset, only
+    /// counting the marked positions.
+    pub unrealized: f64,
+}
+
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+/// Base and quote assets of a symbol.
+struct Assets {
+    base: String,
+    quote: String,
+}
+
+#[derive(Clone, Default, Debug)]
+/// Positions and profit and loss, per exchange and per symbol.
+///
+/// Fills are applied from the `OrderUpdate` notifications, the side of the filled order
+/// being taken from its `OrderConfirmation`, or from `Portfolio::register_order` for orders
+/// whose updates may arrive before their confirmation. Updates of an order whose side is
+/// still unknown are kept until it becomes known. Order ids are scoped by exchange.
+pub struct Portfolio {
+    /// Positions keyed by exchange name and symbol name.
+    positions: HashMap<(String, String), Position>,
+
+    /// Assets keyed by symbol name.
+    assets: HashMap<String, Assets>,
+
+    /// Balances keyed by exchange name and asset.
+    balances: HashMap<(String, String), f64>,
+
+    /// Side of the orders which may still be filled, keyed by exchange name and order id.
+    sides: HashMap<(String, String), Side>,
+
+    /// Updates of orders whose side is unknown, keyed by exchange name and order id.
+    pending: HashMap<(String, String), Vec<(Symbol, OrderUpdate)>>,
+}
+
+impl Portfolio {
+    /// Return an empty `Portfolio`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the base and quote assets of the symbol named `symbol`, e.g. `BTC` and `USDT`
+    /// for binance `BTCUSDT`. The same assets are used on all exchanges.
+    pub fn set_assets(&mut self, symbol: &str, base: &str, quote: &str) {
+        self.assets.insert(symbol.to_owned(), Assets {
+            base: base.to_owned(),
+            quote: quote.to_owned(),
+        });
+    }
+
+    /// Return the base and quote assets of the symbol named `symbol`, if set.
+    pub fn assets_of(&self, symbol: &str) -> Option<(&str, &str)> {
+        self.assets.get(symbol).map(|assets| (assets.base.as_ref(), assets.quote.as_ref()))
+    }
+
+    /// Replace the balances of `exchange`, as returned by `ApiClient::balances`. Balances
+    /// which cannot be parsed are ignored.
+    pub fn set_balances(&mut self, exchange: &str, balances: &Balances) {
+        self.balances.retain(|(name, _), _| name != exchange);
+        for (asset, balance) in balances {
+            let free = balance.free.parse::<f64>();
+            let locked = balance.locked.parse::<f64>();
+            if let (Ok(free), Ok(locked)) = (free, locked) {
+                self.balances.insert((exchange.to_owned(), asset.clone()), free + locked);
+            }
+        }
+    }
+
+    /// Record the side of the order `order_id` of `exchange`, so that its fills can be
+    /// applied even if they arrive before its confirmation.
+    pub fn register_order(&mut self, exchange: &str, order_id: &str, side: Side) {
+        let key = (exchange.to_owned(), order_id.to_owned());
+        let pending = self.pending.remove(&key).unwrap_or_default();
+        self.sides.insert(key, side);
+        for (symbol, update) in pending {
+            self.on_fill(exchange, symbol, side, &update);
+        }
+    }
+
+    /// Apply an order-related notification of the `symbol` stream of `exchange`, other
+    /// notifications are ignored.
+    pub fn on_notification(&mut self, exchange: &str, symbol: Symbol, notif: &Notification) {
+        match notif {
+            Notification::OrderConfirmation(confirmation) => {
+                self.register_order(exchange, &confirmation.order_id, confirmation.side);
+            }
+            Notification::OrderUpdate(update) => {
+                let key = (exchange.to_owned(), update.order_id.clone());
+                match self.sides.get(&key) {
+                    Some(&side) => self.on_fill(exchange, symbol, side, update),
+                    None => self.pending.entry(key)
+                        .or_insert_with(Vec::new)
+                        .push((symbol, (**update).clone())),
+                }
+            }
+            Notification::OrderExpiration(expiration) => {
+                self.sides.remove(&(exchange.to_owned(), expiration.order_id.clone()));
+            }
+            _ => (),
+        }
+    }
+
+    /// Apply the fill described by `update` to the position on `symbol` of `exchange`.
+    pub fn on_fill(&mut self, exchange: &str, symbol: Symbol, side: Side, update: &OrderUpdate) {
+        self.position_mut(exchange, symbol).fill(
+            side,
+            update.consumed_price,
+            update.consumed_size,
+            update.commission
+        );
+
+        if update.remaining_size == 0 {
+            self.sides.remove(&(exchange.to_owned(), update.order_id.clone()));
+        }
+    }
+
+    fn position_mut(&mut self, exchange: &str, symbol: Symbol) -> &mut Position {
+        self.positions.entry((exchange.to_owned(), symbol.name().to_owned()))
+            .or_insert_with(|| Position::new(symbol))
+    }
+
+    /// Mark the position on `symbol` of `exchange` at `price`, in quote asset units.
+    pub fn mark(&mut self, exchange: &str, symbol: Symbol, price: f64) {
+        self.position_mut(exchange, symbol).mark = Some(price);
+    }
+
+    /// Mark the position on `symbol` of `exchange` at the mid price of `order_book`.
+    /// Return `false` if the order book is disconnected or has an empty side, in which
+    /// case the mark price is left unchanged.
+    pub fn mark_to_book(&mut self, exchange: &str, symbol: Symbol, order_book: &LiveOrderBook)
+        -> bool
+    {
+        let mid = match order_book.order_book() {
+            BookState::Live(order_book) => order_book.mid(),
+            BookState::Disconnected => None,
+        };
+
+        match mid {
+            Some(mid) => {
+                let price = mid / symbol.price_tick().ticks_per_unit() as f64;
+                self.mark(exchange, symbol, price);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Return the position on the symbol named `symbol` of `exchange`, if any.
+    pub fn position(&self, exchange: &str, symbol: &str) -> Option<&Position> {
+        self.positions.get(&(exchange.to_owned(), symbol.to_owned()))
+    }
+
+    /// Iterator over all the positions, along with their exchange name.
+    pub fn positions(&self) -> impl Iterator<Item = (&str, &Position)> {
+        self.positions.iter().map(|((exchange, _), position)| (exchange.as_ref(), position))
+    }
+
+    /// Return the holdings and profit and loss of each asset, aggregated across exchanges.
+    /// Positions on symbols without known assets are not taken into account.
+    pub fn assets(&self) -> HashMap<String, AssetSummary> {
+        let mut summaries: HashMap<String, AssetSummary> = HashMap::new();
+
+        for ((_, asset), balance) in &self.balances {
+            summaries.entry(asset.clone()).or_default().balance += balance;
+        }
+
+        for ((_, symbol), position) in &self.positions {
+            let assets = match self.assets.get(symbol) {
+                Some(assets) => assets,
+                None => continue,
+            };
+
+            summaries.entry(assets.base.clone()).or_default().position += position.net();
+
+            let quote = summaries.entry(assets.quote.clone()).or_default();
+            quote.realized += position.realized();
+            quote.unrealized += position.unrealized().unwrap_or(0.);
+        }
+        summaries
+    }
+}
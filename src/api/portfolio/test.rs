@@ -0,0 +1,79 @@
+#![cfg(test)]
+
+use std::collections::HashMap;
+use crate::Side;
+use crate::tick::Tick;
+use crate::api::{Notification, OrderConfirmation, OrderUpdate, Balance};
+use crate::api::symbol::Symbol;
+use crate::api::timestamp::IntoTimestamped;
+use crate::api::portfolio::Portfolio;
+
+fn confirmation(order_id: &str, side: Side) -> Notification {
+    Notification::OrderConfirmation(OrderConfirmation {
+        order_id: order_id.to_owned(),
+        price: 100,
+        size: 10,
+        side,
+    }.with_timestamp(0))
+}
+
+fn fill(order_id: &str, consumed_price: u64, consumed_size: u64) -> Notification {
+    Notification::OrderUpdate(OrderUpdate {
+        order_id: order_id.to_owned(),
+        consumed_size,
+        remaining_size: 1,
+        consumed_price,
+        commission: 1,
+    }.with_timestamp(0))
+}
+
+#[test]
+fn positions() {
+    let symbol = Symbol::new("BTCUSD", Tick::new(1), Tick::new(1)).unwrap();
+    let mut portfolio = Portfolio::new();
+    portfolio.set_assets("BTCUSD", "BTC", "USD");
+
+    let notifs = vec![
+        confirmation("buy", Side::Bid),
+        fill("buy", 100, 2),
+        fill("buy", 110, 2),
+
+        // Fill received before the confirmation of the order.
+        fill("sell", 120, 3),
+        confirmation("sell", Side::Ask),
+
+        // Flip the position.
+        fill("sell", 100, 2),
+    ];
+    for notif in &notifs {
+        portfolio.on_notification("sim", symbol, notif);
+    }
+
+    // The same order id on another exchange refers to another order.
+    portfolio.on_notification("other", symbol, &fill("buy", 100, 5));
+    assert!(portfolio.position("other", "BTCUSD").is_none());
+
+    let position = portfolio.position("sim", "BTCUSD").unwrap();
+    assert_eq!(position.net(), -1.);
+    assert_eq!(position.average_price(), 100.);
+    assert_eq!(position.fees(), 4.);
+    assert_eq!(position.realized(), 3. * 15. - 5. - 4.);
+    assert_eq!(position.unrealized(), None);
+
+    portfolio.mark("sim", symbol, 90.);
+    assert_eq!(portfolio.position("sim", "BTCUSD").unwrap().unrealized(), Some(10.));
+
+    let mut balances = HashMap::new();
+    balances.insert("USD".to_owned(), Balance {
+        free: "1000.5".to_owned(),
+        locked: "2".to_owned(),
+    });
+    portfolio.set_balances("sim", &balances);
+    portfolio.set_balances("other", &balances);
+
+    let assets = portfolio.assets();
+    assert_eq!(assets["BTC"].position, -1.);
+    assert_eq!(assets["USD"].balance, 2005.);
+    assert_eq!(assets["USD"].realized, 36.);
+    assert_eq!(assets["USD"].unrealized, 10.);
+}
@@ -1,5 +1,5 @@
 use std::fmt;
-use openssl::{sign::Signer, hash::MessageDigest, pkey::{PKey, Private}};
+use crate::api::keys::RequestSigner;
 
 crate struct QueryString {
     query: String,
@@ -34,11 +34,11 @@ impl QueryString {
         self.query
     }
 
-    crate fn into_string_with_signature(mut self, key: &PKey<Private>) -> String {
-        let mut signer = Signer::new(MessageDigest::sha256(), key).unwrap();
-        signer.update(self.query.as_bytes()).unwrap();
-        let signature = hex::encode(&signer.sign_to_vec().unwrap());
+    crate fn into_string_with_signature(mut self, signer: &dyn RequestSigner)
+        -> Result<String, failure::Error>
+    {
+        let signature = hex::encode(&signer.sign(self.query.as_bytes())?);
         self.push("signature", &signature);
-        self.query
+        Ok(self.query)
     }
 }
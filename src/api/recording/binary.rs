@@ -0,0 +1,563 @@
+//! Compact binary encoding of records. All integers are little-endian. A recording starts
+//! with the header `magic: [u8; 4], version: u32`, where `magic` is `b"TRDR"`, followed by
+//! the records. A record is laid out as `timestamp: u64, tag: u8, payload`, where the
+//! payload depends on the notification kind:
+//! * `Trade`: `timestamp: u64, price: u64, size: u64, maker_side: u8`
+//! * `LimitUpdates`: `count: u32`, then `count` times `timestamp: u64, price: u64, size: u64,
+//!   side: u8`
+//! * `OrderConfirmation`: `timestamp: u64, order_id, price: u64, size: u64, side: u8`
+//! * `OrderUpdate`: `timestamp: u64, order_id, consumed_size: u64, remaining_size: u64,
+//!   consumed_price: u64, commission: u64`
+//! * `OrderExpiration`: `timestamp: u64, order_id`
+//! * `L3Updates`: `count: u32`, then `count` times `timestamp: u64, kind: u8, order_id`
+//!   followed by `price: u64, size: u64, side: u8` for `Insert`, `size: u64` for `Reduce`
+//!   and `Resize`, nothing for `Remove`
+//! * `Integrity`: `timestamp: u64, kind: u8` followed by `best_bid: u64, best_ask: u64` for
+//!   `Crossed`, `price: u64` for `Locked`, `side: u8, price: u64, last_update: u64` for
+//!   `StaleLevel`, `expected: u32, computed: u32` for `ChecksumMismatch`, and `count: u32`
+//!   then `count` times `price: u64, size: u64, side: u8` for `Discrepancies`
+//! * `Overflow`: `timestamp: u64, dropped: u64, conflated: u64`
+//! * `BestBidOffer`: `timestamp: u64, bid_price: u64, bid_size: u64, ask_price: u64,
+//!   ask_size: u64`
+//!
+//! Each `timestamp: u64` of a timestamped item above is followed by `presence: u8`, whose
+//! bit 0 indicates that `received: u64` follows, bit 1 that `first: u64, last: u64` (the
+//! sequence number) follows and bit 2 that `sent: u64` follows, in that order. Bit 3 is
+//! set if the timestamp was taken from the local clock.
+//!
+//! Strings are encoded as `len: u32` followed by `len` bytes of UTF-8.
+
+use std::io::{self, Read, Write};
+use failure::format_err;
+use crate::Side;
+use crate::order_book::LimitUpdate;
+use crate::order_book::l3::L3Update;
+use crate::api::{
+    Notification,
+    Trade,
+    BestBidOffer,
+    OrderConfirmation,
+    OrderUpdate,
+    OrderExpiration,
+};
+use crate::api::integrity::IntegrityIssue;
+use crate::api::channel::Overflow;
+use crate::api::timestamp::{Timestamp, TimestampUs, Timestamped, IntoTimestamped, Sequence};
+
+const MAGIC: &[u8; 4] = b"TRDR";
+
+/// Version of the layout, checked when reading a recording. Must be bumped on every change
+/// of the layout, including the addition of a tag.
+const VERSION: u32 = 1;
+
+const TRADE: u8 = 0;
+const LIMIT_UPDATES: u8 = 1;
+const ORDER_CONFIRMATION: u8 = 2;
+const ORDER_UPDATE: u8 = 3;
+const ORDER_EXPIRATION: u8 = 4;
+const L3_UPDATES: u8 = 5;
+const INTEGRITY: u8 = 6;
+const OVERFLOW: u8 = 7;
+const BEST_BID_OFFER: u8 = 8;
+
+const INSERT: u8 = 0;
+const REDUCE: u8 = 1;
+const RESIZE: u8 = 2;
+const REMOVE: u8 = 3;
+
+const CROSSED: u8 = 0;
+const LOCKED: u8 = 1;
+const STALE_LEVEL: u8 = 2;
+const CHECKSUM_MISMATCH: u8 = 3;
+const DISCREPANCIES: u8 = 4;
+const SNAPSHOT_FAILED: u8 = 5;
+
+const HAS_RECEIVED: u8 = 0b0001;
+const HAS_SEQUENCE: u8 = 0b0010;
+const HAS_SENT: u8 = 0b0100;
+const LOCAL: u8 = 0b1000;
+
+fn write_u64<W: Write>(writer: &mut W, value: u64) -> io::Result<()> {
+    let mut buf = [0; 8];
+    for (i, byte) in buf.iter_mut().enumerate() {
+        *byte = (value >> (8 * i)) as u8;
+    }
+    writer.write_all(&buf)
+}
+
+fn write_u32<W: Write>(writer: &mut W, value: u32) -> io::Result<()> {
+    let mut buf = [0; 4];
+    for (i, byte) in buf.iter_mut().enumerate() {
+        *byte = (value >> (8 * i)) as u8;
+    }
+    writer.write_all(&buf)
+}
+
+fn write_side<W: Write>(writer: &mut W, side: Side) -> io::Result<()> {
+    writer.write_all(&[match side {
+        Side::Bid => 0,
+        Side::Ask => 1,
+    }])
+}
+
+fn write_str<W: Write>(writer: &mut W, value: &str) -> io::Result<()> {
+    write_u32(writer, value.len() as u32)?;
+    writer.write_all(value.as_bytes())
+}
+
+fn read_u64<R: Read>(reader: &mut R) -> io::Result<u64> {
+    let mut buf = [0; 8];
+    reader.read_exact(&mut buf)?;
+    Ok(buf.iter().rev().fold(0, |acc, &byte| (acc << 8) | u64::from(byte)))
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> io::Result<u32> {
+    let mut buf = [0; 4];
+    reader.read_exact(&mut buf)?;
+    Ok(buf.iter().rev().fold(0, |acc, &byte| (acc << 8) | u32::from(byte)))
+}
+
+fn read_u8<R: Read>(reader: &mut R) -> io::Result<u8> {
+    let mut buf = [0; 1];
+    reader.read_exact(&mut buf)?;
+    Ok(buf[0])
+}
+
+fn read_side<R: Read>(reader: &mut R) -> Result<Side, failure::Error> {
+    match read_u8(reader)? {
+        0 => Ok(Side::Bid),
+        1 => Ok(Side::Ask),
+        other => Err(format_err!("invalid side `{}`", other)),
+    }
+}
+
+fn read_str<R: Read>(reader: &mut R) -> Result<String, failure::Error> {
+    let len = read_u32(reader)? as usize;
+    let mut buf = vec![0; len];
+    reader.read_exact(&mut buf)?;
+    Ok(String::from_utf8(buf)?)
+}
+
+/// Timestamps and sequence number of a `Timestamped` item.
+struct Stamps {
+    timestamp: Timestamp,
+    local: bool,
+    sent: Option<Timestamp>,
+    received: Option<TimestampUs>,
+    sequence: Option<Sequence>,
+}
+
+trait WithStamps: Sized {
+    fn with_stamps(self, stamps: Stamps) -> Timestamped<Self>;
+}
+
+impl<T> WithStamps for T {
+    fn with_stamps(self, stamps: Stamps) -> Timestamped<Self> {
+        let mut item = if stamps.local {
+            self.with_local_timestamp(stamps.timestamp)
+        } else {
+            self.with_timestamp(stamps.timestamp)
+        };
+        if let Some(sent) = stamps.sent {
+            item = item.with_sent(sent);
+        }
+        if let Some(received) = stamps.received {
+            item = item.with_received(received);
+        }
+        if let Some(sequence) = stamps.sequence {
+            item = item.with_sequence(sequence);
+        }
+        item
+    }
+}
+
+fn write_stamps<W: Write, T>(writer: &mut W, item: &Timestamped<T>) -> io::Result<()> {
+    write_u64(writer, item.timestamp())?;
+
+    let mut presence = 0;
+    if item.received().is_some() {
+        presence |= HAS_RECEIVED;
+    }
+    if item.sequence().is_some() {
+        presence |= HAS_SEQUENCE;
+    }
+    if item.sent().is_some() {
+        presence |= HAS_SENT;
+    }
+    if item.exchange_timestamp().is_none() {
+        presence |= LOCAL;
+    }
+    writer.write_all(&[presence])?;
+
+    if let Some(received) = item.received() {
+        write_u64(writer, received.0)?;
+    }
+    if let Some(sequence) = item.sequence() {
+        write_u64(writer, sequence.first)?;
+        write_u64(writer, sequence.last)?;
+    }
+    if let Some(sent) = item.sent() {
+        write_u64(writer, sent)?;
+    }
+    Ok(())
+}
+
+fn read_stamps<R: Read>(reader: &mut R) -> Result<Stamps, failure::Error> {
+    let timestamp = read_u64(reader)?;
+    let presence = read_u8(reader)?;
+    if presence & !(HAS_RECEIVED | HAS_SEQUENCE | HAS_SENT | LOCAL) != 0 {
+        return Err(format_err!("invalid presence byte `{}`", presence));
+    }
+
+    let received = if presence & HAS_RECEIVED != 0 {
+        Some(TimestampUs(read_u64(reader)?))
+    } else {
+        None
+    };
+    let sequence = if presence & HAS_SEQUENCE != 0 {
+        Some(Sequence {
+            first: read_u64(reader)?,
+            last: read_u64(reader)?,
+        })
+    } else {
+        None
+    };
+    let sent = if presence & HAS_SENT != 0 {
+        Some(read_u64(reader)?)
+    } else {
+        None
+    };
+
+    Ok(Stamps {
+        timestamp,
+        local: presence & LOCAL != 0,
+        sent,
+        received,
+        sequence,
+    })
+}
+
+crate fn write_record<W: Write>(
+    writer: &mut W,
+    timestamp: Timestamp,
+    notification: &Notification
+) -> io::Result<()>
+{
+    write_u64(writer, timestamp)?;
+
+    match notification {
+        Notification::Trade(trade) => {
+            writer.write_all(&[TRADE])?;
+            write_stamps(writer, trade)?;
+            write_u64(writer, trade.price)?;
+            write_u64(writer, trade.size)?;
+            write_side(writer, trade.maker_side)?;
+        }
+
+        Notification::LimitUpdates(updates) => {
+            writer.write_all(&[LIMIT_UPDATES])?;
+            write_u32(writer, updates.len() as u32)?;
+            for update in updates {
+                write_stamps(writer, update)?;
+                write_u64(writer, update.price)?;
+                write_u64(writer, update.size)?;
+                write_side(writer, update.side)?;
+            }
+        }
+
+        Notification::OrderConfirmation(confirmation) => {
+            writer.write_all(&[ORDER_CONFIRMATION])?;
+            write_stamps(writer, confirmation)?;
+            write_str(writer, &confirmation.order_id)?;
+            write_u64(writer, confirmation.price)?;
+            write_u64(writer, confirmation.size)?;
+            write_side(writer, confirmation.side)?;
+        }
+
+        Notification::OrderUpdate(update) => {
+            writer.write_all(&[ORDER_UPDATE])?;
+            write_stamps(writer, update)?;
+            write_str(writer, &update.order_id)?;
+            write_u64(writer, update.consumed_size)?;
+            write_u64(writer, update.remaining_size)?;
+            write_u64(writer, update.consumed_price)?;
+            write_u64(writer, update.commission)?;
+        }
+
+        Notification::OrderExpiration(expiration) => {
+            writer.write_all(&[ORDER_EXPIRATION])?;
+            write_stamps(writer, expiration)?;
+            write_str(writer, &expiration.order_id)?;
+        }
+
+        Notification::L3Updates(updates) => {
+            writer.write_all(&[L3_UPDATES])?;
+            write_u32(writer, updates.len() as u32)?;
+            for update in updates {
+                write_stamps(writer, update)?;
+                match &**update {
+                    L3Update::Insert { order_id, price, size, side } => {
+                        writer.write_all(&[INSERT])?;
+                        write_str(writer, order_id)?;
+                        write_u64(writer, *price)?;
+                        write_u64(writer, *size)?;
+                        write_side(writer, *side)?;
+                    }
+                    L3Update::Reduce { order_id, size } => {
+                        writer.write_all(&[REDUCE])?;
+                        write_str(writer, order_id)?;
+                        write_u64(writer, *size)?;
+                    }
+                    L3Update::Resize { order_id, size } => {
+                        writer.write_all(&[RESIZE])?;
+                        write_str(writer, order_id)?;
+                        write_u64(writer, *size)?;
+                    }
+                    L3Update::Remove { order_id } => {
+                        writer.write_all(&[REMOVE])?;
+                        write_str(writer, order_id)?;
+                    }
+                }
+            }
+        }
+
+        Notification::Integrity(issue) => {
+            writer.write_all(&[INTEGRITY])?;
+            write_stamps(writer, issue)?;
+            match &**issue {
+                IntegrityIssue::Crossed { best_bid, best_ask } => {
+                    writer.write_all(&[CROSSED])?;
+                    write_u64(writer, *best_bid)?;
+                    write_u64(writer, *best_ask)?;
+                }
+                IntegrityIssue::Locked { price } => {
+                    writer.write_all(&[LOCKED])?;
+                    write_u64(writer, *price)?;
+                }
+                IntegrityIssue::StaleLevel { side, price, last_update } => {
+                    writer.write_all(&[STALE_LEVEL])?;
+                    write_side(writer, *side)?;
+                    write_u64(writer, *price)?;
+                    write_u64(writer, *last_update)?;
+                }
+                IntegrityIssue::ChecksumMismatch { expected, computed } => {
+                    writer.write_all(&[CHECKSUM_MISMATCH])?;
+                    write_u32(writer, *expected)?;
+                    write_u32(writer, *computed)?;
+                }
+                IntegrityIssue::Discrepancies(updates) => {
+                    writer.write_all(&[DISCREPANCIES])?;
+                    write_u32(writer, updates.len() as u32)?;
+                    for update in updates {
+                        write_u64(writer, update.price)?;
+                        write_u64(writer, update.size)?;
+                        write_side(writer, update.side)?;
+                    }
+                }
+                IntegrityIssue::SnapshotFailed => {
+                    writer.write_all(&[SNAPSHOT_FAILED])?;
+                }
+            }
+        }
+
+        Notification::BestBidOffer(bbo) => {
+            writer.write_all(&[BEST_BID_OFFER])?;
+            write_stamps(writer, bbo)?;
+            write_u64(writer, bbo.bid_price)?;
+            write_u64(writer, bbo.bid_size)?;
+            write_u64(writer, bbo.ask_price)?;
+            write_u64(writer, bbo.ask_size)?;
+        }
+
+        Notification::Overflow(overflow) => {
+            writer.write_all(&[OVERFLOW])?;
+            write_stamps(writer, overflow)?;
+            write_u64(writer, overflow.dropped)?;
+            write_u64(writer, overflow.conflated)?;
+        }
+    }
+    Ok(())
+}
+
+/// Return `Ok(None)` if the reader was exhausted before the record started.
+crate fn write_header<W: Write>(writer: &mut W) -> io::Result<()> {
+    writer.write_all(MAGIC)?;
+    write_u32(writer, VERSION)
+}
+
+/// Check the header of a recording, return `Ok(false)` if the recording is empty.
+crate fn read_header<R: Read>(reader: &mut R) -> Result<bool, failure::Error> {
+    let mut magic = [0; 4];
+    match reader.read_exact(&mut magic) {
+        Ok(()) => (),
+        Err(ref err) if err.kind() == io::ErrorKind::UnexpectedEof => return Ok(false),
+        Err(err) => return Err(err.into()),
+    }
+    if &magic != MAGIC {
+        return Err(format_err!("not a binary recording"));
+    }
+
+    let version = read_u32(reader)?;
+    if version != VERSION {
+        return Err(format_err!(
+            "unsupported recording version {}, expected {}",
+            version,
+            VERSION
+        ));
+    }
+    Ok(true)
+}
+
+crate fn read_record<R: Read>(reader: &mut R)
+    -> Result<Option<(Timestamp, Notification)>, failure::Error>
+{
+    let timestamp = match read_u64(reader) {
+        Ok(timestamp) => timestamp,
+        Err(ref err) if err.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(err) => return Err(err.into()),
+    };
+
+    let notification = match read_u8(reader)? {
+        TRADE => {
+            let stamps = read_stamps(reader)?;
+            Notification::Trade(Trade {
+                price: read_u64(reader)?,
+                size: read_u64(reader)?,
+                maker_side: read_side(reader)?,
+            }.with_stamps(stamps))
+        }
+
+        LIMIT_UPDATES => {
+            let count = read_u32(reader)?;
+            let mut updates = Vec::new();
+            for _ in 0..count {
+                let stamps = read_stamps(reader)?;
+                updates.push(LimitUpdate {
+                    price: read_u64(reader)?,
+                    size: read_u64(reader)?,
+                    side: read_side(reader)?,
+                }.with_stamps(stamps));
+            }
+            Notification::LimitUpdates(updates)
+        }
+
+        ORDER_CONFIRMATION => {
+            let stamps = read_stamps(reader)?;
+            Notification::OrderConfirmation(OrderConfirmation {
+                order_id: read_str(reader)?,
+                price: read_u64(reader)?,
+                size: read_u64(reader)?,
+                side: read_side(reader)?,
+            }.with_stamps(stamps))
+        }
+
+        ORDER_UPDATE => {
+            let stamps = read_stamps(reader)?;
+            Notification::OrderUpdate(OrderUpdate {
+                order_id: read_str(reader)?,
+                consumed_size: read_u64(reader)?,
+                remaining_size: read_u64(reader)?,
+                consumed_price: read_u64(reader)?,
+                commission: read_u64(reader)?,
+            }.with_stamps(stamps))
+        }
+
+        ORDER_EXPIRATION => {
+            let stamps = read_stamps(reader)?;
+            Notification::OrderExpiration(OrderExpiration {
+                order_id: read_str(reader)?,
+            }.with_stamps(stamps))
+        }
+
+        L3_UPDATES => {
+            let count = read_u32(reader)?;
+            let mut updates = Vec::new();
+            for _ in 0..count {
+                let stamps = read_stamps(reader)?;
+                let kind = read_u8(reader)?;
+                let order_id = read_str(reader)?;
+                let update = match kind {
+                    INSERT => L3Update::Insert {
+                        order_id,
+                        price: read_u64(reader)?,
+                        size: read_u64(reader)?,
+                        side: read_side(reader)?,
+                    },
+                    REDUCE => L3Update::Reduce {
+                        order_id,
+                        size: read_u64(reader)?,
+                    },
+                    RESIZE => L3Update::Resize {
+                        order_id,
+                        size: read_u64(reader)?,
+                    },
+                    REMOVE => L3Update::Remove {
+                        order_id,
+                    },
+                    other => return Err(format_err!("invalid L3 update kind `{}`", other)),
+                };
+                updates.push(update.with_stamps(stamps));
+            }
+            Notification::L3Updates(updates)
+        }
+
+        INTEGRITY => {
+            let stamps = read_stamps(reader)?;
+            let issue = match read_u8(reader)? {
+                CROSSED => IntegrityIssue::Crossed {
+                    best_bid: read_u64(reader)?,
+                    best_ask: read_u64(reader)?,
+                },
+                LOCKED => IntegrityIssue::Locked {
+                    price: read_u64(reader)?,
+                },
+                STALE_LEVEL => IntegrityIssue::StaleLevel {
+                    side: read_side(reader)?,
+                    price: read_u64(reader)?,
+                    last_update: read_u64(reader)?,
+                },
+                CHECKSUM_MISMATCH => IntegrityIssue::ChecksumMismatch {
+                    expected: read_u32(reader)?,
+                    computed: read_u32(reader)?,
+                },
+                DISCREPANCIES => {
+                    let count = read_u32(reader)?;
+                    let mut updates = Vec::new();
+                    for _ in 0..count {
+                        updates.push(LimitUpdate {
+                            price: read_u64(reader)?,
+                            size: read_u64(reader)?,
+                            side: read_side(reader)?,
+                        });
+                    }
+                    IntegrityIssue::Discrepancies(updates)
+                }
+                SNAPSHOT_FAILED => IntegrityIssue::SnapshotFailed,
+                other => return Err(format_err!("invalid integrity issue kind `{}`", other)),
+            };
+            Notification::Integrity(issue.with_stamps(stamps))
+        }
+
+        BEST_BID_OFFER => {
+            let stamps = read_stamps(reader)?;
+            Notification::BestBidOffer(BestBidOffer {
+                bid_price: read_u64(reader)?,
+                bid_size: read_u64(reader)?,
+                ask_price: read_u64(reader)?,
+                ask_size: read_u64(reader)?,
+            }.with_stamps(stamps))
+        }
+
+        OVERFLOW => {
+            let stamps = read_stamps(reader)?;
+            Notification::Overflow(Overflow {
+                dropped: read_u64(reader)?,
+                conflated: read_u64(reader)?,
+            }.with_stamps(stamps))
+        }
+
+        other => return Err(format_err!("invalid record tag `{}`", other)),
+    };
+
+    Ok(Some((timestamp, notification)))
+}
@@ -0,0 +1,318 @@
+//! A module defining helpers for recording notification streams to disk and replaying them.
+//!
+//! Each record holds a notification along with the local timestamp at which it was received,
+//! which is used for pacing the replay.
+
+mod binary;
+mod test;
+
+use std::io::{self, Read, Write, BufRead, BufReader};
+use std::path::Path;
+use std::time::{Duration, Instant};
+use futures::prelude::*;
+use futures::sync::mpsc::{channel, Receiver};
+use serde_derive::{Serialize, Deserialize};
+use log::error;
+use crate::api::Notification;
+use crate::api::timestamp::{Timestamp, timestamp_ms};
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+/// Format of a recording.
+pub enum Format {
+    /// One JSON object per line, human readable.
+    JsonLines,
+
+    /// A compact binary encoding.
+    Binary,
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+/// Pacing of a replay.
+pub enum Pacing {
+    /// Notifications are delivered with the same delays as when they were recorded.
+    RealTime,
+
+    /// Delays between notifications are divided by the given factor, which must be a
+    /// finite positive number.
+    Accelerated(f64),
+
+    /// Notifications are delivered without any delay.
+    AsFastAsPossible,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Record<N> {
+    timestamp: Timestamp,
+    notification: N,
+}
+
+/// Write a record, preceded by the header of the recording if `header` is `true`.
+fn write_record<W: Write>(
+    writer: &mut W,
+    format: Format,
+    header: bool,
+    timestamp: Timestamp,
+    notification: &Notification
+) -> Result<(), failure::Error>
+{
+    if header && format == Format::Binary {
+        binary::write_header(writer)?;
+    }
+
+    match format {
+        Format::JsonLines => {
+            serde_json::to_writer(&mut *writer, &Record {
+                timestamp,
+                notification,
+            })?;
+            writer.write_all(b"\n")?;
+        }
+        Format::Binary => binary::write_record(writer, timestamp, notification)?,
+    }
+    Ok(())
+}
+
+/// Return `Ok(None)` once the end of the recording has been reached.
+fn read_record<R: BufRead>(reader: &mut R, format: Format)
+    -> Result<Option<(Timestamp, Notification)>, failure::Error>
+{
+    match format {
+        Format::JsonLines => {
+            let mut line = String::new();
+            loop {
+                line.clear();
+                if reader.read_line(&mut line)? == 0 {
+                    return Ok(None);
+                }
+                if !line.trim().is_empty() {
+                    break;
+                }
+            }
+            let record: Record<Notification> = serde_json::from_str(&line)?;
+            Ok(Some((record.timestamp, record.notification)))
+        }
+        Format::Binary => Ok(binary::read_record(reader)?),
+    }
+}
+
+/// A stream adaptor writing each notification going through it to an underlying writer.
+/// Writes are append-only, one record per notification: wrap the writer in a
+/// `BufWriter` if needed.
+///
+/// # Note
+/// If a write fails, the error is logged and the recording stops, but notifications
+/// are still forwarded.
+pub struct Recorder<S, W: Write> {
+    stream: S,
+    writer: Option<W>,
+    format: Format,
+    header: bool,
+}
+
+impl<S, W: Write> Recorder<S, W> {
+    /// Record notifications from `stream` into `writer`, in the given format. `writer`
+    /// receives a whole recording, header included.
+    pub fn new(stream: S, writer: W, format: Format) -> Self {
+        Recorder {
+            stream,
+            writer: Some(writer),
+            format,
+            header: true,
+        }
+    }
+}
+
+impl<S> Recorder<S, io::BufWriter<std::fs::File>> {
+    /// Record notifications from `stream` into the file at `path`, which is created if it
+    /// does not exist and appended to otherwise. A binary recording can only be appended
+    /// to if it has the same layout version.
+    pub fn create<P: AsRef<Path>>(stream: S, path: P, format: Format) -> io::Result<Self> {
+        let path = path.as_ref();
+        let file = std::fs::OpenOptions::new().create(true).append(true).open(path)?;
+
+        let empty = file.metadata()?.len() == 0;
+        if !empty && format == Format::Binary {
+            binary::read_header(&mut std::fs::File::open(path)?)
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err.to_string()))?;
+        }
+
+        let mut recorder = Self::new(stream, io::BufWriter::new(file), format);
+        recorder.header = empty;
+        Ok(recorder)
+    }
+}
+
+impl<S, W> Stream for Recorder<S, W>
+    where S: Stream<Item = Notification, Error = ()>, W: Write
+{
+    type Item = Notification;
+    type Error = ();
+
+    fn poll(&mut self) -> Poll<Option<Notification>, ()> {
+        let notif = futures::try_ready!(self.stream.poll());
+
+        if let Some(writer) = &mut self.writer {
+            let result = match &notif {
+                Some(notif) => {
+                    let header = std::mem::replace(&mut self.header, false);
+                    write_record(writer, self.format, header, timestamp_ms(), notif)
+                }
+                None => writer.flush().map_err(Into::into),
+            };
+
+            if let Err(err) = result {
+                error!("recording stopped: {}", err);
+                self.writer = None;
+            }
+        }
+
+        Ok(Async::Ready(notif))
+    }
+}
+
+/// An iterator over the records of a recording, yielding each notification along with
+/// the local timestamp at which it was recorded.
+pub struct Records<R> {
+    reader: BufReader<R>,
+    format: Format,
+    header: bool,
+    done: bool,
+}
+
+impl<R: Read> Records<R> {
+    /// Read records from `reader`, in the given format. A binary recording yields an error
+    /// if it was written with another layout version.
+    pub fn new(reader: R, format: Format) -> Self {
+        Records {
+            reader: BufReader::new(reader),
+            format,
+            header: format == Format::Binary,
+            done: false,
+        }
+    }
+}
+
+impl<R: Read> Iterator for Records<R> {
+    type Item = Result<(Timestamp, Notification), failure::Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        if std::mem::replace(&mut self.header, false) {
+            match binary::read_header(&mut self.reader) {
+                Ok(true) => (),
+                Ok(false) => {
+                    self.done = true;
+                    return None;
+                }
+                Err(err) => {
+                    self.done = true;
+                    return Some(Err(err));
+                }
+            }
+        }
+
+        match read_record(&mut self.reader, self.format) {
+            Ok(Some(record)) => Some(Ok(record)),
+            Ok(None) => {
+                self.done = true;
+                None
+            }
+            Err(err) => {
+                self.done = true;
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+/// A stream of notifications read from a recording, suitable for feeding e.g. a
+/// `LiveOrderBook` or a strategy expecting an `ApiClient::Stream`.
+///
+/// The recording is read in a background thread. The stream ends once the end of the
+/// recording is reached, or if a record cannot be read (the error is then logged).
+pub struct Replayer {
+    rcv: Receiver<Notification>,
+}
+
+impl Replayer {
+    /// Replay notifications from `reader`, in the given format and with the given pacing.
+    /// Fail with `io::ErrorKind::InvalidInput` if the factor of `Pacing::Accelerated` is not
+    /// a finite positive number.
+    pub fn new<R: Read + Send + 'static>(reader: R, format: Format, pacing: Pacing)
+        -> io::Result<Self>
+    {
+        use std::thread;
+
+        let speed = match pacing {
+            Pacing::RealTime => Some(1.0),
+            Pacing::Accelerated(factor) => {
+                if !(factor.is_finite() && factor > 0.) {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        format!("invalid acceleration factor: {}", factor)
+                    ));
+                }
+                Some(factor)
+            }
+            Pacing::AsFastAsPossible => None,
+        };
+
+        // Bounded channel so that we do not read the whole recording in advance.
+        let (mut snd, rcv) = channel(1024);
+
+        thread::spawn(move || {
+            let mut start = None;
+
+            for record in Records::new(reader, format) {
+                let (timestamp, notif) = match record {
+                    Ok(record) => record,
+                    Err(err) => {
+                        error!("cannot read recording: {}", err);
+                        break;
+                    }
+                };
+
+                if let Some(speed) = speed {
+                    let (start_instant, start_timestamp) = *start.get_or_insert(
+                        (Instant::now(), timestamp)
+                    );
+                    let elapsed = timestamp.saturating_sub(start_timestamp) as f64 / speed;
+                    let due = start_instant + Duration::from_micros((elapsed * 1000.) as u64);
+                    let now = Instant::now();
+                    if due > now {
+                        thread::sleep(due - now);
+                    }
+                }
+
+                snd = match snd.send(notif).wait() {
+                    Ok(snd) => snd,
+
+                    // The `Replayer` was dropped.
+                    Err(..) => break,
+                };
+            }
+        });
+
+        Ok(Replayer {
+            rcv,
+        })
+    }
+
+    /// Replay notifications from the file at `path`, see `Replayer::new`.
+    pub fn open<P: AsRef<Path>>(path: P, format: Format, pacing: Pacing) -> io::Result<Self> {
+        let file = std::fs::File::open(path)?;
+        Self::new(file, format, pacing)
+    }
+}
+
+impl Stream for Replayer {
+    type Item = Notification;
+    type Error = ();
+
+    fn poll(&mut self) -> Poll<Option<Notification>, ()> {
+        self.rcv.poll()
+    }
+}
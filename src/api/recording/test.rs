@@ -0,0 +1,171 @@
+#![cfg(test)]
+
+use futures::prelude::*;
+use crate::Side;
+use crate::order_book::LimitUpdate;
+use crate::order_book::l3::L3Update;
+use crate::api::{
+    Notification,
+    Trade,
+    BestBidOffer,
+    OrderConfirmation,
+    OrderUpdate,
+    OrderExpiration,
+};
+use crate::api::order_book::{LiveOrderBook, BookState};
+use crate::api::integrity::IntegrityIssue;
+use crate::api::channel::Overflow;
+use crate::api::timestamp::{IntoTimestamped, Sequence, TimestampUs};
+use crate::api::recording::{Recorder, Replayer, Records, Format, Pacing};
+
+fn notifications() -> Vec<Notification> {
+    vec![
+        Notification::LimitUpdates(vec![
+            LimitUpdate::new(100, 5, Side::Bid).with_timestamp(1)
+                .with_sequence(Sequence { first: 10, last: 12 }),
+            LimitUpdate::new(101, 3, Side::Ask).with_timestamp(1)
+                .with_received(TimestampUs(2))
+                .with_sequence(Sequence { first: 10, last: 12 }),
+        ]),
+        Notification::OrderConfirmation(OrderConfirmation {
+            order_id: "my_order".to_owned(),
+            price: 101,
+            size: 1,
+            side: Side::Bid,
+        }.with_local_timestamp(2)),
+        Notification::Trade(Trade {
+            price: 101,
+            size: 1,
+            maker_side: Side::Ask,
+        }.with_timestamp(3).with_sent(3).with_received(TimestampUs(3_500))),
+        Notification::OrderUpdate(OrderUpdate {
+            order_id: "my_order".to_owned(),
+            consumed_size: 1,
+            remaining_size: 0,
+            consumed_price: 101,
+            commission: 7,
+        }.with_timestamp(3)),
+        Notification::OrderExpiration(OrderExpiration {
+            order_id: "other_order".to_owned(),
+        }.with_timestamp(4)),
+        Notification::LimitUpdates(vec![]),
+        Notification::L3Updates(vec![
+            L3Update::Insert {
+                order_id: "a".to_owned(),
+                price: 100,
+                size: 2,
+                side: Side::Bid,
+            }.with_timestamp(5),
+            L3Update::Reduce { order_id: "a".to_owned(), size: 1 }.with_timestamp(5),
+            L3Update::Resize { order_id: "a".to_owned(), size: 3 }.with_timestamp(6),
+            L3Update::Remove { order_id: "a".to_owned() }.with_timestamp(7),
+        ]),
+        Notification::Integrity(IntegrityIssue::Discrepancies(vec![
+            LimitUpdate::new(100, 0, Side::Bid),
+        ]).with_timestamp(8)),
+        Notification::Integrity(IntegrityIssue::SnapshotFailed.with_timestamp(8)),
+        Notification::BestBidOffer(BestBidOffer {
+            bid_price: 100,
+            bid_size: 5,
+            ask_price: 102,
+            ask_size: 1,
+        }.with_timestamp(9)),
+        Notification::Overflow(Overflow {
+            dropped: 2,
+            conflated: 1,
+        }.with_local_timestamp(9)),
+    ]
+}
+
+fn round_trip(format: Format) {
+    let mut recording = Vec::new();
+    let forwarded: Vec<_> = Recorder::new(
+        futures::stream::iter_ok(notifications()),
+        &mut recording,
+        format
+    ).collect().wait().unwrap();
+    assert_eq!(forwarded, notifications());
+
+    let replayed: Vec<_> = Replayer::new(
+        std::io::Cursor::new(recording),
+        format,
+        Pacing::AsFastAsPossible
+    ).unwrap().collect().wait().unwrap();
+    assert_eq!(replayed, notifications());
+}
+
+#[test]
+fn json_lines() {
+    round_trip(Format::JsonLines);
+}
+
+#[test]
+fn binary() {
+    round_trip(Format::Binary);
+}
+
+#[test]
+fn truncated() {
+    let mut recording = Vec::new();
+    Recorder::new(futures::stream::iter_ok(notifications()), &mut recording, Format::Binary)
+        .for_each(|_| Ok(()))
+        .wait()
+        .unwrap();
+    recording.pop();
+
+    let replayed: Vec<_> = Replayer::new(
+        std::io::Cursor::new(recording),
+        Format::Binary,
+        Pacing::AsFastAsPossible
+    ).unwrap().collect().wait().unwrap();
+    assert_eq!(replayed[..], notifications()[..10]);
+}
+
+#[test]
+fn version() {
+    let mut recording = Vec::new();
+    Recorder::new(futures::stream::iter_ok(notifications()), &mut recording, Format::Binary)
+        .for_each(|_| Ok(()))
+        .wait()
+        .unwrap();
+    assert_eq!(&recording[..4], b"TRDR");
+    assert_eq!(Records::new(&recording[..], Format::Binary).count(), notifications().len());
+
+    // A recording with another layout version is rejected.
+    recording[4] += 1;
+    let records: Vec<_> = Records::new(&recording[..], Format::Binary).collect();
+    assert_eq!(records.len(), 1);
+    assert!(records[0].is_err());
+}
+
+#[test]
+fn zero_acceleration() {
+    let replayer = Replayer::new(
+        std::io::Cursor::new(Vec::new()),
+        Format::Binary,
+        Pacing::Accelerated(0.)
+    );
+    assert_eq!(replayer.err().unwrap().kind(), std::io::ErrorKind::InvalidInput);
+}
+
+#[test]
+fn live_order_book() {
+    let recording = r#"
+{"timestamp":1000,"notification":{"LimitUpdates":[{"timestamp":1,"price":100,"size":5,"side":"Bid"}]}}
+{"timestamp":1200,"notification":{"LimitUpdates":[{"timestamp":2,"price":102,"size":1,"side":"Ask"}]}}
+"#;
+
+    let start = std::time::Instant::now();
+    let replayer = Replayer::new(
+        std::io::Cursor::new(recording),
+        Format::JsonLines,
+        Pacing::Accelerated(2.)
+    ).unwrap();
+    let live_order_book = LiveOrderBook::from_stream(replayer);
+
+    // Wait for the whole recording to be replayed, i.e. until the stream ends.
+    while let BookState::Live(_) = live_order_book.order_book() {
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+    assert!(start.elapsed() >= std::time::Duration::from_millis(100));
+}
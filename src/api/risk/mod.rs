@@ -0,0 +1,320 @@
+//! A module defining a wrapper around an `ApiClient`, enforcing pre-trade risk limits
+//! before forwarding orders to the wrapped client.
+//!
+//! Open orders and positions are maintained from the notifications of the streams opened
+//! through the wrapper, so a stream must be running for each traded symbol.
+
+mod test;
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use futures::prelude::*;
+use serde_derive::{Serialize, Deserialize};
+use crate::Side;
+use crate::order_book::OrderBook;
+use crate::api::{
+    ApiClient,
+    GenerateOrderId,
+    Order,
+    OrderAck,
+    Cancel,
+    CancelAck,
+    Notification,
+    NotificationFlags,
+    Balances,
+};
+use crate::api::symbol::{Symbol, WithSymbol, IntoWithSymbol};
+use crate::api::timestamp::Timestamped;
+use crate::api::errors::{
+    self,
+    ApiError,
+    RestErrorKind,
+    OrderErrorKind,
+    RiskViolation,
+};
+use crate::api::order_book::{LiveOrderBook, BookState};
+use crate::api::order_manager::OrderManager;
+use crate::api::portfolio::Portfolio;
+
+/// Exchange name under which the positions are kept in the portfolio of a `RiskClient`.
+const EXCHANGE: &str = "";
+
+#[derive(Clone, PartialEq, Debug, Default, Serialize, Deserialize)]
+/// Pre-trade risk limits, `None` meaning no limit. Sizes are in base asset units,
+/// prices and notionals in quote asset units.
+pub struct RiskLimits {
+    /// Maximum size of an order.
+    pub max_order_size: Option<f64>,
+
+    /// Maximum notional (price times size) of an order.
+    pub max_notional: Option<f64>,
+
+    /// Maximum number of open orders, including the orders not acknowledged yet.
+    pub max_open_orders: Option<usize>,
+
+    /// Maximum absolute net position per asset, across all the symbols having this asset
+    /// as base asset. The assets of each symbol must be set through the portfolio, see
+    /// `RiskClient::with_portfolio`. The remaining size of the open orders on the same side is
+    /// counted as if filled. Orders reducing the position are always accepted.
+    pub max_position: HashMap<String, f64>,
+
+    /// Maximum relative distance between the order price and the mid price of the order
+    /// book of the symbol, e.g. `0.05` for 5%. The order books must be provided through
+    /// `RiskClient::set_order_book`.
+    pub price_collar: Option<f64>,
+}
+
+/// A wrapper around an `ApiClient`, rejecting the orders which breach the configured
+/// `RiskLimits` with an `OrderErrorKind::RiskLimit` error instead of sending them.
+///
+/// Orders without an id are given one, so that they can be tracked until they terminate.
+pub struct RiskClient<C> {
+    client: C,
+    limits: Arc<Mutex<RiskLimits>>,
+    killed: Arc<AtomicBool>,
+    orders: OrderManager,
+    portfolio: Arc<Mutex<Portfolio>>,
+    order_books: Arc<Mutex<HashMap<String, LiveOrderBook>>>,
+}
+
+impl<C: ApiClient> RiskClient<C> {
+    /// Wrap `client`, enforcing `limits`.
+    pub fn new(client: C, limits: RiskLimits) -> Self {
+        RiskClient {
+            client,
+            limits: Arc::new(Mutex::new(limits)),
+            killed: Arc::new(AtomicBool::new(false)),
+            orders: OrderManager::new(),
+            portfolio: Arc::new(Mutex::new(Portfolio::new())),
+            order_books: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Wrapped client.
+    pub fn inner(&self) -> &C {
+        &self.client
+    }
+
+    /// Replace the risk limits.
+    pub fn set_limits(&self, limits: RiskLimits) {
+        *self.limits.lock().unwrap() = limits;
+    }
+
+    /// Reject all the orders from now on, until `resume` is called. Open orders are left
+    /// untouched, they can be cancelled by querying `order_manager`.
+    pub fn kill(&self) {
+        self.killed.store(true, Ordering::SeqCst);
+    }
+
+    /// Disengage the kill switch.
+    pub fn resume(&self) {
+        self.killed.store(false, Ordering::SeqCst);
+    }
+
+    /// Return whether the kill switch is engaged.
+    pub fn is_killed(&self) -> bool {
+        self.killed.load(Ordering::SeqCst)
+    }
+
+    /// Use `order_book` as the reference for the price collar of the symbol named `symbol`.
+    pub fn set_order_book(&self, symbol: &str, order_book: LiveOrderBook) {
+        self.order_books.lock().unwrap().insert(symbol.to_owned(), order_book);
+    }
+
+    /// Order manager tracking the orders sent through `self`. Terminated orders are removed
+    /// from it each time a new order is sent.
+    pub fn order_manager(&self) -> &OrderManager {
+        &self.orders
+    }
+
+    /// Call `f` with the portfolio tracking the positions, e.g. for setting the assets of
+    /// the symbols. The portfolio is locked during the call, so `f` must not send orders
+    /// through `self`.
+    pub fn with_portfolio<F, R>(&self, f: F) -> R
+        where F: FnOnce(&mut Portfolio) -> R
+    {
+        f(&mut self.portfolio.lock().unwrap())
+    }
+
+    fn check(&self, symbol: Symbol, order: &Order, portfolio: &Portfolio)
+        -> Result<(), RiskViolation>
+    {
+        if self.is_killed() {
+            return Err(RiskViolation::KillSwitch);
+        }
+
+        let limits = self.limits.lock().unwrap();
+        let price_tick = symbol.price_tick().ticks_per_unit() as f64;
+        let size_tick = symbol.size_tick().ticks_per_unit() as f64;
+        let price = order.price().ticked(symbol.price_tick()) as f64 / price_tick;
+        let size = order.size().ticked(symbol.size_tick()) as f64 / size_tick;
+
+        if limits.max_order_size.map(|max| size > max).unwrap_or(false) {
+            return Err(RiskViolation::OrderSize);
+        }
+
+        if limits.max_notional.map(|max| price * size > max).unwrap_or(false) {
+            return Err(RiskViolation::Notional);
+        }
+
+        let open_orders = self.orders.open_orders();
+        if let Some(max) = limits.max_open_orders {
+            if open_orders.len() >= max {
+                return Err(RiskViolation::OpenOrders);
+            }
+        }
+
+        let base = portfolio.assets_of(symbol.name()).map(|(base, _)| base);
+        let max = base.and_then(|base| limits.max_position.get(base));
+        if let (Some(base), Some(max)) = (base, max) {
+            let position = portfolio.assets()
+                .get(base)
+                .map(|asset| asset.position)
+                .unwrap_or(0.);
+
+            // The open orders on the same side would move the position further if filled.
+            let pending: f64 = open_orders.iter()
+                .filter(|open| open.side == order.side)
+                .filter_map(|open| {
+                    let symbol = open.symbol?;
+                    if portfolio.assets_of(symbol.name()).map(|(base, _)| base) != Some(base) {
+                        return None;
+                    }
+                    Some(open.remaining as f64 / symbol.size_tick().ticks_per_unit() as f64)
+                })
+                .sum();
+            let (exposure, projected) = match order.side {
+                Side::Bid => (position + pending, position + pending + size),
+                Side::Ask => (position - pending, position - pending - size),
+            };
+            if projected.abs() > *max && projected.abs() > exposure.abs() {
+                return Err(RiskViolation::Position);
+            }
+        }
+
+        if let Some(collar) = limits.price_collar {
+            let mid = match self.order_books.lock().unwrap().get(symbol.name()) {
+                Some(order_book) => match order_book.order_book() {
+                    BookState::Live(order_book) => order_book.mid(),
+                    BookState::Disconnected => None,
+                },
+                None => None,
+            };
+            let mid = mid.ok_or(RiskViolation::NoReferencePrice)? / price_tick;
+            if (price - mid).abs() > collar * mid {
+                return Err(RiskViolation::PriceCollar);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<C: ApiClient> GenerateOrderId for RiskClient<C> {
+    fn new_order_id(hint: &str) -> String {
+        C::new_order_id(hint)
+    }
+}
+
+impl<C: ApiClient> ApiClient for RiskClient<C> {
+    type Stream = RiskStream<C::Stream>;
+
+    fn find_symbol(&self, symbol: &str) -> Option<Symbol> {
+        self.client.find_symbol(symbol)
+    }
+
+    fn stream_with_flags(&self, symbol: Symbol, flags: NotificationFlags) -> Self::Stream {
+        RiskStream {
+            stream: self.client.stream_with_flags(symbol, flags | NotificationFlags::ORDERS),
+            symbol,
+            forward_orders: flags.contains(NotificationFlags::ORDERS),
+            orders: self.orders.clone(),
+            portfolio: self.portfolio.clone(),
+        }
+    }
+
+    fn order(&self, order: WithSymbol<&Order>)
+        -> Box<dyn Future<Item = Timestamped<OrderAck>, Error = errors::OrderError> + Send + 'static>
+    {
+        let symbol = order.symbol();
+        let order = match order.order_id() {
+            Some(_) => (*order).clone(),
+            None => {
+                use uuid::Uuid;
+                let hint = Uuid::new_v4().simple().to_string();
+                (*order).clone().with_order_id::<C>(&hint)
+            }
+        };
+
+        // Keep the portfolio locked until the order is registered by the order manager,
+        // so that concurrent orders are checked one after the other.
+        let portfolio = self.portfolio.lock().unwrap();
+        self.orders.remove_terminated();
+        if let Err(violation) = self.check(symbol, &order, &portfolio) {
+            let kind = RestErrorKind::Specific(OrderErrorKind::RiskLimit(violation));
+            return Box::new(futures::future::err(ApiError::RestError(kind.into())));
+        }
+        self.orders.order(&self.client, order.with_symbol(symbol))
+    }
+
+    fn cancel(&self, cancel: WithSymbol<&Cancel>)
+        -> Box<dyn Future<Item = Timestamped<CancelAck>, Error = errors::CancelError> + Send + 'static>
+    {
+        self.orders.cancel(&self.client, cancel)
+    }
+
+    fn ping(&self)
+        -> Box<dyn Future<Item = Timestamped<()>, Error = errors::Error> + Send + 'static>
+    {
+        self.client.ping()
+    }
+
+    fn order_book(&self, symbol: Symbol)
+        -> Box<dyn Future<Item = Timestamped<OrderBook>, Error = errors::Error> + Send + 'static>
+    {
+        self.client.order_book(symbol)
+    }
+
+    fn balances(&self)
+        -> Box<dyn Future<Item = Balances, Error = errors::Error> + Send + 'static>
+    {
+        self.client.balances()
+    }
+}
+
+/// A stream of notifications opened through a `RiskClient`, applying the order
+/// notifications to the open orders and positions of the client.
+pub struct RiskStream<S> {
+    stream: S,
+    symbol: Symbol,
+    forward_orders: bool,
+    orders: OrderManager,
+    portfolio: Arc<Mutex<Portfolio>>,
+}
+
+impl<S: Stream<Item = Notification>> Stream for RiskStream<S> {
+    type Item = Notification;
+    type Error = S::Error;
+
+    fn poll(&mut self) -> Poll<Option<Notification>, S::Error> {
+        loop {
+            let notif = match futures::try_ready!(self.stream.poll()) {
+                Some(notif) => notif,
+                None => return Ok(Async::Ready(None)),
+            };
+
+            self.orders.on_notification(&notif);
+            self.portfolio.lock().unwrap().on_notification(EXCHANGE, self.symbol, &notif);
+
+            let is_order = match notif {
+                Notification::OrderConfirmation(..) |
+                Notification::OrderUpdate(..) |
+                Notification::OrderExpiration(..) => true,
+                _ => false,
+            };
+            if !is_order || self.forward_orders {
+                return Ok(Async::Ready(Some(notif)));
+            }
+        }
+    }
+}
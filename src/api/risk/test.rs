@@ -0,0 +1,104 @@
+#![cfg(test)]
+
+use futures::prelude::*;
+use crate::Side;
+use crate::tick::Tick;
+use crate::api::{ApiClient, Order, Cancel};
+use crate::api::errors::{ApiError, RestErrorKind, OrderErrorKind, OrderError, RiskViolation};
+use crate::api::symbol::{Symbol, IntoWithSymbol};
+use crate::api::sim::{Exchange, Client, Settings};
+use crate::api::order_manager::OrderState;
+use crate::api::risk::{RiskClient, RiskLimits};
+
+fn violation(err: OrderError) -> RiskViolation {
+    match err {
+        ApiError::RestError(err) => match err.kind() {
+            RestErrorKind::Specific(OrderErrorKind::RiskLimit(violation)) => violation,
+            other => panic!("unexpected error kind {:?}", other),
+        },
+        other => panic!("unexpected error {:?}", other),
+    }
+}
+
+fn send(client: &RiskClient<Client>, symbol: Symbol, order: Order)
+    -> Result<String, RiskViolation>
+{
+    client.order(order.with_symbol(symbol))
+        .wait()
+        .map(|ack| ack.order_id.clone())
+        .map_err(violation)
+}
+
+#[test]
+fn risk_limits() {
+    let exchange = Exchange::new(Settings::default());
+    let symbol = exchange.add_symbol("BTCUSD", Tick::new(100), Tick::new(1000)).unwrap();
+    let client = RiskClient::new(exchange.client(), RiskLimits {
+        max_order_size: Some(2.),
+        max_notional: Some(250.),
+        max_open_orders: Some(1),
+        ..Default::default()
+    });
+
+    assert_eq!(
+        send(&client, symbol, Order::new("100.00", "2.5", Side::Bid)),
+        Err(RiskViolation::OrderSize)
+    );
+    assert_eq!(
+        send(&client, symbol, Order::new("200.00", "1.5", Side::Bid)),
+        Err(RiskViolation::Notional)
+    );
+
+    // The order is given an id, and tracked until it is cancelled.
+    let order_id = send(&client, symbol, Order::new("100.00", "1.5", Side::Bid)).unwrap();
+    assert_eq!(client.order_manager().open_orders().len(), 1);
+    assert_eq!(
+        send(&client, symbol, Order::new("100.00", "1", Side::Bid)),
+        Err(RiskViolation::OpenOrders)
+    );
+
+    client.cancel(Cancel::new(order_id.clone()).with_symbol(symbol)).wait().unwrap();
+    let order = client.order_manager().order_by_id(&order_id).unwrap();
+    assert_eq!(order.state, OrderState::Cancelled);
+
+    client.kill();
+    assert_eq!(
+        send(&client, symbol, Order::new("100.00", "1", Side::Bid)),
+        Err(RiskViolation::KillSwitch)
+    );
+    client.resume();
+
+    // Terminated orders are not kept once another order is sent.
+    assert!(client.order_manager().order_by_id(&order_id).is_none());
+
+    client.set_limits(RiskLimits {
+        price_collar: Some(0.05),
+        ..Default::default()
+    });
+    assert_eq!(
+        send(&client, symbol, Order::new("100.00", "1", Side::Bid)),
+        Err(RiskViolation::NoReferencePrice)
+    );
+}
+
+#[test]
+fn position_with_open_orders() {
+    let exchange = Exchange::new(Settings::default());
+    let symbol = exchange.add_symbol("BTCUSD", Tick::new(100), Tick::new(1000)).unwrap();
+    let mut max_position = std::collections::HashMap::new();
+    max_position.insert("BTC".to_owned(), 2.);
+    let client = RiskClient::new(exchange.client(), RiskLimits {
+        max_position,
+        ..Default::default()
+    });
+    client.with_portfolio(|portfolio| portfolio.set_assets("BTCUSD", "BTC", "USD"));
+
+    // Resting orders count towards the position on their side only.
+    send(&client, symbol, Order::new("100.00", "1.5", Side::Bid)).unwrap();
+    assert_eq!(
+        send(&client, symbol, Order::new("100.00", "1", Side::Bid)),
+        Err(RiskViolation::Position)
+    );
+    send(&client, symbol, Order::new("200.00", "1", Side::Ask)).unwrap();
+    send(&client, symbol, Order::new("100.00", "0.5", Side::Bid)).unwrap();
+}
@@ -0,0 +1,379 @@
+//! A module defining a smart order router, splitting marketable orders across several
+//! venues trading the same pair.
+//!
+//! Each venue is made of a client, the symbol of the pair on this venue, a live order book
+//! and a taker fee. Prices and sizes are compared across venues in asset units, since
+//! ticks may differ from one venue to another.
+
+mod test;
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use futures::prelude::*;
+use serde_derive::{Serialize, Deserialize};
+use crate::Side;
+use crate::tick::TickUnit;
+use crate::api::{Order, TimeInForce, Notification};
+use crate::api::symbol::{Symbol, IntoWithSymbol};
+use crate::api::dynamic::DynApiClient;
+use crate::api::order_book::{LiveOrderBook, BookState};
+use crate::api::order_manager::{OrderManager, OrderState};
+
+/// A venue on which orders can be routed.
+pub struct Venue {
+    name: String,
+    client: Box<dyn DynApiClient>,
+    symbol: Symbol,
+    order_book: LiveOrderBook,
+    taker_fee: f64,
+    orders: OrderManager,
+}
+
+impl Venue {
+    /// Return a new venue named `name`, trading `symbol` through `client`. `order_book`
+    /// is the live order book of `symbol`, and `taker_fee` the fee charged on the traded
+    /// notional when taking liquidity, e.g. `0.001` for 0.1%.
+    ///
+    /// # Panics
+    /// Panic if `taker_fee` is not a finite number in `[0, 1)`.
+    pub fn new<C>(
+        name: &str,
+        client: C,
+        symbol: Symbol,
+        order_book: LiveOrderBook,
+        taker_fee: f64
+    ) -> Self
+        where C: DynApiClient + 'static
+    {
+        assert!(
+            taker_fee.is_finite() && taker_fee >= 0. && taker_fee < 1.,
+            "invalid taker fee: {}",
+            taker_fee
+        );
+
+        Venue {
+            name: name.to_owned(),
+            client: Box::new(client),
+            symbol,
+            order_book,
+            taker_fee,
+            orders: OrderManager::new(),
+        }
+    }
+
+    /// Venue name.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Order manager tracking the orders routed to this venue.
+    pub fn order_manager(&self) -> &OrderManager {
+        &self.orders
+    }
+
+    /// Limits of the side of the book consumed by an order of the given `side`, in asset
+    /// units and in ticks, best first.
+    fn limits(&self, side: Side) -> Vec<(f64, TickUnit, TickUnit)> {
+        let order_book = match self.order_book.order_book() {
+            BookState::Live(order_book) => order_book,
+            BookState::Disconnected => return Vec::new(),
+        };
+
+        let price_tick = self.symbol.price_tick().ticks_per_unit() as f64;
+        let limits: Box<dyn Iterator<Item = (&TickUnit, &TickUnit)>> = match side {
+            Side::Bid => Box::new(order_book.ask()),
+            Side::Ask => Box::new(order_book.bid()),
+        };
+        limits.map(|(&price, &size)| (price as f64 / price_tick, price, size)).collect()
+    }
+}
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+/// Part of a routed order allocated to one venue.
+pub struct Allocation {
+    /// Venue name.
+    pub venue: String,
+
+    /// Worst price of the limits consumed on the venue, in ticks of the venue symbol.
+    pub price: TickUnit,
+
+    /// Allocated size, in size ticks of the venue symbol.
+    pub size: TickUnit,
+}
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+/// Execution of a routed order on one venue.
+pub struct VenueExecution {
+    /// Venue name.
+    pub venue: String,
+
+    /// Id of the child order sent to the venue.
+    pub order_id: String,
+
+    /// Size sent, in asset units.
+    pub size: f64,
+
+    /// Size executed so far, in asset units.
+    pub filled: f64,
+
+    /// Average execution price in asset units, or `None` if nothing was executed.
+    pub average_price: Option<f64>,
+
+    /// State of the child order.
+    pub state: OrderState,
+}
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+/// An order routed across venues.
+pub struct Route {
+    /// Side of the order.
+    pub side: Side,
+
+    /// Total size to execute, in asset units.
+    pub size: f64,
+
+    /// Worst acceptable price, in asset units.
+    pub limit_price: f64,
+
+    /// Child orders, as `(venue index, order id)`.
+    children: Vec<(usize, String)>,
+}
+
+#[derive(Default)]
+/// Fills of a child order, in venue ticks.
+struct Fills {
+    size: TickUnit,
+    notional: f64,
+}
+
+struct Inner {
+    venues: Vec<Venue>,
+    fills: Mutex<HashMap<String, Fills>>,
+}
+
+#[derive(Clone)]
+/// A smart order router, splitting marketable orders across venues by best price net of
+/// fees. A `Router` is a cheap handle which can be cloned and shared between threads.
+///
+/// Child orders are sent as immediate-or-cancel limit orders, and their executions are
+/// tracked from the order notifications of each venue, which must be fed through
+/// `on_notification` or by wrapping the venue streams with `track`.
+pub struct Router {
+    inner: Arc<Inner>,
+}
+
+impl Router {
+    /// Return a new `Router` over `venues`.
+    pub fn new(venues: Vec<Venue>) -> Self {
+        Router {
+            inner: Arc::new(Inner {
+                venues,
+                fills: Mutex::new(HashMap::new()),
+            }),
+        }
+    }
+
+    /// Venues of the router.
+    pub fn venues(&self) -> &[Venue] {
+        &self.inner.venues
+    }
+
+    fn venue_index(&self, venue: &str) -> Option<usize> {
+        self.inner.venues.iter().position(|v| v.name == venue)
+    }
+
+    /// Apply an order notification of the venue named `venue`.
+    pub fn on_notification(&self, venue: &str, notif: &Notification) {
+        let index = match self.venue_index(venue) {
+            Some(index) => index,
+            None => return,
+        };
+        self.inner.venues[index].orders.on_notification(notif);
+
+        if let Notification::OrderUpdate(update) = notif {
+            let mut fills = self.inner.fills.lock().unwrap();
+            if let Some(fills) = fills.get_mut(&update.order_id) {
+                fills.size += update.consumed_size;
+                fills.notional += update.consumed_price as f64 * update.consumed_size as f64;
+            }
+        }
+    }
+
+    /// Wrap the stream of the venue named `venue`, so that its notifications are applied
+    /// to `self` before being forwarded.
+    pub fn track<S: Stream<Item = Notification>>(&self, venue: &str, stream: S) -> Track<S> {
+        Track {
+            stream,
+            venue: venue.to_owned(),
+            router: self.clone(),
+        }
+    }
+
+    /// Split an order of `size` on `side`, at `limit_price` at worst, across the venues:
+    /// the limits of all the books are consumed by best price net of fees. Sizes and prices
+    /// are in asset units. The allocated size may be lower than `size` if there is not
+    /// enough liquidity.
+    pub fn plan(&self, side: Side, size: f64, limit_price: f64) -> Vec<Allocation> {
+        let mut limits = Vec::new();
+        for (index, venue) in self.inner.venues.iter().enumerate() {
+            for (price, ticked_price, ticked_size) in venue.limits(side) {
+                let acceptable = match side {
+                    Side::Bid => price <= limit_price,
+                    Side::Ask => price >= limit_price,
+                };
+                if !acceptable {
+                    break;
+                }
+
+                // Price actually paid (received) per unit once fees are charged.
+                let net_price = match side {
+                    Side::Bid => price * (1. + venue.taker_fee),
+                    Side::Ask => price * (1. - venue.taker_fee),
+                };
+                limits.push((net_price, index, ticked_price, ticked_size));
+            }
+        }
+
+        limits.sort_by(|a, b| {
+            // Net prices are finite since fees are checked by `Venue::new`.
+            let ordering = a.0.partial_cmp(&b.0).expect("net price is NaN");
+            match side {
+                Side::Bid => ordering,
+                Side::Ask => ordering.reverse(),
+            }
+        });
+
+        let mut allocations: Vec<Option<Allocation>> = vec![None; self.inner.venues.len()];
+        let mut remaining = size;
+        for (_, index, price, limit_size) in limits {
+            let size_tick = self.inner.venues[index].symbol.size_tick().ticks_per_unit();
+            let size = limit_size.min((remaining * size_tick as f64 + 1e-9) as TickUnit);
+            if size == 0 {
+                continue;
+            }
+            remaining -= size as f64 / size_tick as f64;
+
+            let allocation = allocations[index].get_or_insert_with(|| Allocation {
+                venue: self.inner.venues[index].name.clone(),
+                price,
+                size: 0,
+            });
+            allocation.price = price;
+            allocation.size += size;
+        }
+        allocations.into_iter().filter_map(|allocation| allocation).collect()
+    }
+
+    /// Route an order of `size` on `side`, at `limit_price` at worst, by sending one child
+    /// order per venue as planned by `plan`. The returned future resolves once all the
+    /// child orders have been acknowledged or rejected.
+    pub fn route(&self, side: Side, size: f64, limit_price: f64)
+        -> Box<dyn Future<Item = Route, Error = ()> + Send + 'static>
+    {
+        let mut children = Vec::new();
+        let mut acks = Vec::new();
+
+        for allocation in self.plan(side, size, limit_price) {
+            let index = self.venue_index(&allocation.venue).unwrap();
+            let venue = &self.inner.venues[index];
+
+            let hint = {
+                use uuid::Uuid;
+                Uuid::new_v4().simple().to_string()
+            };
+            let order_id = venue.client.new_order_id(&hint);
+            let mut order = Order::new(allocation.price, allocation.size, side)
+                .with_time_in_force(TimeInForce::ImmediateOrCancel);
+            order.order_id = Some(order_id.clone());
+
+            self.inner.fills.lock().unwrap().insert(order_id.clone(), Fills::default());
+            let order = order.with_symbol(venue.symbol);
+            venue.orders.order_sent(&order);
+
+            let orders = venue.orders.clone();
+            let ack_id = order_id.clone();
+            acks.push(venue.client.order(order).then(move |result| {
+                orders.order_result(&ack_id, result.as_ref());
+                Ok::<_, ()>(())
+            }));
+            children.push((index, order_id));
+        }
+
+        let route = Route {
+            side,
+            size,
+            limit_price,
+            children,
+        };
+        Box::new(futures::future::join_all(acks).map(move |_| route))
+    }
+
+    /// Executions of the child orders of `route`, one per venue.
+    pub fn executions(&self, route: &Route) -> Vec<VenueExecution> {
+        let fills = self.inner.fills.lock().unwrap();
+        route.children.iter().filter_map(|(index, order_id)| {
+            let venue = &self.inner.venues[*index];
+            let order = venue.orders.order_by_id(order_id)?;
+            let fill = fills.get(order_id)?;
+
+            let price_tick = venue.symbol.price_tick().ticks_per_unit() as f64;
+            let size_tick = venue.symbol.size_tick().ticks_per_unit() as f64;
+            Some(VenueExecution {
+                venue: venue.name.clone(),
+                order_id: order_id.clone(),
+                size: order.size as f64 / size_tick,
+                filled: fill.size as f64 / size_tick,
+                average_price: if fill.size > 0 {
+                    Some(fill.notional / fill.size as f64 / price_tick)
+                } else {
+                    None
+                },
+                state: order.state,
+            })
+        }).collect()
+    }
+
+    /// Size of `route` not executed yet, in asset units.
+    pub fn unfilled(&self, route: &Route) -> f64 {
+        let filled: f64 = self.executions(route).iter().map(|execution| execution.filled).sum();
+        (route.size - filled).max(0.)
+    }
+
+    /// Return whether all the child orders of `route` have terminated.
+    pub fn is_complete(&self, route: &Route) -> bool {
+        self.executions(route).iter().all(|execution| execution.state.is_terminal())
+    }
+
+    /// Route the unfilled size of a complete `route` again, against the current books, e.g.
+    /// after some child orders were only partially filled. The returned future fails
+    /// without sending anything if `route` is not complete, see `is_complete`.
+    pub fn reroute(&self, route: &Route)
+        -> Box<dyn Future<Item = Route, Error = ()> + Send + 'static>
+    {
+        if !self.is_complete(route) {
+            return Box::new(futures::future::err(()));
+        }
+        self.route(route.side, self.unfilled(route), route.limit_price)
+    }
+}
+
+/// A stream adaptor feeding the notifications of a venue stream to a `Router`, see
+/// `Router::track`.
+pub struct Track<S> {
+    stream: S,
+    venue: String,
+    router: Router,
+}
+
+impl<S: Stream<Item = Notification>> Stream for Track<S> {
+    type Item = Notification;
+    type Error = S::Error;
+
+    fn poll(&mut self) -> Poll<Option<Notification>, S::Error> {
+        let notif = futures::try_ready!(self.stream.poll());
+        if let Some(notif) = &notif {
+            self.router.on_notification(&self.venue, notif);
+        }
+        Ok(Async::Ready(notif))
+    }
+}
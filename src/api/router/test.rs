@@ -0,0 +1,96 @@
+#![cfg(test)]
+
+use std::thread;
+use std::time::Duration;
+use futures::prelude::*;
+use futures::sync::mpsc::UnboundedSender;
+use crate::Side;
+use crate::tick::Tick;
+use crate::order_book::LimitUpdate;
+use crate::api::{ApiClient, Order, Notification, NotificationFlags};
+use crate::api::symbol::{Symbol, IntoWithSymbol};
+use crate::api::timestamp::IntoTimestamped;
+use crate::api::order_book::LiveOrderBook;
+use crate::api::sim::{Exchange, Client, Settings, Fees};
+use crate::api::order_manager::OrderState;
+use crate::api::router::{Router, Venue, Allocation};
+
+/// A live order book stuck on the given asks.
+fn order_book(asks: &[(u64, u64)]) -> (LiveOrderBook, UnboundedSender<Notification>) {
+    let (snd, rcv) = futures::sync::mpsc::unbounded();
+    let updates = asks.iter()
+        .map(|&(price, size)| LimitUpdate::new(price, size, Side::Ask).with_timestamp(0))
+        .collect();
+    let stream = futures::stream::iter_ok(vec![Notification::LimitUpdates(updates)]).chain(rcv);
+    (LiveOrderBook::from_stream(stream), snd)
+}
+
+/// A simulated venue with the given asks and taker fee, along with a client of it.
+fn venue(taker: f64, asks: &[(&str, &str)]) -> (Symbol, Client) {
+    let exchange = Exchange::new(Settings {
+        latency: 0,
+        fees: Fees { maker: 0., taker },
+        ..Settings::default()
+    });
+    let symbol = exchange.add_symbol("BTCUSD", Tick::new(100), Tick::new(1000)).unwrap();
+    let maker = exchange.client();
+    for &(price, size) in asks {
+        let order = Order::new(price, size, Side::Ask);
+        maker.order(order.with_symbol(symbol)).wait().unwrap();
+    }
+    (symbol, exchange.client())
+}
+
+#[test]
+fn route() {
+    let (symbol_a, client_a) = venue(0.01, &[("100.00", "1"), ("101.00", "5")]);
+    let (symbol_b, client_b) = venue(0., &[("100.50", "2")]);
+    let stream_a = client_a.stream_with_flags(symbol_a, NotificationFlags::ORDERS);
+    let stream_b = client_b.stream_with_flags(symbol_b, NotificationFlags::ORDERS);
+
+    // The book of `b` is stale: only 2 are actually available.
+    let (book_a, _a) = order_book(&[(10_000, 1_000), (10_100, 5_000)]);
+    let (book_b, _b) = order_book(&[(10_050, 3_000)]);
+    let router = Router::new(vec![
+        Venue::new("a", client_a, symbol_a, book_a, 0.01),
+        Venue::new("b", client_b, symbol_b, book_b, 0.),
+    ]);
+
+    // `b` is cheaper than `a` at 100.00 once fees are charged.
+    assert_eq!(router.plan(Side::Bid, 3.5, 100.9), vec![
+        Allocation { venue: "a".to_owned(), price: 10_000, size: 500 },
+        Allocation { venue: "b".to_owned(), price: 10_050, size: 3_000 },
+    ]);
+
+    for (name, stream) in vec![("a", stream_a), ("b", stream_b)] {
+        let stream = router.track(name, stream);
+        thread::spawn(move || stream.for_each(|_| Ok(())).wait());
+    }
+
+    let route = router.route(Side::Bid, 4., 100.9).wait().unwrap();
+    thread::sleep(Duration::from_millis(50));
+
+    let mut executions = router.executions(&route);
+    executions.sort_by(|a, b| a.venue.cmp(&b.venue));
+    let summary: Vec<_> = executions.iter()
+        .map(|execution| (execution.filled, execution.average_price, execution.state))
+        .collect();
+    assert_eq!(summary, vec![
+        (1., Some(100.), OrderState::Filled),
+        (2., Some(100.5), OrderState::Cancelled),
+    ]);
+    assert!(router.is_complete(&route));
+    assert_eq!(router.unfilled(&route), 1.);
+}
+
+#[test]
+fn reroute_incomplete() {
+    let (symbol, client) = venue(0., &[("100.00", "1")]);
+    let (book, _snd) = order_book(&[(10_000, 1_000)]);
+    let router = Router::new(vec![Venue::new("a", client, symbol, book, 0.)]);
+
+    // Notifications are not tracked: the child order stays open.
+    let route = router.route(Side::Bid, 1., 100.).wait().unwrap();
+    assert!(!router.is_complete(&route));
+    assert!(router.reroute(&route).wait().is_err());
+}
@@ -0,0 +1,321 @@
+//! A module defining a price-time priority matching engine.
+
+use std::collections::{HashMap, VecDeque};
+use crate::Side;
+use crate::tick::TickUnit;
+use crate::order_book::{OrderBook, LimitUpdate};
+use crate::api::{
+    OrderType,
+    TimeInForce,
+    Trade,
+    OrderConfirmation,
+    OrderUpdate,
+    OrderExpiration,
+};
+use crate::api::errors::{OrderErrorKind, CancelErrorKind};
+
+/// Identify the account owning an order.
+pub type AccountId = usize;
+
+#[derive(Copy, Clone, PartialEq, Debug, Default)]
+/// Fees charged on each trade, as a fraction of the traded notional (e.g. `0.001` for
+/// 0.1%). Commissions are expressed in units of `price tick * size tick`.
+pub struct Fees {
+    /// Fee charged to the liquidity provider.
+    pub maker: f64,
+
+    /// Fee charged to the liquidity consumer.
+    pub taker: f64,
+}
+
+impl Fees {
+    crate fn commission(fee: f64, price: TickUnit, size: TickUnit) -> TickUnit {
+        (price as f64 * size as f64 * fee).round() as TickUnit
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+/// An event produced by the matching engine.
+pub enum Event {
+    /// An order has been accepted by the engine.
+    OrderConfirmation(AccountId, OrderConfirmation),
+
+    /// An order has been (partially) filled.
+    OrderUpdate(AccountId, OrderUpdate),
+
+    /// An order has expired or was canceled.
+    OrderExpiration(AccountId, OrderExpiration),
+
+    /// A trade was executed.
+    Trade(Trade),
+
+    /// Some limits of the order book have changed. Each limit appears at most once.
+    LimitUpdates(Vec<LimitUpdate>),
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+struct RestingOrder {
+    order_id: String,
+    account: AccountId,
+    size: TickUnit,
+}
+
+#[derive(Clone, Debug, Default)]
+/// A matching engine for one symbol, tracking individual orders with a price-time
+/// priority. The aggregated view is maintained in an `OrderBook`.
+pub struct MatchingEngine {
+    book: OrderBook,
+    queues: HashMap<(Side, TickUnit), VecDeque<RestingOrder>>,
+
+    /// (account, order id) => (side, price)
+    orders: HashMap<(AccountId, String), (Side, TickUnit)>,
+
+    fees: Fees,
+}
+
+impl MatchingEngine {
+    /// Return an empty `MatchingEngine` charging the given fees.
+    pub fn new(fees: Fees) -> Self {
+        MatchingEngine {
+            fees,
+            ..Default::default()
+        }
+    }
+
+    /// Return the aggregated order book.
+    pub fn order_book(&self) -> &OrderBook {
+        &self.book
+    }
+
+    /// Return the side, price and remaining size of the given resting order of `account`,
+    /// if any.
+    pub fn order(&self, account: AccountId, order_id: &str)
+        -> Option<(Side, TickUnit, TickUnit)>
+    {
+        let &(side, price) = self.orders.get(&(account, order_id.to_owned()))?;
+        let size = self.queues[&(side, price)]
+            .iter()
+            .find(|order| order.account == account && order.order_id == order_id)?
+            .size;
+        Some((side, price, size))
+    }
+
+    /// Return the total size of the orders ahead of the given resting order of `account`
+    /// at its limit.
+    ///
+    /// # Complexity
+    /// `O(k)` where `k` is the number of orders at the limit.
+    pub fn queue_position(&self, account: AccountId, order_id: &str) -> Option<TickUnit> {
+        let &(side, price) = self.orders.get(&(account, order_id.to_owned()))?;
+        let mut ahead = 0;
+        for order in &self.queues[&(side, price)] {
+            if order.account == account && order.order_id == order_id {
+                return Some(ahead);
+            }
+            ahead += order.size;
+        }
+        None
+    }
+
+    fn crosses(&self, side: Side, price: TickUnit) -> bool {
+        match side {
+            Side::Bid => price >= self.book.best_ask(),
+            Side::Ask => price <= self.book.best_bid(),
+        }
+    }
+
+    /// Size available on the other side at prices crossing `price`.
+    fn available(&self, side: Side, price: TickUnit) -> TickUnit {
+        match side {
+            Side::Bid => self.book.ask()
+                .take_while(|(&p, _)| p <= price)
+                .map(|(_, &size)| size)
+                .sum(),
+            Side::Ask => self.book.bid()
+                .take_while(|(&p, _)| p >= price)
+                .map(|(_, &size)| size)
+                .sum(),
+        }
+    }
+
+    fn update_limit(
+        &mut self,
+        side: Side,
+        price: TickUnit,
+        delta: i128,
+        updates: &mut Vec<LimitUpdate>
+    )
+    {
+        let size = (i128::from(self.book.size_at_limit(side, price)) + delta) as TickUnit;
+        let update = LimitUpdate::new(price, size, side);
+        self.book.update(update);
+
+        match updates.iter_mut().find(|u| u.side == side && u.price == price) {
+            Some(previous) => *previous = update,
+            None => updates.push(update),
+        }
+    }
+
+    /// Insert a new order and match it against the resting orders.
+    ///
+    /// # Errors
+    /// Return `Err` if `order_id` is already in use by `account`, or if `order_type` is
+    /// `OrderType::LimitMaker` and the order would take liquidity.
+    pub fn insert(
+        &mut self,
+        account: AccountId,
+        order_id: String,
+        side: Side,
+        price: TickUnit,
+        size: TickUnit,
+        order_type: OrderType,
+        time_in_force: TimeInForce
+    ) -> Result<Vec<Event>, OrderErrorKind>
+    {
+        if self.orders.contains_key(&(account, order_id.clone())) {
+            return Err(OrderErrorKind::DuplicateOrder);
+        }
+
+        if order_type == OrderType::LimitMaker && self.crosses(side, price) {
+            return Err(OrderErrorKind::WouldTakeLiquidity);
+        }
+
+        let mut events = vec![
+            Event::OrderConfirmation(account, OrderConfirmation {
+                order_id: order_id.clone(),
+                price,
+                size,
+                side,
+            })
+        ];
+
+        if time_in_force == TimeInForce::FillOrKilll && self.available(side, price) < size {
+            events.push(Event::OrderExpiration(account, OrderExpiration {
+                order_id,
+            }));
+            return Ok(events);
+        }
+
+        let other_side = match side {
+            Side::Bid => Side::Ask,
+            Side::Ask => Side::Bid,
+        };
+
+        let mut updates = Vec::new();
+        let mut remaining = size;
+        while remaining > 0 && self.crosses(side, price) {
+            let best = match other_side {
+                Side::Bid => self.book.best_bid(),
+                Side::Ask => self.book.best_ask(),
+            };
+
+            let (maker, consumed) = {
+                let queue = self.queues.get_mut(&(other_side, best)).expect("empty limit");
+                let maker = queue.front_mut().expect("empty limit");
+                let consumed = std::cmp::min(maker.size, remaining);
+                maker.size -= consumed;
+                let filled = maker.clone();
+                if maker.size == 0 {
+                    queue.pop_front();
+                    if queue.is_empty() {
+                        self.queues.remove(&(other_side, best));
+                    }
+                }
+                (filled, consumed)
+            };
+            remaining -= consumed;
+
+            if maker.size == 0 {
+                self.orders.remove(&(maker.account, maker.order_id.clone()));
+            }
+
+            events.push(Event::Trade(Trade {
+                price: best,
+                size: consumed,
+                maker_side: other_side,
+            }));
+
+            events.push(Event::OrderUpdate(maker.account, OrderUpdate {
+                order_id: maker.order_id,
+                consumed_size: consumed,
+                remaining_size: maker.size,
+                consumed_price: best,
+                commission: Fees::commission(self.fees.maker, best, consumed),
+            }));
+
+            events.push(Event::OrderUpdate(account, OrderUpdate {
+                order_id: order_id.clone(),
+                consumed_size: consumed,
+                remaining_size: remaining,
+                consumed_price: best,
+                commission: Fees::commission(self.fees.taker, best, consumed),
+            }));
+
+            self.update_limit(other_side, best, -i128::from(consumed), &mut updates);
+        }
+
+        if remaining > 0 {
+            match time_in_force {
+                TimeInForce::GoodTilCanceled => {
+                    self.queues.entry((side, price)).or_insert_with(VecDeque::new).push_back(
+                        RestingOrder {
+                            order_id: order_id.clone(),
+                            account,
+                            size: remaining,
+                        }
+                    );
+                    self.orders.insert((account, order_id), (side, price));
+                    self.update_limit(side, price, i128::from(remaining), &mut updates);
+                }
+
+                TimeInForce::ImmediateOrCancel | TimeInForce::FillOrKilll => {
+                    events.push(Event::OrderExpiration(account, OrderExpiration {
+                        order_id,
+                    }));
+                }
+            }
+        }
+
+        if !updates.is_empty() {
+            events.push(Event::LimitUpdates(updates));
+        }
+        Ok(events)
+    }
+
+    /// Cancel a resting order owned by `account`.
+    ///
+    /// # Errors
+    /// Return `Err` if no such order is resting in the book.
+    pub fn cancel(&mut self, account: AccountId, order_id: &str)
+        -> Result<Vec<Event>, CancelErrorKind>
+    {
+        let key = (account, order_id.to_owned());
+        let (side, price) = match self.orders.remove(&key) {
+            Some(location) => location,
+            None => return Err(CancelErrorKind::UnknownOrder),
+        };
+
+        let order = {
+            let queue = self.queues.get_mut(&(side, price)).expect("empty limit");
+            let index = queue.iter()
+                .position(|order| order.account == account && order.order_id == order_id)
+                .expect("order not found at its limit");
+
+            let order = queue.remove(index).unwrap();
+            if queue.is_empty() {
+                self.queues.remove(&(side, price));
+            }
+            order
+        };
+
+        let mut updates = Vec::new();
+        self.update_limit(side, price, -i128::from(order.size), &mut updates);
+
+        Ok(vec![
+            Event::OrderExpiration(account, OrderExpiration {
+                order_id: order.order_id,
+            }),
+            Event::LimitUpdates(updates),
+        ])
+    }
+}
@@ -0,0 +1,374 @@
+//! Implementation of `ApiClient` for a local exchange simulator.
+//!
+//! An `Exchange` runs one price-time priority matching engine per symbol, and any number
+//! of `Client`s can connect to it, each one acting as a distinct account. Clients emit
+//! the same notifications as the real exchange clients, so that strategies can be run
+//! end-to-end without touching a real exchange.
+
+pub mod matching;
+mod test;
+
+use std::collections::HashMap;
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::{Duration, Instant};
+use std::thread;
+use futures::prelude::*;
+use futures::sync::oneshot;
+use crate::tick::Tick;
+use crate::order_book::OrderBook;
+use crate::api::{
+    self,
+    ApiClient,
+    GenerateOrderId,
+    Notification,
+    NotificationFlags,
+    Order,
+    OrderAck,
+    Cancel,
+    CancelAck,
+    Balances,
+};
+use crate::api::errors::{ApiError, RestErrorKind, ErrorKind, OrderErrorKind, CancelErrorKind};
+use crate::api::symbol::{Symbol, WithSymbol};
+use crate::api::timestamp::{Timestamped, IntoTimestamped, timestamp_ms};
+use crate::api::channel::{self, ChannelSettings, Receiver, Sender};
+
+use self::matching::{MatchingEngine, Event, AccountId};
+
+pub use self::matching::Fees;
+
+#[derive(Copy, Clone, PartialEq, Debug, Default)]
+/// Settings of a simulated exchange.
+pub struct Settings {
+    /// One-way latency between a client and the exchange, in ms. A REST request is
+    /// processed `latency` ms after being sent, and its response is received `latency` ms
+    /// later. Notifications are received `latency` ms after the exchange emitted them.
+    /// With a zero latency, requests are processed synchronously.
+    pub latency: u64,
+
+    /// Fees charged on each trade.
+    pub fees: Fees,
+
+    /// Settings of the notification channel of each stream. With `OverflowPolicy::Block`,
+    /// a full channel stalls the whole exchange until the consumer makes some room.
+    pub channel: ChannelSettings,
+}
+
+type Job = Box<dyn FnOnce() + Send>;
+
+#[derive(Clone)]
+/// A FIFO queue of jobs, each one being run by a background thread once its due
+/// time is reached. Since all delays are equal, due times are non-decreasing.
+struct DelayLine {
+    snd: Arc<Mutex<mpsc::Sender<(Instant, Job)>>>,
+}
+
+impl DelayLine {
+    fn new() -> Self {
+        let (snd, rcv) = mpsc::channel::<(Instant, Job)>();
+        thread::spawn(move || {
+            for (due, job) in rcv {
+                let now = Instant::now();
+                if due > now {
+                    thread::sleep(due - now);
+                }
+                job();
+            }
+        });
+
+        DelayLine {
+            snd: Arc::new(Mutex::new(snd)),
+        }
+    }
+
+    fn schedule(&self, delay: Duration, job: Job) {
+        // The thread only terminates once every sender has been dropped.
+        self.snd.lock().unwrap().send((Instant::now() + delay, job)).unwrap();
+    }
+}
+
+struct Subscriber {
+    account: AccountId,
+    symbol: String,
+    flags: NotificationFlags,
+    snd: Arc<Sender>,
+}
+
+struct State {
+    symbols: HashMap<String, Symbol>,
+
+    /// symbol name => matching engine
+    engines: HashMap<String, MatchingEngine>,
+
+    subscribers: Vec<Subscriber>,
+    next_account: AccountId,
+}
+
+#[derive(Clone)]
+/// A simulated exchange, shared by all its clients.
+pub struct Exchange {
+    settings: Settings,
+    state: Arc<Mutex<State>>,
+    delay_line: Option<DelayLine>,
+}
+
+impl Exchange {
+    /// Create a new simulated exchange with the given `settings` and without any symbol.
+    pub fn new(settings: Settings) -> Self {
+        Exchange {
+            settings,
+            state: Arc::new(Mutex::new(State {
+                symbols: HashMap::new(),
+                engines: HashMap::new(),
+                subscribers: Vec::new(),
+                next_account: 0,
+            })),
+            delay_line: if settings.latency > 0 { Some(DelayLine::new()) } else { None },
+        }
+    }
+
+    /// List a new symbol on the exchange. Commissions for this symbol are expressed in
+    /// units of `price_tick * size_tick`. Return `None` if the symbol name is too long.
+    pub fn add_symbol(&self, name: &str, price_tick: Tick, size_tick: Tick) -> Option<Symbol> {
+        let commission_tick = Tick::new(price_tick.ticks_per_unit() * size_tick.ticks_per_unit());
+        let symbol = Symbol::new(name, price_tick, size_tick)?
+            .with_commission_tick(commission_tick);
+
+        let mut state = self.state.lock().unwrap();
+        state.symbols.insert(name.to_lowercase(), symbol);
+        state.engines.insert(symbol.name().to_owned(), MatchingEngine::new(self.settings.fees));
+        Some(symbol)
+    }
+
+    /// Return a new client connected to this exchange, acting as a new account.
+    pub fn client(&self) -> Client {
+        let mut state = self.state.lock().unwrap();
+        let account = state.next_account;
+        state.next_account += 1;
+
+        Client {
+            exchange: self.clone(),
+            account,
+        }
+    }
+
+    /// Run `job` after the configured latency, or immediately if the latency is zero.
+    fn delayed<F: FnOnce() + Send + 'static>(&self, job: F) {
+        match &self.delay_line {
+            Some(delay_line) => {
+                delay_line.schedule(Duration::from_millis(self.settings.latency), Box::new(job))
+            }
+            None => job(),
+        }
+    }
+
+    /// Forward the events emitted by the engine of `symbol` to the relevant subscribers.
+    fn dispatch(&self, state: &mut State, symbol: &str, events: Vec<Event>) {
+        let timestamp = timestamp_ms();
+        state.subscribers.retain(|sub| !sub.snd.is_closed());
+
+        for event in events {
+            let (owner, flag, notif) = match event {
+                Event::OrderConfirmation(account, confirmation) => (
+                    Some(account),
+                    NotificationFlags::ORDERS,
+                    Notification::OrderConfirmation(confirmation.with_timestamp(timestamp)),
+                ),
+                Event::OrderUpdate(account, update) => (
+                    Some(account),
+                    NotificationFlags::ORDERS,
+                    Notification::OrderUpdate(update.with_timestamp(timestamp)),
+                ),
+                Event::OrderExpiration(account, expiration) => (
+                    Some(account),
+                    NotificationFlags::ORDERS,
+                    Notification::OrderExpiration(expiration.with_timestamp(timestamp)),
+                ),
+                Event::Trade(trade) => (
+                    None,
+                    NotificationFlags::TRADES,
+                    Notification::Trade(trade.with_timestamp(timestamp)),
+                ),
+                Event::LimitUpdates(updates) => (
+                    None,
+                    // The full book is also sent to partial book and best bid offer subscribers.
+                    NotificationFlags::ORDER_BOOK |
+                        NotificationFlags::PARTIAL_ORDER_BOOK |
+                        NotificationFlags::BEST_BID_OFFER,
+                    Notification::LimitUpdates(
+                        updates.into_iter().map(|u| u.with_timestamp(timestamp)).collect()
+                    ),
+                ),
+            };
+
+            let subscribers = state.subscribers.iter().filter(|sub| {
+                sub.symbol == symbol &&
+                    sub.flags.intersects(flag) &&
+                    owner.map(|owner| owner == sub.account).unwrap_or(true)
+            });
+
+            for sub in subscribers {
+                let snd = sub.snd.clone();
+                let notif = notif.clone();
+                self.delayed(move || {
+                    // The consumer may have dropped the stream in the meantime.
+                    let _ = snd.send(notif);
+                });
+            }
+        }
+    }
+
+    /// Perform `request` on the exchange state after the configured latency, and return
+    /// a future resolving to its result after the configured latency again.
+    fn request<T, K, F>(&self, request: F)
+        -> Box<dyn Future<Item = T, Error = ApiError<K>> + Send + 'static>
+            where T: Send + 'static,
+                  K: ErrorKind + Send + 'static,
+                  F: FnOnce(&Exchange, &mut State) -> Result<T, RestErrorKind<K>> + Send + 'static
+    {
+        let (snd, rcv) = oneshot::channel();
+        let exchange = self.clone();
+        self.delayed(move || {
+            let result = {
+                let mut state = exchange.state.lock().unwrap();
+                request(&exchange, &mut state)
+            };
+            exchange.delayed(move || {
+                let _ = snd.send(result);
+            });
+        });
+
+        let fut = rcv
+            .map_err(|_| ApiError::RestError(RestErrorKind::UnknownStatus.into()))
+            .and_then(|result| result.map_err(|kind| ApiError::RestError(kind.into())));
+        Box::new(fut)
+    }
+}
+
+/// A client of a simulated exchange.
+///
+/// # Note
+/// Balances are not simulated: `balances` always returns an empty map, and orders are never
+/// rejected for insufficient balance.
+pub struct Client {
+    exchange: Exchange,
+    account: AccountId,
+}
+
+impl ApiClient for Client {
+    type Stream = Receiver;
+
+    fn find_symbol(&self, symbol: &str) -> Option<Symbol> {
+        self.exchange.state.lock().unwrap().symbols.get(&symbol.to_lowercase()).cloned()
+    }
+
+    fn stream_with_flags(&self, symbol: Symbol, flags: NotificationFlags) -> Self::Stream {
+        let (snd, rcv) = channel::channel(self.exchange.settings.channel);
+        let mut state = self.exchange.state.lock().unwrap();
+
+        // Like the real exchanges, start with a snapshot of the order book.
+        let order_book = NotificationFlags::ORDER_BOOK |
+            NotificationFlags::PARTIAL_ORDER_BOOK |
+            NotificationFlags::BEST_BID_OFFER;
+        if flags.intersects(order_book) {
+            if let Some(engine) = state.engines.get(symbol.name()) {
+                let timestamp = timestamp_ms();
+                let snapshot = OrderBook::new()
+                    .diff(engine.order_book())
+                    .map(|update| update.with_timestamp(timestamp))
+                    .collect();
+                snd.send(Notification::LimitUpdates(snapshot)).unwrap();
+            }
+        }
+
+        state.subscribers.push(Subscriber {
+            account: self.account,
+            symbol: symbol.name().to_owned(),
+            flags,
+            snd: Arc::new(snd),
+        });
+        rcv
+    }
+
+    fn order(&self, order: WithSymbol<&Order>)
+        -> Box<dyn Future<Item = Timestamped<OrderAck>, Error = api::errors::OrderError> + Send + 'static>
+    {
+        let symbol = order.symbol();
+        let account = self.account;
+        let order_id = order.order_id()
+            .map(|order_id| order_id.to_owned())
+            .unwrap_or_else(|| Self::new_order_id(""));
+        let price = order.price.ticked(symbol.price_tick());
+        let size = order.size.ticked(symbol.size_tick());
+        let side = order.side;
+        let order_type = order.type_;
+        let time_in_force = order.time_in_force;
+
+        self.exchange.request(move |exchange, state| {
+            if size == 0 {
+                return Err(RestErrorKind::InvalidRequest);
+            }
+
+            let events = state.engines.get_mut(symbol.name())
+                .ok_or(RestErrorKind::<OrderErrorKind>::InvalidRequest)?
+                .insert(account, order_id.clone(), side, price, size, order_type, time_in_force)
+                .map_err(RestErrorKind::Specific)?;
+
+            exchange.dispatch(state, symbol.name(), events);
+            Ok(OrderAck {
+                order_id,
+            }.timestamped())
+        })
+    }
+
+    fn cancel(&self, cancel: WithSymbol<&Cancel>)
+        -> Box<dyn Future<Item = Timestamped<CancelAck>, Error = api::errors::CancelError> + Send + 'static>
+    {
+        let symbol = cancel.symbol();
+        let account = self.account;
+        let order_id = cancel.order_id().to_owned();
+
+        self.exchange.request(move |exchange, state| {
+            let events = state.engines.get_mut(symbol.name())
+                .ok_or(RestErrorKind::<CancelErrorKind>::InvalidRequest)?
+                .cancel(account, &order_id)
+                .map_err(RestErrorKind::Specific)?;
+
+            exchange.dispatch(state, symbol.name(), events);
+            Ok(CancelAck.timestamped())
+        })
+    }
+
+    fn ping(&self)
+        -> Box<dyn Future<Item = Timestamped<()>, Error = api::errors::Error> + Send + 'static>
+    {
+        self.exchange.request(|_, _| Ok(().timestamped()))
+    }
+
+    fn order_book(&self, symbol: Symbol)
+        -> Box<dyn Future<Item = Timestamped<OrderBook>, Error = api::errors::Error> + Send + 'static>
+    {
+        self.exchange.request(move |_, state| {
+            let engine = state.engines.get(symbol.name())
+                .ok_or(RestErrorKind::InvalidRequest)?;
+            Ok(engine.order_book().clone().timestamped())
+        })
+    }
+
+    fn balances(&self)
+        -> Box<dyn Future<Item = Balances, Error = api::errors::Error> + Send + 'static>
+    {
+        Box::new(Ok(Balances::new()).into_future())
+    }
+}
+
+impl GenerateOrderId for Client {
+    fn new_order_id(hint: &str) -> String {
+        if hint.is_empty() {
+            use uuid::Uuid;
+            Uuid::new_v4().to_string()
+        } else {
+            hint.to_owned()
+        }
+    }
+}
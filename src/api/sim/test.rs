@@ -0,0 +1,248 @@
+#![cfg(test)]
+
+use futures::prelude::*;
+use crate::Side;
+use crate::tick::Tick;
+use crate::order_book::LimitUpdate;
+use crate::api::{
+    ApiClient,
+    Order,
+    Cancel,
+    OrderType,
+    TimeInForce,
+    Notification,
+    NotificationFlags,
+    Trade,
+    OrderUpdate,
+    OrderExpiration,
+};
+use crate::api::errors::{ApiError, RestErrorKind, OrderErrorKind, CancelErrorKind};
+use crate::api::symbol::IntoWithSymbol;
+use crate::api::sim::{Exchange, Client, Settings, Fees};
+use crate::api::sim::matching::{MatchingEngine, Event};
+
+fn insert(
+    engine: &mut MatchingEngine,
+    account: usize,
+    order_id: &str,
+    side: Side,
+    price: u64,
+    size: u64,
+    time_in_force: TimeInForce,
+) -> Result<Vec<Event>, OrderErrorKind>
+{
+    engine.insert(
+        account,
+        order_id.to_owned(),
+        side,
+        price,
+        size,
+        OrderType::Limit,
+        time_in_force
+    )
+}
+
+#[test]
+fn price_time_priority() {
+    let mut engine = MatchingEngine::new(Fees { maker: 0.0, taker: 0.01 });
+    let gtc = TimeInForce::GoodTilCanceled;
+
+    insert(&mut engine, 0, "a", Side::Ask, 101, 5, gtc).unwrap();
+    insert(&mut engine, 0, "b", Side::Ask, 100, 3, gtc).unwrap();
+    insert(&mut engine, 1, "c", Side::Ask, 100, 4, gtc).unwrap();
+    assert_eq!(engine.queue_position(1, "c"), Some(3));
+    assert_eq!(engine.order_book().size_at_limit(Side::Ask, 100), 7);
+
+    let events = insert(&mut engine, 2, "d", Side::Bid, 101, 9, gtc).unwrap();
+    assert_eq!(events[1], Event::Trade(Trade { price: 100, size: 3, maker_side: Side::Ask }));
+    assert_eq!(events[2], Event::OrderUpdate(0, OrderUpdate {
+        order_id: "b".to_owned(),
+        consumed_size: 3,
+        remaining_size: 0,
+        consumed_price: 100,
+        commission: 0,
+    }));
+    assert_eq!(events[3], Event::OrderUpdate(2, OrderUpdate {
+        order_id: "d".to_owned(),
+        consumed_size: 3,
+        remaining_size: 6,
+        consumed_price: 100,
+        commission: 3,
+    }));
+    assert_eq!(events[4], Event::Trade(Trade { price: 100, size: 4, maker_side: Side::Ask }));
+    assert_eq!(events[7], Event::Trade(Trade { price: 101, size: 2, maker_side: Side::Ask }));
+    assert_eq!(events[10], Event::LimitUpdates(vec![
+        LimitUpdate::new(100, 0, Side::Ask),
+        LimitUpdate::new(101, 3, Side::Ask),
+    ]));
+    assert_eq!(events.len(), 11);
+
+    assert_eq!(engine.order(0, "a"), Some((Side::Ask, 101, 3)));
+    assert_eq!(engine.order(0, "b"), None);
+    assert_eq!(engine.order(2, "d"), None);
+}
+
+#[test]
+fn order_types() {
+    let mut engine = MatchingEngine::new(Fees::default());
+    insert(&mut engine, 0, "a", Side::Bid, 100, 5, TimeInForce::GoodTilCanceled).unwrap();
+
+    assert_eq!(
+        insert(&mut engine, 0, "a", Side::Bid, 99, 1, TimeInForce::GoodTilCanceled),
+        Err(OrderErrorKind::DuplicateOrder)
+    );
+
+    // Order ids are scoped by account.
+    insert(&mut engine, 1, "a", Side::Bid, 99, 1, TimeInForce::GoodTilCanceled).unwrap();
+    assert_eq!(engine.order(1, "a"), Some((Side::Bid, 99, 1)));
+    engine.cancel(1, "a").unwrap();
+    assert_eq!(engine.order(1, "a"), None);
+    assert_eq!(
+        engine.insert(
+            1,
+            "b".to_owned(),
+            Side::Ask,
+            100,
+            1,
+            OrderType::LimitMaker,
+            TimeInForce::GoodTilCanceled
+        ),
+        Err(OrderErrorKind::WouldTakeLiquidity)
+    );
+
+    // Not enough liquidity: nothing is filled.
+    let events = insert(&mut engine, 1, "c", Side::Ask, 100, 6, TimeInForce::FillOrKilll).unwrap();
+    assert_eq!(events.len(), 2);
+    assert_eq!(engine.order(0, "a"), Some((Side::Bid, 100, 5)));
+
+    // The remainder expires instead of resting.
+    let events = insert(
+        &mut engine,
+        1,
+        "d",
+        Side::Ask,
+        100,
+        6,
+        TimeInForce::ImmediateOrCancel
+    ).unwrap();
+    assert_eq!(events[events.len() - 2], Event::OrderExpiration(1, OrderExpiration {
+        order_id: "d".to_owned(),
+    }));
+    assert_eq!(engine.order_book().best_bid(), 0);
+    assert_eq!(engine.order_book().best_ask(), std::u64::MAX);
+}
+
+#[test]
+fn cancel() {
+    let mut engine = MatchingEngine::new(Fees::default());
+    insert(&mut engine, 0, "a", Side::Bid, 100, 5, TimeInForce::GoodTilCanceled).unwrap();
+
+    assert_eq!(engine.cancel(1, "a"), Err(CancelErrorKind::UnknownOrder));
+    assert_eq!(engine.cancel(0, "b"), Err(CancelErrorKind::UnknownOrder));
+
+    let events = engine.cancel(0, "a").unwrap();
+    assert_eq!(events[1], Event::LimitUpdates(vec![LimitUpdate::new(100, 0, Side::Bid)]));
+    assert_eq!(engine.order(0, "a"), None);
+    assert_eq!(engine.cancel(0, "a"), Err(CancelErrorKind::UnknownOrder));
+}
+
+#[test]
+fn client() {
+    use tokio::runtime::current_thread;
+
+    let exchange = Exchange::new(Settings::default());
+    let symbol = exchange.add_symbol("BTCUSD", Tick::new(100), Tick::new(1000)).unwrap();
+    let maker = exchange.client();
+    let taker = exchange.client();
+    assert_eq!(maker.find_symbol("btcusd"), Some(symbol));
+
+    let maker_stream = maker.stream_with_flags(symbol, NotificationFlags::ORDERS);
+    let taker_stream = taker.stream(symbol);
+
+    let order = Order::new("100.00", "1.5", Side::Ask).with_order_id::<Client>("maker");
+    let ack = current_thread::block_on_all(maker.order(order.with_symbol(symbol))).unwrap();
+    assert_eq!(ack.order_id, "maker");
+
+    let order = Order::new("101.00", "1", Side::Bid);
+    let ack = current_thread::block_on_all(taker.order(order.with_symbol(symbol))).unwrap();
+    assert!(!ack.order_id.is_empty());
+
+    let cancel = Cancel::new("maker".to_owned());
+    match current_thread::block_on_all(taker.cancel(cancel.with_symbol(symbol))) {
+        Err(ApiError::RestError(err)) => assert_eq!(
+            err.kind(),
+            RestErrorKind::Specific(CancelErrorKind::UnknownOrder)
+        ),
+        other => panic!("expected an unknown order error, got {:?}", other.map(|_| ())),
+    }
+    let cancel = Cancel::new("maker".to_owned());
+    current_thread::block_on_all(maker.cancel(cancel.with_symbol(symbol))).unwrap();
+
+    drop(maker);
+    drop(taker);
+    drop(exchange);
+
+    let notifs: Vec<_> = maker_stream.wait().map(Result::unwrap).collect();
+    match &notifs[..] {
+        [
+            Notification::OrderConfirmation(_),
+            Notification::OrderUpdate(update),
+            Notification::OrderExpiration(_),
+        ] => assert_eq!(update.remaining_size, 500),
+        other => panic!("unexpected notifications: {:?}", other),
+    }
+
+    let notifs: Vec<_> = taker_stream.wait().map(Result::unwrap).collect();
+    match &notifs[..] {
+        [
+            Notification::LimitUpdates(snapshot),
+            Notification::LimitUpdates(_),
+            Notification::OrderConfirmation(_),
+            Notification::Trade(trade),
+            Notification::OrderUpdate(update),
+            Notification::LimitUpdates(_),
+            Notification::LimitUpdates(_),
+        ] => {
+            assert!(snapshot.is_empty());
+            assert_eq!(trade.price, 10000);
+            assert_eq!(update.remaining_size, 0);
+        }
+        other => panic!("unexpected notifications: {:?}", other),
+    }
+}
+
+#[test]
+fn bounded_stream() {
+    use crate::api::channel::{ChannelSettings, OverflowPolicy, Overflow};
+
+    let exchange = Exchange::new(Settings {
+        channel: ChannelSettings {
+            capacity: Some(2),
+            policy: OverflowPolicy::DropOldest,
+        },
+        ..Settings::default()
+    });
+    let symbol = exchange.add_symbol("BTCUSD", Tick::new(100), Tick::new(1000)).unwrap();
+    let client = exchange.client();
+    let stream = client.stream_with_flags(symbol, NotificationFlags::ORDERS);
+
+    for order_id in &["a", "b", "c"] {
+        let order = Order::new("100.00", "1", Side::Bid).with_order_id::<Client>(order_id);
+        client.order(order.with_symbol(symbol)).wait().unwrap();
+    }
+    drop(client);
+    drop(exchange);
+
+    let notifs: Vec<_> = stream.wait().map(Result::unwrap).collect();
+    match &notifs[..] {
+        [
+            Notification::Overflow(overflow),
+            Notification::OrderConfirmation(b),
+            Notification::OrderConfirmation(c),
+        ] => {
+            assert_eq!(**overflow, Overflow { dropped: 1, conflated: 0 });
+            assert_eq!((b.order_id.as_ref(), c.order_id.as_ref()), ("b", "c"));
+        }
+        other => panic!("unexpected notifications: {:?}", other),
+    }
+}
@@ -0,0 +1,37 @@
+//! A module defining an event-driven strategy interface, which can be run either live
+//! against any `ApiClient` or in the backtester.
+
+use futures::prelude::*;
+use crate::api::{ApiClient, Notification};
+use crate::api::symbol::Symbol;
+
+/// An event-driven trading strategy, trading on one symbol through an `ApiClient`.
+///
+/// # Note
+/// Callbacks must not block on the futures returned by the client: in the backtester,
+/// these futures only resolve once the callback has returned. They should rather be
+/// spawned with `tokio::executor::current_thread::spawn`, which works both when running
+/// live through `run` and in the backtester, and the outcome of orders be tracked through
+/// the `ORDERS` notifications.
+pub trait Strategy<C: ApiClient> {
+    /// Called once before any notification is received.
+    fn start(&mut self, _client: &C, _symbol: Symbol) { }
+
+    /// Called for each notification received for the traded symbol.
+    fn on_notification(&mut self, client: &C, notification: &Notification);
+}
+
+/// Run `strategy` live on `symbol` until the notification stream ends. Block the
+/// current thread.
+pub fn run<C, S>(client: &C, symbol: Symbol, strategy: &mut S)
+    where C: ApiClient, S: Strategy<C>
+{
+    use tokio::runtime::current_thread;
+
+    strategy.start(client, symbol);
+    let fut = client.stream(symbol).for_each(|notif| {
+        strategy.on_notification(client, &notif);
+        Ok(())
+    });
+    let _ = current_thread::block_on_all(fut);
+}
@@ -27,6 +27,11 @@ impl Symbol {
         })
     }
 
+    crate fn with_commission_tick(mut self, commission_tick: Tick) -> Self {
+        self.commission_tick = commission_tick;
+        self
+    }
+
     /// Symbol name.
     pub fn name(&self) -> &str {
         &self.name
@@ -6,6 +6,24 @@ use serde_derive::{Serialize, Deserialize};
 /// Base type for timestamps.
 pub type Timestamp = u64;
 
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Serialize, Deserialize)]
+#[serde(transparent)]
+/// A local UTC timestamp in microseconds, as opposed to `Timestamp` which is in
+/// milliseconds.
+pub struct TimestampUs(pub u64);
+
+impl TimestampUs {
+    /// Convert a timestamp in milliseconds.
+    pub fn from_ms(timestamp: Timestamp) -> Self {
+        TimestampUs(timestamp * 1000)
+    }
+
+    /// Convert to milliseconds, truncating the sub-millisecond part.
+    pub fn as_ms(self) -> Timestamp {
+        self.0 / 1000
+    }
+}
+
 /// Return UTC timestamp in milliseconds.
 pub fn timestamp_ms() -> Timestamp {
     use std::time::{SystemTime, UNIX_EPOCH};
@@ -16,24 +34,165 @@ pub fn timestamp_ms() -> Timestamp {
     timestamp.as_secs() * 1000 + u64::from(timestamp.subsec_millis())
 }
 
+/// Return UTC timestamp in microseconds.
+pub fn timestamp_us() -> TimestampUs {
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("time went backward");
+    TimestampUs(timestamp.as_secs() * 1_000_000 + u64::from(timestamp.subsec_micros()))
+}
+
+fn is_false(value: &bool) -> bool {
+    !*value
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+/// Range of exchange sequence numbers covered by the message a notification was
+/// built from. Messages are consecutive if the `first` sequence number of a message
+/// is the `last` sequence number of the previous one plus one.
+///
+/// Sequence numbers are only comparable between notifications of the same kind: e.g.
+/// binance trades are numbered by trade ids while book updates are numbered by update ids.
+pub struct Sequence {
+    /// First sequence number covered by the message.
+    pub first: u64,
+
+    /// Last sequence number covered by the message.
+    pub last: u64,
+}
+
+impl Sequence {
+    /// Return a `Sequence` covering the single sequence number `sequence`.
+    pub fn single(sequence: u64) -> Self {
+        Sequence {
+            first: sequence,
+            last: sequence,
+        }
+    }
+
+    /// Return whether `self` directly follows `previous`.
+    pub fn follows(&self, previous: &Sequence) -> bool {
+        previous.last + 1 == self.first
+    }
+}
+
 #[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
 /// Wrapper around a type carrying an additional timestamp. Deref to `T`.
+///
+/// The timestamp is either given by the exchange (`with_timestamp`) or taken from the
+/// local clock (`timestamped`), which can be told apart with `exchange_timestamp`.
+/// Notifications forwarded by exchange streams also carry the local time at which
+/// the underlying message was received, the time at which the exchange sent it and its
+/// sequence number, when the exchange reports them.
 pub struct Timestamped<T> {
     timestamp: Timestamp,
+    #[serde(default, skip_serializing_if = "is_false")]
+    local: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    sent: Option<Timestamp>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    received: Option<TimestampUs>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    sequence: Option<Sequence>,
     #[serde(flatten)]
     inner: T,
 }
 
 impl<T> Timestamped<T> {
-    /// Registered timestamp.
+    /// Registered timestamp, in ms. For notifications, this is the time of the event as
+    /// reported by the exchange, or the local time if the exchange did not report one.
     pub fn timestamp(&self) -> Timestamp {
         self.timestamp
     }
 
+    /// Registered timestamp if it was given by the exchange, `None` if it was taken from
+    /// the local clock.
+    pub fn exchange_timestamp(&self) -> Option<Timestamp> {
+        if self.local {
+            None
+        } else {
+            Some(self.timestamp)
+        }
+    }
+
+    /// Time at which the exchange sent the underlying message, in ms, if reported.
+    pub fn sent(&self) -> Option<Timestamp> {
+        self.sent
+    }
+
+    /// Local time at which the underlying message was received, if any.
+    pub fn received(&self) -> Option<TimestampUs> {
+        self.received
+    }
+
+    /// Delay in µs between the exchange sending the underlying message, or the event
+    /// itself if the send time is unknown, and the local receive time. This includes the
+    /// offset between the exchange clock and the local clock.
+    pub fn latency(&self) -> Option<u64> {
+        let sent = self.sent.or_else(|| self.exchange_timestamp())?;
+        Some(self.received?.0.saturating_sub(TimestampUs::from_ms(sent).0))
+    }
+
+    /// Set the time at which the exchange sent the underlying message, in ms.
+    pub fn with_sent(mut self, sent: Timestamp) -> Self {
+        self.sent = Some(sent);
+        self
+    }
+
+    /// Exchange sequence number of the underlying message, if any.
+    pub fn sequence(&self) -> Option<Sequence> {
+        self.sequence
+    }
+
+    /// Set the local receive time.
+    pub fn with_received(mut self, received: TimestampUs) -> Self {
+        self.received = Some(received);
+        self
+    }
+
+    /// Set the exchange sequence number.
+    pub fn with_sequence(mut self, sequence: Sequence) -> Self {
+        self.sequence = Some(sequence);
+        self
+    }
+
+    /// Set the local receive time if not already set.
+    crate fn set_received(&mut self, received: TimestampUs) {
+        if self.received.is_none() {
+            self.received = Some(received);
+        }
+    }
+
     /// Return the wrapped value.
     pub fn into_inner(self) -> T {
         self.inner
     }
+
+    /// Apply `f` to the wrapped value, keeping the timestamps and sequence number.
+    pub fn map<U, F: FnOnce(T) -> U>(self, f: F) -> Timestamped<U> {
+        Timestamped {
+            timestamp: self.timestamp,
+            local: self.local,
+            sent: self.sent,
+            received: self.received,
+            sequence: self.sequence,
+            inner: f(self.inner),
+        }
+    }
+}
+
+impl<T: PartialEq> Timestamped<T> {
+    /// Return whether `self` and `other` carry the same exchange data, i.e. the same
+    /// timestamp, sequence number and wrapped value, whatever their send and receive times:
+    /// e.g. the same event received twice, by two connections.
+    pub fn same_exchange_data(&self, other: &Self) -> bool {
+        self.timestamp == other.timestamp &&
+            self.local == other.local &&
+            self.sequence == other.sequence &&
+            self.inner == other.inner
+    }
 }
 
 impl<T> Deref for Timestamped<T> {
@@ -46,21 +205,30 @@ impl<T> Deref for Timestamped<T> {
 
 /// Wrap `Self` in a `TimeStamped<Self>`.
 pub trait IntoTimestamped: Sized {
-    /// Add the current timestamp to `self`.
+    /// Add the current local timestamp to `self`.
     fn timestamped(self) -> Timestamped<Self> {
-        Timestamped {
-            timestamp: timestamp_ms(),
-            inner: self,
-        }
+        self.with_local_timestamp(timestamp_ms())
     }
 
-    /// Add the given `timestamp` to `self`.
+    /// Add the given exchange `timestamp` to `self`.
     fn with_timestamp(self, timestamp: Timestamp) -> Timestamped<Self> {
         Timestamped {
             timestamp,
+            local: false,
+            sent: None,
+            received: None,
+            sequence: None,
             inner: self,
         }
     }
+
+    /// Add the given `timestamp`, taken from the local clock, to `self`.
+    fn with_local_timestamp(self, timestamp: Timestamp) -> Timestamped<Self> {
+        Timestamped {
+            local: true,
+            ..self.with_timestamp(timestamp)
+        }
+    }
 }
 
 impl<T: Sized> IntoTimestamped for T { }
@@ -0,0 +1,105 @@
+//! A module defining a stream adaptor which replaces limit updates by best bid and offer
+//! changes, for consumers which only need the top of the book.
+
+mod test;
+
+use futures::prelude::*;
+use crate::Side;
+use crate::order_book::OrderBook;
+use crate::api::{Notification, BestBidOffer};
+
+impl BestBidOffer {
+    /// Best bid and offer of `order_book`.
+    pub fn of(order_book: &OrderBook) -> Self {
+        let (bid_price, ask_price) = (order_book.best_bid(), order_book.best_ask());
+        BestBidOffer {
+            bid_price,
+            bid_size: order_book.size_at_limit(Side::Bid, bid_price),
+            ask_price,
+            ask_size: order_book.size_at_limit(Side::Ask, ask_price),
+        }
+    }
+}
+
+/// A stream adaptor maintaining the order book from the `LimitUpdates` notifications of
+/// the underlying stream, and forwarding a `Notification::BestBidOffer` instead each time
+/// the top of the book changes. Other notifications are forwarded as is.
+///
+/// The emitted notifications carry the timestamps and sequence number of the last update
+/// of the batch which changed the top of the book.
+pub struct TopOfBook<S> {
+    stream: S,
+    order_book: OrderBook,
+    last: Option<BestBidOffer>,
+    forward_updates: bool,
+    pending: Option<Notification>,
+}
+
+impl<S: Stream<Item = Notification>> TopOfBook<S> {
+    /// Only forward the best bid and offer changes of `stream`.
+    pub fn new(stream: S) -> Self {
+        TopOfBook {
+            stream,
+            order_book: OrderBook::new(),
+            last: None,
+            forward_updates: false,
+            pending: None,
+        }
+    }
+
+    /// Forward the limit updates of `stream` as well, each batch being followed by the best
+    /// bid and offer change it caused, if any.
+    pub fn with_limit_updates(stream: S) -> Self {
+        TopOfBook {
+            forward_updates: true,
+            ..Self::new(stream)
+        }
+    }
+
+    /// Order book maintained by `self`.
+    pub fn order_book(&self) -> &OrderBook {
+        &self.order_book
+    }
+}
+
+impl<S: Stream<Item = Notification>> Stream for TopOfBook<S> {
+    type Item = Notification;
+    type Error = S::Error;
+
+    fn poll(&mut self) -> Poll<Option<Notification>, S::Error> {
+        if let Some(notif) = self.pending.take() {
+            return Ok(Async::Ready(Some(notif)));
+        }
+
+        loop {
+            let updates = match futures::try_ready!(self.stream.poll()) {
+                Some(Notification::LimitUpdates(updates)) => updates,
+                other => return Ok(Async::Ready(other)),
+            };
+
+            let last_update = match updates.last() {
+                Some(update) => *update,
+                None => continue,
+            };
+            for update in &updates {
+                self.order_book.update(update.into_inner());
+            }
+
+            let bbo = BestBidOffer::of(&self.order_book);
+            let change = if self.last != Some(bbo) {
+                self.last = Some(bbo);
+                Some(Notification::BestBidOffer(last_update.map(|_| bbo)))
+            } else {
+                None
+            };
+
+            if self.forward_updates {
+                self.pending = change;
+                return Ok(Async::Ready(Some(Notification::LimitUpdates(updates))));
+            }
+            if change.is_some() {
+                return Ok(Async::Ready(change));
+            }
+        }
+    }
+}
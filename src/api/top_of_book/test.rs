@@ -0,0 +1,63 @@
+#![cfg(test)]
+
+use futures::prelude::*;
+use crate::Side;
+use crate::tick::TickUnit;
+use crate::api::{Notification, BestBidOffer};
+use crate::api::timestamp::IntoTimestamped;
+use crate::api::notifications::{limits, trade};
+use crate::api::top_of_book::TopOfBook;
+
+fn bbo(timestamp: u64, bid: (u64, u64), ask: (u64, u64)) -> Notification {
+    Notification::BestBidOffer(BestBidOffer {
+        bid_price: bid.0,
+        bid_size: bid.1,
+        ask_price: ask.0,
+        ask_size: ask.1,
+    }.with_timestamp(timestamp))
+}
+
+#[test]
+fn top_of_book() {
+    let trade = trade(3, 101, 1, Side::Ask);
+
+    let notifications = vec![
+        limits(1, &[(100, 5, Side::Bid), (99, 2, Side::Bid)]),
+        // Deeper limits do not change the top of the book.
+        limits(2, &[(98, 1, Side::Bid), (102, 4, Side::Ask)]),
+        trade.clone(),
+        limits(4, &[(97, 1, Side::Bid)]),
+        limits(5, &[(100, 0, Side::Bid), (101, 3, Side::Ask)]),
+    ];
+
+    let stream = futures::stream::iter_ok::<_, ()>(notifications.clone());
+    let forwarded: Vec<_> = TopOfBook::new(stream).collect().wait().unwrap();
+
+    assert_eq!(
+        forwarded,
+        vec![
+            bbo(1, (100, 5), (TickUnit::max_value(), 0)),
+            bbo(2, (100, 5), (102, 4)),
+            trade.clone(),
+            bbo(5, (99, 2), (101, 3)),
+        ]
+    );
+
+    // Limit updates can be kept, each batch is followed by the change it caused.
+    let stream = futures::stream::iter_ok::<_, ()>(notifications.clone());
+    let forwarded: Vec<_> = TopOfBook::with_limit_updates(stream).collect().wait().unwrap();
+
+    assert_eq!(
+        forwarded,
+        vec![
+            notifications[0].clone(),
+            bbo(1, (100, 5), (TickUnit::max_value(), 0)),
+            notifications[1].clone(),
+            bbo(2, (100, 5), (102, 4)),
+            trade,
+            notifications[3].clone(),
+            notifications[4].clone(),
+            bbo(5, (99, 2), (101, 3)),
+        ]
+    );
+}
@@ -0,0 +1,195 @@
+//! In-memory transports, allowing to run clients against local mock servers.
+
+use std::sync::{mpsc, Arc, Mutex};
+use std::collections::VecDeque;
+use futures::prelude::*;
+use failure::format_err;
+use hyper::{Request, Body, Chunk, Method, StatusCode, HeaderMap};
+use crate::api::errors::RequestError;
+use crate::api::transport::{
+    KeepAlive,
+    Flow,
+    RestTransport,
+    StreamSink,
+    StreamHandler,
+    StreamingTransport,
+};
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+/// A REST request received by a `MemoryRest` transport.
+pub struct RecordedRequest {
+    /// HTTP method.
+    pub method: Method,
+
+    /// Path of the requested URI, e.g. `/api/v3/order`.
+    pub path: String,
+
+    /// Query part of the requested URI, if any.
+    pub query: Option<String>,
+
+    /// Request headers.
+    pub headers: HeaderMap,
+
+    /// Request body.
+    pub body: String,
+}
+
+type Responder = dyn Fn(&RecordedRequest) -> (StatusCode, String) + Send + Sync;
+
+#[derive(Clone)]
+/// A REST transport answering requests with a user provided function instead of
+/// sending them over the network. All received requests are recorded.
+pub struct MemoryRest {
+    responder: Arc<Responder>,
+    requests: Arc<Mutex<Vec<RecordedRequest>>>,
+}
+
+impl MemoryRest {
+    /// Return a new `MemoryRest`, answering each request with the status code and
+    /// body returned by `responder`.
+    pub fn new<F>(responder: F) -> Self
+        where F: Fn(&RecordedRequest) -> (StatusCode, String) + Send + Sync + 'static
+    {
+        MemoryRest {
+            responder: Arc::new(responder),
+            requests: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    /// Return all the requests received so far, in order.
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+impl RestTransport for MemoryRest {
+    fn request(&self, request: Request<Body>)
+        -> Box<dyn Future<Item = (StatusCode, Chunk), Error = RequestError> + Send + 'static>
+    {
+        let (parts, body) = request.into_parts();
+        let responder = self.responder.clone();
+        let requests = self.requests.clone();
+
+        let fut = body.concat2().map_err(RequestError::new).and_then(move |body| {
+            let request = RecordedRequest {
+                method: parts.method,
+                path: parts.uri.path().to_owned(),
+                query: parts.uri.query().map(|query| query.to_owned()),
+                headers: parts.headers,
+                body: String::from_utf8_lossy(&body).into_owned(),
+            };
+
+            let (status, body) = responder(&request);
+            requests.lock().unwrap().push(request);
+            Ok((status, Chunk::from(body)))
+        });
+        Box::new(fut)
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+struct ConnectionState {
+    address: Option<String>,
+    received: Vec<String>,
+}
+
+struct PendingConnection {
+    rcv: mpsc::Receiver<String>,
+    state: Arc<Mutex<ConnectionState>>,
+}
+
+#[derive(Clone, Default)]
+/// A streaming transport whose connections are served by `MemoryServer` handles
+/// instead of a remote server.
+pub struct MemoryStreaming {
+    pending: Arc<Mutex<VecDeque<PendingConnection>>>,
+}
+
+/// The server side of a `MemoryStreaming` connection. The connection terminates
+/// once the `MemoryServer` is dropped and all the messages sent through it have been
+/// handled.
+pub struct MemoryServer {
+    snd: mpsc::Sender<String>,
+    state: Arc<Mutex<ConnectionState>>,
+}
+
+impl MemoryStreaming {
+    /// Return a new `MemoryStreaming` without any pending connection.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Prepare a connection which will be handed to the next call to `connect`, and
+    /// return the server side of it. Messages can be sent before the connection is
+    /// actually open, they will be delivered after the `on_open` event.
+    pub fn accept(&self) -> MemoryServer {
+        let (snd, rcv) = mpsc::channel();
+        let state = Arc::new(Mutex::new(ConnectionState::default()));
+        self.pending.lock().unwrap().push_back(PendingConnection {
+            rcv,
+            state: state.clone(),
+        });
+
+        MemoryServer {
+            snd,
+            state,
+        }
+    }
+}
+
+impl MemoryServer {
+    /// Send a text message to the client side.
+    pub fn send<T: Into<String>>(&self, text: T) {
+        // The client side may have already terminated the connection, nothing to do then.
+        let _ = self.snd.send(text.into());
+    }
+
+    /// Return the address the client side connected to, or `None` if the connection
+    /// has not been opened yet.
+    pub fn address(&self) -> Option<String> {
+        self.state.lock().unwrap().address.clone()
+    }
+
+    /// Return all the messages sent by the client side so far, in order.
+    pub fn received(&self) -> Vec<String> {
+        self.state.lock().unwrap().received.clone()
+    }
+}
+
+struct MemorySink {
+    state: Arc<Mutex<ConnectionState>>,
+}
+
+impl StreamSink for MemorySink {
+    fn send(&self, text: String) -> Result<(), failure::Error> {
+        self.state.lock().unwrap().received.push(text);
+        Ok(())
+    }
+
+    fn ping(&self) -> Result<(), failure::Error> {
+        Ok(())
+    }
+}
+
+impl StreamingTransport for MemoryStreaming {
+    fn connect(&self, address: &str, _: KeepAlive, mut handler: Box<dyn StreamHandler>)
+        -> Result<(), failure::Error>
+    {
+        let connection = self.pending.lock().unwrap().pop_front()
+            .ok_or_else(|| format_err!("no pending connection for `{}`", address))?;
+
+        connection.state.lock().unwrap().address = Some(address.to_owned());
+
+        let sink = MemorySink {
+            state: connection.state,
+        };
+        handler.on_open(&sink)?;
+
+        for text in connection.rcv.iter() {
+            if handler.on_message(&text) == Flow::Close {
+                break;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,177 @@
+//! A module defining the transport layers used by the `ApiClient` implementors for
+//! performing REST requests and for streaming notifications.
+//!
+//! Clients are generic over these transports: by default they use an HTTPS client and
+//! a WebSocket connection, but they can be run against local mock servers by using
+//! the in-memory implementations from the `memory` module instead.
+//!
+//! The default transports are configured through `TransportSettings`. The WebSocket
+//! transport does not support proxies: only REST requests go through `TransportSettings::proxy`.
+
+pub mod memory;
+mod websocket;
+mod proxy;
+mod test;
+
+use std::time::Duration;
+use futures::prelude::*;
+use hyper::{Request, Body, Chunk, StatusCode};
+use hyper::header::{HeaderValue, USER_AGENT};
+use serde_derive::{Serialize, Deserialize};
+use crate::api::errors::RequestError;
+
+pub use self::websocket::WsTransport;
+
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+#[serde(default)]
+/// Settings of the default transports, see `Params::transport`.
+pub struct TransportSettings {
+    /// Value of the `User-Agent` header added to REST requests not setting one.
+    pub user_agent: String,
+
+    /// Maximum duration of the TCP connection to the REST endpoint, in ms, if any.
+    pub connect_timeout: Option<u64>,
+
+    /// Maximum duration of a whole REST request, in ms, if any.
+    ///
+    /// # Note
+    /// Timeouts rely on the timer of the tokio runtime driving the request.
+    pub request_timeout: Option<u64>,
+
+    /// Delay after which a streaming connection is closed if nothing was received, in ms.
+    pub stream_timeout: u64,
+
+    /// Address of an HTTP proxy through which REST requests are tunnelled with `CONNECT`,
+    /// e.g. `http://127.0.0.1:3128`, if any.
+    ///
+    /// # Note
+    /// Streaming connections do not support proxies, they always connect directly.
+    pub proxy: Option<String>,
+
+    /// Number of threads used for resolving DNS names.
+    pub dns_threads: usize,
+}
+
+impl Default for TransportSettings {
+    fn default() -> Self {
+        TransportSettings {
+            user_agent: concat!("trade-rs/", env!("CARGO_PKG_VERSION")).to_owned(),
+            connect_timeout: None,
+            request_timeout: None,
+            stream_timeout: 30_000,
+            proxy: None,
+            dns_threads: 2,
+        }
+    }
+}
+
+#[derive(Clone)]
+/// Default REST transport, i.e. an HTTPS client.
+pub struct HttpsTransport {
+    client: hyper::Client<hyper_tls::HttpsConnector<proxy::ProxyConnector>>,
+    user_agent: HeaderValue,
+    request_timeout: Option<Duration>,
+}
+
+/// Build a new HTTPS client configured by `settings`, usable as a REST transport.
+pub fn https(settings: &TransportSettings) -> Result<HttpsTransport, failure::Error> {
+    let mut http = hyper::client::HttpConnector::new(settings.dns_threads);
+    http.enforce_http(false);
+    http.set_connect_timeout(settings.connect_timeout.map(Duration::from_millis));
+
+    let proxy = match &settings.proxy {
+        Some(proxy) => Some(proxy.parse()?),
+        None => None,
+    };
+    let connector = proxy::ProxyConnector::new(http, proxy)?;
+    let tls = native_tls::TlsConnector::new()?;
+
+    Ok(HttpsTransport {
+        client: hyper::Client::builder().build(hyper_tls::HttpsConnector::from((connector, tls))),
+        user_agent: HeaderValue::from_str(&settings.user_agent)?,
+        request_timeout: settings.request_timeout.map(Duration::from_millis),
+    })
+}
+
+/// A transport able to perform REST requests.
+pub trait RestTransport: Clone + Send + Sync + 'static {
+    /// Send `request` and return the response status code along with the whole
+    /// response body.
+    fn request(&self, request: Request<Body>)
+        -> Box<dyn Future<Item = (StatusCode, Chunk), Error = RequestError> + Send + 'static>;
+}
+
+impl RestTransport for HttpsTransport {
+    fn request(&self, mut request: Request<Body>)
+        -> Box<dyn Future<Item = (StatusCode, Chunk), Error = RequestError> + Send + 'static>
+    {
+        if !request.headers().contains_key(USER_AGENT) {
+            request.headers_mut().insert(USER_AGENT, self.user_agent.clone());
+        }
+
+        let fut = self.client.request(request).and_then(|res| {
+            let status = res.status();
+            res.into_body().concat2().and_then(move |body| {
+                Ok((status, body))
+            })
+        });
+
+        match self.request_timeout {
+            Some(timeout) => {
+                Box::new(tokio::timer::Timeout::new(fut, timeout).map_err(RequestError::new))
+            }
+            None => Box::new(fut.map_err(RequestError::new)),
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+/// Indicate whether a streaming connection should be kept alive by regularly
+/// sending pings to the other side.
+pub enum KeepAlive {
+    /// Send pings regularly.
+    True,
+
+    /// Do not send pings.
+    False,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+/// Indicate whether a streaming connection should go on after a message was handled.
+pub enum Flow {
+    /// Keep the connection open.
+    Continue,
+
+    /// Close the connection, e.g. because nobody listens to the notifications anymore.
+    Close,
+}
+
+/// The sending half of a streaming connection.
+pub trait StreamSink {
+    /// Send a text message to the other side.
+    fn send(&self, text: String) -> Result<(), failure::Error>;
+
+    /// Send a ping to the other side. This may be a no-op depending on the transport.
+    fn ping(&self) -> Result<(), failure::Error>;
+}
+
+/// An object handling the events of a streaming connection.
+pub trait StreamHandler: Send {
+    /// Called once the connection is open, e.g. for sending subscription messages.
+    /// Returning an error will terminate the connection.
+    fn on_open(&mut self, out: &dyn StreamSink) -> Result<(), failure::Error>;
+
+    /// Called each time a text message is received.
+    fn on_message(&mut self, text: &str) -> Flow;
+}
+
+/// A transport able to open streaming connections.
+pub trait StreamingTransport: Clone + Send + Sync + 'static {
+    /// Connect to `address` and drive `handler` until the connection terminates.
+    ///
+    /// # Note
+    /// This call blocks: clients always run it inside a dedicated thread. Panicking
+    /// inside `handler` is allowed and will terminate the connection.
+    fn connect(&self, address: &str, keep_alive: KeepAlive, handler: Box<dyn StreamHandler>)
+        -> Result<(), failure::Error>;
+}
@@ -0,0 +1,92 @@
+//! A connector tunnelling TCP connections through an HTTP proxy with the `CONNECT`
+//! method.
+
+use std::io;
+use futures::prelude::*;
+use futures::future;
+use hyper::Uri;
+use hyper::client::HttpConnector;
+use hyper::client::connect::{Connect, Connected, Destination};
+use tokio::net::TcpStream;
+
+/// Maximum size of the response of the proxy to a `CONNECT` request.
+const MAX_RESPONSE_SIZE: usize = 8192;
+
+#[derive(Clone)]
+/// A connector going through `proxy` if any, or connecting directly otherwise.
+crate struct ProxyConnector {
+    http: HttpConnector,
+    proxy: Option<Destination>,
+}
+
+impl ProxyConnector {
+    crate fn new(http: HttpConnector, proxy: Option<Uri>) -> Result<Self, failure::Error> {
+        let proxy = match proxy {
+            Some(uri) => Some(Destination::try_from_uri(uri)?),
+            None => None,
+        };
+        Ok(ProxyConnector {
+            http,
+            proxy,
+        })
+    }
+}
+
+fn other_error(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::Other, msg)
+}
+
+/// Read the response of the proxy to a `CONNECT` request, i.e. until the end of the
+/// headers: the proxy sends nothing more before the client speaks.
+fn read_response(tcp: TcpStream)
+    -> impl Future<Item = TcpStream, Error = io::Error> + Send
+{
+    future::loop_fn((tcp, Vec::new()), |(tcp, mut response)| {
+        tokio::io::read(tcp, vec![0; 1024]).and_then(move |(tcp, buf, len)| {
+            if len == 0 {
+                return Err(other_error("proxy closed the connection".to_owned()));
+            }
+            response.extend_from_slice(&buf[..len]);
+
+            if response.windows(4).any(|window| window == b"\r\n\r\n") {
+                let status_line = String::from_utf8_lossy(&response)
+                    .lines()
+                    .next()
+                    .unwrap_or_default()
+                    .to_owned();
+                match status_line.split(' ').nth(1) {
+                    Some("200") => Ok(future::Loop::Break(tcp)),
+                    _ => Err(other_error(format!("proxy refused to connect: `{}`", status_line))),
+                }
+            } else if response.len() > MAX_RESPONSE_SIZE {
+                Err(other_error("proxy response is too large".to_owned()))
+            } else {
+                Ok(future::Loop::Continue((tcp, response)))
+            }
+        })
+    })
+}
+
+impl Connect for ProxyConnector {
+    type Transport = TcpStream;
+    type Error = io::Error;
+    type Future = Box<dyn Future<Item = (TcpStream, Connected), Error = io::Error> + Send>;
+
+    fn connect(&self, dst: Destination) -> Self::Future {
+        let proxy = match &self.proxy {
+            Some(proxy) => proxy.clone(),
+            None => return Box::new(self.http.connect(dst)),
+        };
+
+        let port = dst.port().unwrap_or(if dst.scheme() == "https" { 443 } else { 80 });
+        let authority = format!("{}:{}", dst.host(), port);
+        let request = format!("CONNECT {0} HTTP/1.1\r\nHost: {0}\r\n\r\n", authority);
+
+        let fut = self.http.connect(proxy).and_then(move |(tcp, connected)| {
+            tokio::io::write_all(tcp, request.into_bytes())
+                .and_then(|(tcp, _)| read_response(tcp))
+                .map(move |tcp| (tcp, connected))
+        });
+        Box::new(fut)
+    }
+}
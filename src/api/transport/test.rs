@@ -0,0 +1,55 @@
+#![cfg(test)]
+
+use std::io::{Read, Write};
+use std::net::TcpListener;
+use std::thread;
+use hyper::{Request, Body, StatusCode};
+use crate::api::transport::{https, RestTransport, TransportSettings};
+
+/// Read from `stream` until the end of the headers.
+fn read_headers<R: Read>(stream: &mut R) -> String {
+    let mut headers = Vec::new();
+    let mut byte = [0];
+    while !headers.ends_with(b"\r\n\r\n") {
+        stream.read_exact(&mut byte).unwrap();
+        headers.push(byte[0]);
+    }
+    String::from_utf8(headers).unwrap()
+}
+
+#[test]
+fn proxy() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+
+    // A proxy accepting the tunnel, then acting as the target server.
+    let server = thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let connect = read_headers(&mut stream);
+        stream.write_all(b"HTTP/1.1 200 Connection established\r\n\r\n").unwrap();
+
+        let request = read_headers(&mut stream);
+        stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok").unwrap();
+        (connect, request)
+    });
+
+    let transport = https(&TransportSettings {
+        user_agent: "agent".to_owned(),
+        proxy: Some(format!("http://{}", address)),
+        ..Default::default()
+    }).unwrap();
+
+    let request = Request::get("http://mock:8080/path")
+        .body(Body::empty())
+        .unwrap();
+    let (status, body) = tokio::runtime::current_thread::block_on_all(
+        transport.request(request)
+    ).unwrap();
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(&body[..], b"ok");
+
+    let (connect, request) = server.join().unwrap();
+    assert!(connect.starts_with("CONNECT mock:8080 HTTP/1.1\r\n"));
+    assert!(request.starts_with("GET /path HTTP/1.1\r\n"));
+    assert!(request.to_lowercase().contains("user-agent: agent\r\n"));
+}
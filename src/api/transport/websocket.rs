@@ -0,0 +1,148 @@
+// `Timeout`, `Token`
+#![allow(deprecated)]
+
+use ws::util::{Timeout, Token};
+use log::warn;
+use failure::format_err;
+use crate::api::transport::{
+    KeepAlive,
+    Flow,
+    StreamSink,
+    StreamHandler,
+    StreamingTransport,
+    TransportSettings,
+};
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+/// Default streaming transport, over WebSocket. Connections are always direct: the proxy of
+/// `TransportSettings` is not supported, and a warning is logged if one is set.
+pub struct WsTransport {
+    expire_timeout: u64,
+}
+
+impl WsTransport {
+    /// Return a new WebSocket transport configured by `settings`.
+    pub fn new(settings: &TransportSettings) -> Self {
+        if let Some(proxy) = &settings.proxy {
+            warn!("streaming connections do not go through the proxy `{}`", proxy);
+        }
+        WsTransport {
+            expire_timeout: settings.stream_timeout,
+        }
+    }
+}
+
+impl Default for WsTransport {
+    fn default() -> Self {
+        WsTransport::new(&TransportSettings::default())
+    }
+}
+
+impl StreamingTransport for WsTransport {
+    fn connect(&self, address: &str, keep_alive: KeepAlive, handler: Box<dyn StreamHandler>)
+        -> Result<(), failure::Error>
+    {
+        let mut handler = Some(handler);
+        let expire_timeout = self.expire_timeout;
+        ws::connect(address, |out| {
+            let handler = handler.take().expect("connection opened twice");
+            Handler::new(out, keep_alive, expire_timeout, handler)
+        }).map_err(|err| format_err!("{}", err))
+    }
+}
+
+impl StreamSink for ws::Sender {
+    fn send(&self, text: String) -> Result<(), failure::Error> {
+        ws::Sender::send(self, text).map_err(|err| format_err!("{}", err))
+    }
+
+    fn ping(&self) -> Result<(), failure::Error> {
+        ws::Sender::ping(self, vec![]).map_err(|err| format_err!("{}", err))
+    }
+}
+
+/// An object handling a WebSocket connection, forwarding events to a `StreamHandler`.
+/// Inside handler functions, panicking can be used to terminate
+/// the connection easily (the connection always happen in a
+/// separate, free thread).
+struct Handler {
+    out: ws::Sender,
+    keep_alive: KeepAlive,
+    expire_timeout: u64,
+
+    /// We keep a reference to the `EXPIRE` timeout so that we can cancel it when we receive
+    /// something from the server.
+    timeout: Option<Timeout>,
+
+    inner: Box<dyn StreamHandler>,
+}
+
+const PING: Token = Token(1);
+const EXPIRE: Token = Token(2);
+
+const PING_TIMEOUT: u64 = 10_000;
+
+impl Handler {
+    fn new(
+        out: ws::Sender,
+        keep_alive: KeepAlive,
+        expire_timeout: u64,
+        inner: Box<dyn StreamHandler>
+    ) -> Self {
+        Handler {
+            out,
+            keep_alive,
+            expire_timeout,
+            timeout: None,
+            inner,
+        }
+    }
+}
+
+impl ws::Handler for Handler {
+    fn on_open(&mut self, _: ws::Handshake) -> ws::Result<()> {
+        if let Err(err) = self.inner.on_open(&self.out) {
+            return Err(ws::Error::new(ws::ErrorKind::Internal, format!("{}", err)));
+        }
+
+        if self.keep_alive == KeepAlive::True {
+            self.out.timeout(PING_TIMEOUT, PING)?;
+        }
+        self.out.timeout(self.expire_timeout, EXPIRE)
+    }
+
+    fn on_timeout(&mut self, event: Token) -> ws::Result<()> {
+        match event {
+            PING => {
+                self.out.ping(vec![])?;
+                self.out.timeout(PING_TIMEOUT, PING)
+            }
+            EXPIRE => self.out.close(ws::CloseCode::Away),
+            _ => Err(ws::Error::new(ws::ErrorKind::Internal, "invalid timeout token encountered")),
+        }
+    }
+
+    fn on_new_timeout(&mut self, event: Token, timeout: Timeout) -> ws::Result<()> {
+        if event == EXPIRE {
+            if let Some(t) = self.timeout.take() {
+                self.out.cancel(t)?;
+            }
+            self.timeout = Some(timeout)
+        }
+        Ok(())
+    }
+
+    fn on_frame(&mut self, frame: ws::Frame) -> ws::Result<Option<ws::Frame>> {
+        self.out.timeout(self.expire_timeout, EXPIRE)?;
+        Ok(Some(frame))
+    }
+
+    fn on_message(&mut self, msg: ws::Message) -> ws::Result<()> {
+        if let ws::Message::Text(text) = msg {
+            if self.inner.on_message(&text) == Flow::Close {
+                return self.out.close(ws::CloseCode::Normal);
+            }
+        }
+        Ok(())
+    }
+}
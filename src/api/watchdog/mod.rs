@@ -0,0 +1,226 @@
+//! A module defining a dead man's switch, cancelling all the open orders of a symbol when
+//! its notification stream disconnects or when the process stops watching it.
+//!
+//! None of the supported exchanges offer a native cancel-on-disconnect mechanism for spot
+//! trading, so the cancellation is performed by the library: a stream disconnects when the
+//! exchange closes it, or when nothing was received during the `EXPIRE` timeout of the
+//! websocket transport. Orders cannot be cancelled if the whole process dies, a native
+//! mechanism is needed for that.
+
+mod test;
+
+use std::sync::{Arc, Mutex, Condvar};
+use std::thread;
+use futures::prelude::*;
+use log::{error, warn};
+use serde_derive::{Serialize, Deserialize};
+use crate::api::{ApiClient, Cancel};
+use crate::api::symbol::{Symbol, IntoWithSymbol};
+use crate::api::order_manager::OrderManager;
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+/// Event which triggered the cancellation of the open orders.
+pub enum Trigger {
+    /// The watched stream ended or failed.
+    Disconnected,
+
+    /// The `Watchdog` was dropped while armed.
+    Dropped,
+
+    /// `Watchdog::trigger` was called.
+    Manual,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+/// State of a `Watchdog`.
+pub enum WatchdogState {
+    /// Waiting for a trigger.
+    Armed,
+
+    /// Disarmed, the open orders will not be cancelled.
+    Disarmed,
+
+    /// Cancel requests for the open orders have been sent.
+    Cancelling(Trigger),
+
+    /// All the cancel requests have completed.
+    Done {
+        /// Event which triggered the cancellation.
+        trigger: Trigger,
+
+        /// Number of orders successfully cancelled.
+        cancelled: usize,
+
+        /// Number of cancel requests which failed, e.g. because the order was filled in
+        /// the meantime.
+        failed: usize,
+    },
+}
+
+struct Inner<C> {
+    client: C,
+    symbol: Symbol,
+    orders: OrderManager,
+    state: Mutex<WatchdogState>,
+    done: Condvar,
+}
+
+impl<C> Inner<C> {
+    fn set_state(&self, state: WatchdogState) {
+        *self.state.lock().unwrap() = state;
+        self.done.notify_all();
+    }
+}
+
+/// Cancel the open orders of `inner` in a background thread, if still armed.
+fn fire<C>(inner: &Arc<Inner<C>>, trigger: Trigger)
+    where C: ApiClient + Send + Sync + 'static
+{
+    {
+        let mut state = inner.state.lock().unwrap();
+        if *state != WatchdogState::Armed {
+            return;
+        }
+        *state = WatchdogState::Cancelling(trigger);
+    }
+    warn!("watchdog triggered ({:?}), cancelling open orders", trigger);
+
+    let cancels: Vec<_> = inner.orders.open_orders()
+        .into_iter()
+        .filter(|order| order.symbol == Some(inner.symbol))
+        .map(|order| {
+            let cancel = Cancel::new(order.order_id);
+            inner.orders.cancel(&inner.client, cancel.with_symbol(inner.symbol))
+                .then(Ok::<_, ()>)
+        })
+        .collect();
+
+    let inner = inner.clone();
+    thread::spawn(move || {
+        let fut = futures::future::join_all(cancels).map(move |results| {
+            let cancelled = results.iter().filter(|result| result.is_ok()).count();
+            for err in results.iter().filter_map(|result| result.as_ref().err()) {
+                error!("watchdog could not cancel order: `{}`", err);
+            }
+
+            inner.set_state(WatchdogState::Done {
+                trigger,
+                cancelled,
+                failed: results.len() - cancelled,
+            });
+        });
+
+        use tokio::runtime::current_thread;
+        current_thread::block_on_all(fut).unwrap();
+    });
+}
+
+/// A dead man's switch cancelling all the open orders of a symbol, as tracked by an
+/// `OrderManager`, when a watched stream disconnects, when `trigger` is called, or when
+/// the `Watchdog` is dropped while armed. Orders are cancelled only once.
+///
+/// The `OrderManager` may be shared with watchdogs of other symbols: only the orders sent
+/// through it on the watched symbol are cancelled, orders only known through
+/// notifications are left alone since their symbol is unknown.
+pub struct Watchdog<C: ApiClient + Send + Sync + 'static> {
+    inner: Arc<Inner<C>>,
+}
+
+impl<C: ApiClient + Send + Sync + 'static> Watchdog<C> {
+    /// Return a new armed `Watchdog`, cancelling the open orders on `symbol` found in
+    /// `orders` through `client`.
+    pub fn new(client: C, symbol: Symbol, orders: OrderManager) -> Self {
+        Watchdog {
+            inner: Arc::new(Inner {
+                client,
+                symbol,
+                orders,
+                state: Mutex::new(WatchdogState::Armed),
+                done: Condvar::new(),
+            }),
+        }
+    }
+
+    /// Client used for cancelling the orders.
+    pub fn client(&self) -> &C {
+        &self.inner.client
+    }
+
+    /// Wrap `stream` so that the watchdog is triggered when it ends or fails.
+    pub fn watch<S: Stream>(&self, stream: S) -> Watched<S, C> {
+        Watched {
+            stream,
+            inner: self.inner.clone(),
+        }
+    }
+
+    /// Current state.
+    pub fn state(&self) -> WatchdogState {
+        *self.inner.state.lock().unwrap()
+    }
+
+    /// Cancel all the open orders now, if still armed.
+    pub fn trigger(&self) {
+        fire(&self.inner, Trigger::Manual);
+    }
+
+    /// Disarm the watchdog, e.g. for a graceful shutdown keeping orders on the exchange.
+    /// Has no effect once triggered.
+    pub fn disarm(&self) {
+        let mut state = self.inner.state.lock().unwrap();
+        if *state == WatchdogState::Armed {
+            *state = WatchdogState::Disarmed;
+        }
+    }
+
+    /// Block until the watchdog is triggered and all the cancel requests have completed,
+    /// or until it is disarmed. Return the final state.
+    pub fn wait(&self) -> WatchdogState {
+        let mut state = self.inner.state.lock().unwrap();
+        loop {
+            match *state {
+                WatchdogState::Armed | WatchdogState::Cancelling(..) => {
+                    state = self.inner.done.wait(state).unwrap();
+                }
+                final_state => return final_state,
+            }
+        }
+    }
+}
+
+impl<C: ApiClient + Send + Sync + 'static> Drop for Watchdog<C> {
+    fn drop(&mut self) {
+        if self.state() == WatchdogState::Armed {
+            fire(&self.inner, Trigger::Dropped);
+
+            // Wait for the cancel requests, the process may be exiting.
+            let mut state = self.inner.state.lock().unwrap();
+            while let WatchdogState::Cancelling(..) = *state {
+                state = self.inner.done.wait(state).unwrap();
+            }
+        }
+    }
+}
+
+/// A stream adaptor triggering a `Watchdog` once the underlying stream ends or fails,
+/// see `Watchdog::watch`.
+pub struct Watched<S, C> {
+    stream: S,
+    inner: Arc<Inner<C>>,
+}
+
+impl<S, C> Stream for Watched<S, C>
+    where S: Stream, C: ApiClient + Send + Sync + 'static
+{
+    type Item = S::Item;
+    type Error = S::Error;
+
+    fn poll(&mut self) -> Poll<Option<S::Item>, S::Error> {
+        let result = self.stream.poll();
+        match result {
+            Ok(Async::Ready(None)) | Err(_) => fire(&self.inner, Trigger::Disconnected),
+            _ => (),
+        }
+        result
+    }
+}
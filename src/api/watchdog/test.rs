@@ -0,0 +1,81 @@
+#![cfg(test)]
+
+use futures::prelude::*;
+use crate::Side;
+use crate::tick::Tick;
+use crate::api::Order;
+use crate::api::symbol::{Symbol, IntoWithSymbol};
+use crate::api::sim::{Exchange, Client, Settings};
+use crate::api::order_manager::{OrderManager, OrderState};
+use crate::api::watchdog::{Watchdog, WatchdogState, Trigger};
+
+fn send(watchdog: &Watchdog<Client>, orders: &OrderManager, symbol: Symbol, order_id: &str) {
+    let order = Order::new("100.00", "1", Side::Bid).with_order_id::<Client>(order_id);
+    orders.order(watchdog.client(), order.with_symbol(symbol)).wait().unwrap();
+}
+
+#[test]
+fn cancel_on_disconnect() {
+    let exchange = Exchange::new(Settings::default());
+    let symbol = exchange.add_symbol("BTCUSD", Tick::new(100), Tick::new(1000)).unwrap();
+    let orders = OrderManager::new();
+    let watchdog = Watchdog::new(exchange.client(), symbol, orders.clone());
+
+    send(&watchdog, &orders, symbol, "a");
+    send(&watchdog, &orders, symbol, "b");
+    assert_eq!(watchdog.state(), WatchdogState::Armed);
+
+    let stream = watchdog.watch(futures::stream::iter_ok::<_, ()>(vec![()]));
+    stream.collect().wait().unwrap();
+    assert_eq!(
+        watchdog.wait(),
+        WatchdogState::Done { trigger: Trigger::Disconnected, cancelled: 2, failed: 0 }
+    );
+    assert_eq!(orders.orders_in(OrderState::Cancelled).len(), 2);
+
+    // Orders are cancelled only once.
+    send(&watchdog, &orders, symbol, "c");
+    drop(watchdog);
+    assert_eq!(orders.order_by_id("c").unwrap().state, OrderState::Open);
+}
+
+#[test]
+fn cancel_on_drop() {
+    let exchange = Exchange::new(Settings::default());
+    let symbol = exchange.add_symbol("BTCUSD", Tick::new(100), Tick::new(1000)).unwrap();
+    let orders = OrderManager::new();
+
+    let watchdog = Watchdog::new(exchange.client(), symbol, orders.clone());
+    send(&watchdog, &orders, symbol, "a");
+    drop(watchdog);
+    assert_eq!(orders.order_by_id("a").unwrap().state, OrderState::Cancelled);
+
+    let watchdog = Watchdog::new(exchange.client(), symbol, orders.clone());
+    send(&watchdog, &orders, symbol, "b");
+    watchdog.disarm();
+    drop(watchdog);
+    assert_eq!(orders.order_by_id("b").unwrap().state, OrderState::Open);
+}
+
+#[test]
+fn shared_order_manager() {
+    let exchange = Exchange::new(Settings::default());
+    let btc = exchange.add_symbol("BTCUSD", Tick::new(100), Tick::new(1000)).unwrap();
+    let eth = exchange.add_symbol("ETHUSD", Tick::new(100), Tick::new(1000)).unwrap();
+    let orders = OrderManager::new();
+
+    let btc_watchdog = Watchdog::new(exchange.client(), btc, orders.clone());
+    let eth_watchdog = Watchdog::new(exchange.client(), eth, orders.clone());
+    send(&btc_watchdog, &orders, btc, "a");
+    send(&eth_watchdog, &orders, eth, "b");
+
+    // Only the orders on the watched symbol are cancelled.
+    btc_watchdog.trigger();
+    assert_eq!(
+        btc_watchdog.wait(),
+        WatchdogState::Done { trigger: Trigger::Manual, cancelled: 1, failed: 0 }
+    );
+    assert_eq!(orders.order_by_id("a").unwrap().state, OrderState::Cancelled);
+    assert_eq!(orders.order_by_id("b").unwrap().state, OrderState::Open);
+    eth_watchdog.disarm();
+}
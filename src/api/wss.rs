@@ -1,106 +1,73 @@
-// `Timeout`, `Token`
-#![allow(deprecated)]
-
-use ws::util::{Timeout, Token};
-use futures::sync::mpsc::UnboundedSender;
 use log::error;
 use crate::api::Notification;
+use crate::api::channel::{Sender, Disconnected};
+use crate::api::timestamp::{TimestampUs, timestamp_us};
+use crate::api::transport::{Flow, StreamSink, StreamHandler};
+
+/// Sending half of a notification stream, stamping each notification with the local
+/// receive time of the message being handled.
+crate struct NotifSender {
+    snd: Sender,
+    received: Option<TimestampUs>,
+}
 
-pub type NotifSender = UnboundedSender<Notification>;
+impl NotifSender {
+    crate fn new(snd: Sender) -> Self {
+        NotifSender {
+            snd,
+            received: None,
+        }
+    }
 
-#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
-crate enum KeepAlive {
-    True,
-    False,
+    /// Forward `notif` to the consumer.
+    crate fn send(&self, mut notif: Notification) -> Result<(), Disconnected> {
+        if let Some(received) = self.received {
+            notif.set_received(received);
+        }
+        self.snd.send(notif)
+    }
 }
 
-/// An object handling a WebSocket API connection.
+/// An object handling a streaming API connection, forwarding notifications
+/// to the consumer.
 /// Inside handler functions, panicking can be used to terminate
 /// the connection easily (the connection always happen in a
 /// separate, free thread).
+/// The connection is closed once the consumer drops the stream.
 crate struct Handler<T> {
-    out: ws::Sender,
     snd: NotifSender,
-    keep_alive: KeepAlive,
-
-    /// We keep a reference to the `EXPIRE` timeout so that we can cancel it when we receive
-    /// something from the server.
-    timeout: Option<Timeout>,
-
     inner: T,
 }
 
 crate trait HandlerImpl {
-    fn on_open(&mut self, out: &ws::Sender) -> ws::Result<()>;
+    fn on_open(&mut self, out: &dyn StreamSink) -> Result<(), failure::Error>;
     fn on_message(&mut self, text: &str, out: &NotifSender) -> Result<(), failure::Error>;
 }
 
-const PING: Token = Token(1);
-const EXPIRE: Token = Token(2);
-
-const PING_TIMEOUT: u64 = 10_000;
-const EXPIRE_TIMEOUT: u64 = 30_000;
-
 impl<T> Handler<T> {
-    crate fn new(
-        out: ws::Sender,
-        snd: UnboundedSender<Notification>,
-        keep_alive: KeepAlive,
-        inner: T
-    ) -> Self
-    {
+    crate fn new(snd: Sender, inner: T) -> Self {
         Handler {
-            out,
-            snd,
-            keep_alive,
-            timeout: None,
+            snd: NotifSender::new(snd),
             inner,
         }
     }
 }
 
-impl<T: HandlerImpl> ws::Handler for Handler<T> {
-    fn on_open(&mut self, _: ws::Handshake) -> ws::Result<()> {
-        self.inner.on_open(&self.out)?;
-
-        if self.keep_alive == KeepAlive::True {
-            self.out.timeout(PING_TIMEOUT, PING)?;
-        }
-        self.out.timeout(EXPIRE_TIMEOUT, EXPIRE)
+impl<T: HandlerImpl + Send> StreamHandler for Handler<T> {
+    fn on_open(&mut self, out: &dyn StreamSink) -> Result<(), failure::Error> {
+        self.inner.on_open(out)
     }
 
-    fn on_timeout(&mut self, event: Token) -> ws::Result<()> {
-        match event {
-            PING => {
-                self.out.ping(vec![])?;
-                self.out.timeout(PING_TIMEOUT, PING)
-            }
-            EXPIRE => self.out.close(ws::CloseCode::Away),
-            _ => Err(ws::Error::new(ws::ErrorKind::Internal, "invalid timeout token encountered")),
-        }
-    }
+    fn on_message(&mut self, text: &str) -> Flow {
+        self.snd.received = Some(timestamp_us());
+        let result = self.inner.on_message(text, &self.snd);
 
-    fn on_new_timeout(&mut self, event: Token, timeout: Timeout) -> ws::Result<()> {
-        if event == EXPIRE {
-            if let Some(t) = self.timeout.take() {
-                self.out.cancel(t)?;
-            }
-            self.timeout = Some(timeout)
+        if self.snd.snd.is_closed() {
+            return Flow::Close;
         }
-        Ok(())
-    }
-
-    fn on_frame(&mut self, frame: ws::Frame) -> ws::Result<Option<ws::Frame>> {
-        self.out.timeout(EXPIRE_TIMEOUT, EXPIRE)?;
-        Ok(Some(frame))
-    }
-
-    fn on_message(&mut self, msg: ws::Message) -> ws::Result<()> {
-        if let ws::Message::Text(text) = msg {
-            if let Err(err) = self.inner.on_message(&text, &self.snd) {
-                error!("message handling encountered error: `{}`", err)
-            }
+        if let Err(err) = result {
+            error!("message handling encountered error: `{}`", err)
         }
-        Ok(())
+        Flow::Continue
     }
 }
@@ -0,0 +1,145 @@
+//! Analytics queries on order books.
+
+use crate::Side;
+use crate::tick::TickUnit;
+use crate::order_book::OrderBook;
+
+impl OrderBook {
+    /// Return the best limit (price, size) of the given side, if any.
+    fn best_limit(&self, side: Side) -> Option<(TickUnit, TickUnit)> {
+        match side {
+            Side::Bid => self.bid().next(),
+            Side::Ask => self.ask().next(),
+        }.map(|(&price, &size)| (price, size))
+    }
+
+    /// Iterator over the limits consumed by a market order of the given side, i.e. the
+    /// ask side for a buy order and the bid side for a sell order.
+    fn consumed_limits<'a>(&'a self, side: Side)
+        -> Box<dyn Iterator<Item = (TickUnit, TickUnit)> + 'a>
+    {
+        match side {
+            Side::Bid => Box::new(self.ask().map(|(&price, &size)| (price, size))),
+            Side::Ask => Box::new(self.bid().map(|(&price, &size)| (price, size))),
+        }
+    }
+
+    /// Return the mid price, in ticks, or `None` if one side is empty.
+    ///
+    /// # Complexity
+    /// `O(1)`.
+    pub fn mid(&self) -> Option<f64> {
+        let (bid, _) = self.best_limit(Side::Bid)?;
+        let (ask, _) = self.best_limit(Side::Ask)?;
+        Some((bid as f64 + ask as f64) / 2.)
+    }
+
+    /// Return the microprice, i.e. the average of the best bid and best ask prices
+    /// weighted by the size on the opposite side, in ticks, or `None` if one side is empty.
+    ///
+    /// # Complexity
+    /// `O(1)`.
+    pub fn microprice(&self) -> Option<f64> {
+        let (bid, bid_size) = self.best_limit(Side::Bid)?;
+        let (ask, ask_size) = self.best_limit(Side::Ask)?;
+        let (bid_size, ask_size) = (bid_size as f64, ask_size as f64);
+        Some((bid as f64 * ask_size + ask as f64 * bid_size) / (bid_size + ask_size))
+    }
+
+    /// Return the spread, in ticks, or `None` if one side is empty.
+    ///
+    /// # Complexity
+    /// `O(1)`.
+    pub fn spread(&self) -> Option<TickUnit> {
+        let (bid, _) = self.best_limit(Side::Bid)?;
+        let (ask, _) = self.best_limit(Side::Ask)?;
+        Some(ask.saturating_sub(bid))
+    }
+
+    /// Return the cumulative size on the given side, at limits at most `ticks` away from
+    /// the best price of that side (included).
+    ///
+    /// # Complexity
+    /// `O(k)` where `k` is the number of limits within `ticks`.
+    pub fn depth(&self, side: Side, ticks: TickUnit) -> TickUnit {
+        let best = match self.best_limit(side) {
+            Some((price, _)) => price,
+            None => return 0,
+        };
+
+        match side {
+            Side::Bid => self.bid()
+                .take_while(|(&price, _)| best - price <= ticks)
+                .map(|(_, &size)| size)
+                .sum(),
+            Side::Ask => self.ask()
+                .take_while(|(&price, _)| price - best <= ticks)
+                .map(|(_, &size)| size)
+                .sum(),
+        }
+    }
+
+    /// Return the volume-weighted average price, in ticks, at which a market order of the
+    /// given side and size would be filled, or `None` if there is not enough liquidity.
+    ///
+    /// # Complexity
+    /// `O(k)` where `k` is the number of limits consumed by the order.
+    pub fn vwap(&self, side: Side, size: TickUnit) -> Option<f64> {
+        if size == 0 {
+            return None;
+        }
+
+        let mut remaining = size;
+        let mut notional = 0.;
+        for (price, limit_size) in self.consumed_limits(side) {
+            let consumed = std::cmp::min(remaining, limit_size);
+            notional += price as f64 * consumed as f64;
+            remaining -= consumed;
+            if remaining == 0 {
+                return Some(notional / size as f64);
+            }
+        }
+        None
+    }
+
+    /// Return the price impact, in ticks, of a market order of the given side and size,
+    /// i.e. the distance between the best price and the worst price reached by the order,
+    /// or `None` if there is not enough liquidity.
+    ///
+    /// # Complexity
+    /// `O(k)` where `k` is the number of limits consumed by the order.
+    pub fn price_impact(&self, side: Side, size: TickUnit) -> Option<TickUnit> {
+        let mut limits = self.consumed_limits(side);
+        let (best, best_size) = limits.next()?;
+
+        let mut remaining = size.saturating_sub(best_size);
+        let mut worst = best;
+        while remaining > 0 {
+            let (price, limit_size) = limits.next()?;
+            remaining = remaining.saturating_sub(limit_size);
+            worst = price;
+        }
+
+        Some(match side {
+            Side::Bid => worst - best,
+            Side::Ask => best - worst,
+        })
+    }
+
+    /// Return the size imbalance between the bid and the ask sides over the top `levels`
+    /// limits of each side, i.e. `(bid size - ask size) / (bid size + ask size)`, which
+    /// lies in `[-1, 1]`. Return `None` if both sides are empty.
+    ///
+    /// # Complexity
+    /// `O(levels)`.
+    pub fn imbalance(&self, levels: usize) -> Option<f64> {
+        let bid: TickUnit = self.bid().take(levels).map(|(_, &size)| size).sum();
+        let ask: TickUnit = self.ask().take(levels).map(|(_, &size)| size).sum();
+        if bid == 0 && ask == 0 {
+            return None;
+        }
+
+        let (bid, ask) = (bid as f64, ask as f64);
+        Some((bid - ask) / (bid + ask))
+    }
+}
@@ -0,0 +1,343 @@
+//! A module defining an order book backed by tick-indexed arrays.
+
+use std::collections::btree_map::BTreeMap;
+use crate::Side;
+use crate::tick::TickUnit;
+use crate::order_book::{Book, OrderBook, LimitUpdate};
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+/// An order book storing the limits within a window of prices in two arrays indexed by
+/// price (one per side), and the limits outside the window in two `BTreeMap`. The window
+/// is recentered around the best prices whenever one of them leaves it.
+///
+/// Compared to `OrderBook`, updates within the window and best price lookups do not
+/// allocate and are `O(1)`. When the size of a best limit drops to zero, the next best
+/// limit is found through bitmaps of the non-empty slots of the window, in
+/// `O(1 + c / 4096)` where `c` is the capacity.
+pub struct ArrayOrderBook {
+    /// Price of the first slot of the window.
+    base: TickUnit,
+
+    bid: Vec<TickUnit>,
+    ask: Vec<TickUnit>,
+
+    /// Non-empty slots of `bid` and `ask`.
+    bid_slots: Slots,
+    ask_slots: Slots,
+
+    /// Limits outside of the window.
+    overflow_bid: BTreeMap<TickUnit, TickUnit>,
+    overflow_ask: BTreeMap<TickUnit, TickUnit>,
+
+    best_bid: TickUnit,
+    best_ask: TickUnit,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+/// A bitmap of the non-empty slots of one side of the window, along with a summary bitmap
+/// of the non-zero words, so that searching the next non-empty slot scans one bit per 4096
+/// slots at worst.
+struct Slots {
+    words: Vec<u64>,
+    summary: Vec<u64>,
+}
+
+/// Mask of the bits at or below `bit`.
+fn at_or_below(bit: usize) -> u64 {
+    u64::max_value() >> (63 - bit)
+}
+
+/// Mask of the bits at or above `bit`.
+fn at_or_above(bit: usize) -> u64 {
+    u64::max_value() << bit
+}
+
+fn highest(word: u64) -> usize {
+    63 - word.leading_zeros() as usize
+}
+
+fn lowest(word: u64) -> usize {
+    word.trailing_zeros() as usize
+}
+
+impl Slots {
+    fn new(capacity: usize) -> Self {
+        let words = (capacity + 63) / 64;
+        Slots {
+            words: vec![0; words],
+            summary: vec![0; (words + 63) / 64],
+        }
+    }
+
+    fn set(&mut self, index: usize, occupied: bool) {
+        let (word, bit) = (index / 64, index % 64);
+        if occupied {
+            self.words[word] |= 1 << bit;
+        } else {
+            self.words[word] &= !(1 << bit);
+        }
+
+        let (summary, bit) = (word / 64, word % 64);
+        if self.words[word] != 0 {
+            self.summary[summary] |= 1 << bit;
+        } else {
+            self.summary[summary] &= !(1 << bit);
+        }
+    }
+
+    /// Last non-empty slot at or below `index`.
+    fn last(&self, index: usize) -> Option<usize> {
+        let (word, bit) = (index / 64, index % 64);
+        let masked = self.words[word] & at_or_below(bit);
+        if masked != 0 {
+            return Some(word * 64 + highest(masked));
+        }
+        if word == 0 {
+            return None;
+        }
+
+        let (summary, bit) = ((word - 1) / 64, (word - 1) % 64);
+        let masked = self.summary[summary] & at_or_below(bit);
+        let word = if masked != 0 {
+            summary * 64 + highest(masked)
+        } else {
+            let summary = self.summary[..summary].iter().rposition(|&bits| bits != 0)?;
+            summary * 64 + highest(self.summary[summary])
+        };
+        Some(word * 64 + highest(self.words[word]))
+    }
+
+    /// First non-empty slot at or above `index`.
+    fn first(&self, index: usize) -> Option<usize> {
+        let (word, bit) = (index / 64, index % 64);
+        let masked = self.words[word] & at_or_above(bit);
+        if masked != 0 {
+            return Some(word * 64 + lowest(masked));
+        }
+        if word + 1 == self.words.len() {
+            return None;
+        }
+
+        let (summary, bit) = ((word + 1) / 64, (word + 1) % 64);
+        let masked = self.summary[summary] & at_or_above(bit);
+        let word = if masked != 0 {
+            summary * 64 + lowest(masked)
+        } else {
+            let next = summary + 1;
+            let summary = next + self.summary[next..].iter().position(|&bits| bits != 0)?;
+            summary * 64 + lowest(self.summary[summary])
+        };
+        Some(word * 64 + lowest(self.words[word]))
+    }
+}
+
+impl ArrayOrderBook {
+    /// Return an empty `ArrayOrderBook` with a window of `capacity` ticks.
+    ///
+    /// # Panics
+    /// Panic if `capacity` is zero.
+    pub fn new(capacity: usize) -> Self {
+        assert!(capacity > 0, "zero capacity");
+
+        ArrayOrderBook {
+            base: 0,
+            bid: vec![0; capacity],
+            ask: vec![0; capacity],
+            bid_slots: Slots::new(capacity),
+            ask_slots: Slots::new(capacity),
+            overflow_bid: BTreeMap::new(),
+            overflow_ask: BTreeMap::new(),
+            best_bid: 0,
+            best_ask: TickUnit::max_value(),
+        }
+    }
+
+    /// Number of ticks covered by the window.
+    pub fn capacity(&self) -> usize {
+        self.bid.len()
+    }
+
+    fn index(&self, price: TickUnit) -> Option<usize> {
+        if price < self.base {
+            return None;
+        }
+
+        let index = price - self.base;
+        if index < self.capacity() as TickUnit {
+            Some(index as usize)
+        } else {
+            None
+        }
+    }
+
+    fn set(&mut self, side: Side, price: TickUnit, size: TickUnit) {
+        let index = self.index(price);
+        let (window, slots, overflow) = match side {
+            Side::Bid => (&mut self.bid, &mut self.bid_slots, &mut self.overflow_bid),
+            Side::Ask => (&mut self.ask, &mut self.ask_slots, &mut self.overflow_ask),
+        };
+
+        match index {
+            Some(index) => {
+                window[index] = size;
+                slots.set(index, size > 0);
+            }
+            None if size == 0 => { overflow.remove(&price); },
+            None => { overflow.insert(price, size); },
+        }
+    }
+
+    /// Search the best bid at or below `from`.
+    fn search_best_bid(&self, from: TickUnit) -> TickUnit {
+        let overflow = self.overflow_bid.range(..=from).next_back().map(|(&price, _)| price);
+
+        let window = match self.index(from) {
+            Some(index) => Some(index),
+            None if from >= self.base => Some(self.capacity() - 1),
+            None => None,
+        }.and_then(|index| self.bid_slots.last(index)).map(|index| self.base + index as TickUnit);
+
+        std::cmp::max(window, overflow).unwrap_or(0)
+    }
+
+    /// Search the best ask at or above `from`.
+    fn search_best_ask(&self, from: TickUnit) -> TickUnit {
+        let overflow = self.overflow_ask.range(from..).next().map(|(&price, _)| price);
+
+        let window = match self.index(from) {
+            Some(index) => Some(index),
+            None if from < self.base => Some(0),
+            None => None,
+        }.and_then(|start| self.ask_slots.first(start)).map(|index| self.base + index as TickUnit);
+
+        match (window, overflow) {
+            (Some(window), Some(overflow)) => std::cmp::min(window, overflow),
+            (window, overflow) => window.or(overflow).unwrap_or(TickUnit::max_value()),
+        }
+    }
+
+    /// Move the window so that it is centered around the best prices.
+    ///
+    /// # Complexity
+    /// `O(c + n * log(n))` where `c` is the capacity and `n` the number of limits.
+    fn recenter(&mut self) {
+        let center = match (self.best_bid, self.best_ask) {
+            (0, ask) if ask == TickUnit::max_value() => return,
+            (0, ask) => ask,
+            (bid, ask) if ask == TickUnit::max_value() => bid,
+            // The book may be transiently crossed.
+            (bid, ask) if bid > ask => ask + (bid - ask) / 2,
+            (bid, ask) => bid + (ask - bid) / 2,
+        };
+
+        let limits: Vec<_> = self.limits(Side::Bid)
+            .map(|(price, size)| (Side::Bid, price, size))
+            .chain(self.limits(Side::Ask).map(|(price, size)| (Side::Ask, price, size)))
+            .collect();
+
+        let capacity = self.capacity();
+        self.base = center.saturating_sub(capacity as TickUnit / 2);
+        self.bid = vec![0; capacity];
+        self.ask = vec![0; capacity];
+        self.bid_slots = Slots::new(capacity);
+        self.ask_slots = Slots::new(capacity);
+        self.overflow_bid.clear();
+        self.overflow_ask.clear();
+
+        for (side, price, size) in limits {
+            self.set(side, price, size);
+        }
+    }
+
+    /// Collapse `self` into an `OrderBook`.
+    pub fn to_order_book(&self) -> OrderBook {
+        let mut order_book = OrderBook::new();
+        for side in &[Side::Bid, Side::Ask] {
+            for (price, size) in self.limits(*side) {
+                order_book.update(LimitUpdate::new(price, size, *side));
+            }
+        }
+        order_book
+    }
+}
+
+impl Book for ArrayOrderBook {
+    /// # Complexity
+    /// `O(1)` within the window, see type documentation for details.
+    fn update(&mut self, update: LimitUpdate) {
+        let LimitUpdate { price, size, side } = update;
+        self.set(side, price, size);
+
+        match side {
+            Side::Bid if size > 0 && price > self.best_bid => self.best_bid = price,
+            Side::Ask if size > 0 && price < self.best_ask => self.best_ask = price,
+            Side::Bid if size == 0 && price == self.best_bid => {
+                self.best_bid = self.search_best_bid(price);
+            }
+            Side::Ask if size == 0 && price == self.best_ask => {
+                self.best_ask = self.search_best_ask(price);
+            }
+            _ => return,
+        }
+
+        let bid_outside = self.best_bid != 0 && self.index(self.best_bid).is_none();
+        let ask_outside = self.best_ask != TickUnit::max_value() &&
+            self.index(self.best_ask).is_none();
+        if bid_outside || ask_outside {
+            self.recenter();
+        }
+    }
+
+    fn best_bid(&self) -> TickUnit {
+        self.best_bid
+    }
+
+    fn best_ask(&self) -> TickUnit {
+        self.best_ask
+    }
+
+    fn size_at_limit(&self, side: Side, price: TickUnit) -> TickUnit {
+        let (window, overflow) = match side {
+            Side::Bid => (&self.bid, &self.overflow_bid),
+            Side::Ask => (&self.ask, &self.overflow_ask),
+        };
+
+        match self.index(price) {
+            Some(index) => window[index],
+            None => overflow.get(&price).cloned().unwrap_or(0),
+        }
+    }
+
+    fn limits<'a>(&'a self, side: Side) -> Box<dyn Iterator<Item = (TickUnit, TickUnit)> + 'a> {
+        let base = self.base;
+        let capacity = self.capacity() as TickUnit;
+        let non_empty = move |(index, &size): (usize, &TickUnit)| {
+            if size > 0 {
+                Some((base + index as TickUnit, size))
+            } else {
+                None
+            }
+        };
+
+        match side {
+            Side::Bid => {
+                let above = self.overflow_bid.range(base + capacity..).rev();
+                let below = self.overflow_bid.range(..base).rev();
+                Box::new(
+                    above.map(|(&price, &size)| (price, size))
+                        .chain(self.bid.iter().enumerate().rev().filter_map(non_empty))
+                        .chain(below.map(|(&price, &size)| (price, size)))
+                )
+            }
+            Side::Ask => {
+                let below = self.overflow_ask.range(..base);
+                let above = self.overflow_ask.range(base + capacity..);
+                Box::new(
+                    below.map(|(&price, &size)| (price, size))
+                        .chain(self.ask.iter().enumerate().filter_map(non_empty))
+                        .chain(above.map(|(&price, &size)| (price, size)))
+                )
+            }
+        }
+    }
+}
@@ -0,0 +1,233 @@
+//! A module defining an order book tracking individual orders (also known as L3).
+
+use std::collections::{HashMap, VecDeque};
+use std::collections::btree_map::BTreeMap;
+use serde_derive::{Serialize, Deserialize};
+use crate::Side;
+use crate::tick::TickUnit;
+use crate::order_book::{OrderBook, LimitUpdate};
+
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+/// Represent an update of an individual order of the order book.
+pub enum L3Update {
+    /// A new order was inserted at the back of the queue of its limit.
+    Insert {
+        /// Unique order id.
+        order_id: String,
+
+        /// Price of the order.
+        price: TickUnit,
+
+        /// Size of the order.
+        size: TickUnit,
+
+        /// Side of the order.
+        side: Side,
+    },
+
+    /// Some size of an order was consumed by a trade.
+    Reduce {
+        /// Order being consumed.
+        order_id: String,
+
+        /// Consumed size.
+        size: TickUnit,
+    },
+
+    /// The size of an order was changed, without losing its queue position.
+    Resize {
+        /// Order being resized.
+        order_id: String,
+
+        /// New size of the order.
+        size: TickUnit,
+    },
+
+    /// An order was removed from the book, either because it was filled or canceled.
+    Remove {
+        /// Removed order.
+        order_id: String,
+    },
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+struct Limit {
+    /// (order id, size), in time priority
+    orders: VecDeque<(String, TickUnit)>,
+    size: TickUnit,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+/// An order book tracking individual orders along with their time priority. Can be
+/// collapsed into an aggregated `OrderBook`.
+pub struct L3OrderBook {
+    ask: BTreeMap<TickUnit, Limit>,
+    bid: BTreeMap<TickUnit, Limit>,
+
+    /// order id => (side, price)
+    orders: HashMap<String, (Side, TickUnit)>,
+}
+
+impl L3OrderBook {
+    /// Return an empty `L3OrderBook`.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    fn side(&self, side: Side) -> &BTreeMap<TickUnit, Limit> {
+        match side {
+            Side::Bid => &self.bid,
+            Side::Ask => &self.ask,
+        }
+    }
+
+    fn side_mut(&mut self, side: Side) -> &mut BTreeMap<TickUnit, Limit> {
+        match side {
+            Side::Bid => &mut self.bid,
+            Side::Ask => &mut self.ask,
+        }
+    }
+
+    /// Apply an update, and return the corresponding update of the aggregated order book,
+    /// if any. Updates referring to unknown orders are ignored, as well as insertions of
+    /// already known orders.
+    ///
+    /// # Complexity
+    /// `O(log(n))` for insertions, `O(log(n) + k)` otherwise, where `n` is the number of
+    /// limits at the given side and `k` the number of orders at the given limit.
+    pub fn update(&mut self, update: &L3Update) -> Option<LimitUpdate> {
+        let (order_id, side, price) = match update {
+            L3Update::Insert { order_id, price, size, side } => {
+                if self.orders.contains_key(order_id) {
+                    return None;
+                }
+
+                self.orders.insert(order_id.clone(), (*side, *price));
+                let limit = self.side_mut(*side).entry(*price).or_insert_with(Default::default);
+                limit.orders.push_back((order_id.clone(), *size));
+                limit.size += size;
+                return Some(LimitUpdate::new(*price, limit.size, *side));
+            }
+
+            L3Update::Reduce { order_id, .. } |
+            L3Update::Resize { order_id, .. } |
+            L3Update::Remove { order_id } => {
+                let (side, price) = *self.orders.get(order_id)?;
+                (order_id, side, price)
+            }
+        };
+
+        let (size, remove) = {
+            let limit = self.side_mut(side).get_mut(&price).expect("empty limit");
+            let index = limit.orders.iter()
+                .position(|(id, _)| id == order_id)
+                .expect("order not found at its limit");
+
+            let new_size = match update {
+                L3Update::Reduce { size, .. } => limit.orders[index].1.saturating_sub(*size),
+                L3Update::Resize { size, .. } => *size,
+                _ => 0,
+            };
+
+            limit.size = limit.size - limit.orders[index].1 + new_size;
+            if new_size == 0 {
+                limit.orders.remove(index);
+            } else {
+                limit.orders[index].1 = new_size;
+            }
+            (limit.size, new_size == 0)
+        };
+
+        if remove {
+            self.orders.remove(order_id);
+        }
+        if size == 0 {
+            self.side_mut(side).remove(&price);
+        }
+        Some(LimitUpdate::new(price, size, side))
+    }
+
+    /// Return best bid price. If the bid side is empty, return `0`.
+    pub fn best_bid(&self) -> TickUnit {
+        self.bid.keys().next_back().cloned().unwrap_or(0)
+    }
+
+    /// Return best ask price. If the ask side is empty, return `TickUnit::max_value()`.
+    pub fn best_ask(&self) -> TickUnit {
+        self.ask.keys().next().cloned().unwrap_or(TickUnit::max_value())
+    }
+
+    /// Retrieve the aggregated size at the given limit.
+    ///
+    /// # Complexity
+    /// `O(log(n))` where `n` is the number of limits at the given side.
+    pub fn size_at_limit(&self, side: Side, price: TickUnit) -> TickUnit {
+        self.side(side).get(&price).map(|limit| limit.size).unwrap_or(0)
+    }
+
+    /// Iterator over the orders (order id, size) at the given limit, in time priority.
+    pub fn orders_at_limit(&self, side: Side, price: TickUnit)
+        -> impl Iterator<Item = (&str, TickUnit)>
+    {
+        self.side(side)
+            .get(&price)
+            .into_iter()
+            .flat_map(|limit| limit.orders.iter().map(|(id, size)| (id.as_str(), *size)))
+    }
+
+    /// Return the side, price and size of the given order, if any.
+    ///
+    /// # Complexity
+    /// `O(log(n) + k)` where `n` is the number of limits at the order side and `k`
+    /// the number of orders at the order limit.
+    pub fn order(&self, order_id: &str) -> Option<(Side, TickUnit, TickUnit)> {
+        let &(side, price) = self.orders.get(order_id)?;
+        let size = self.orders_at_limit(side, price).find(|(id, _)| *id == order_id)?.1;
+        Some((side, price, size))
+    }
+
+    /// Return the total size of the orders ahead of the given order at its limit.
+    ///
+    /// # Complexity
+    /// `O(log(n) + k)` where `n` is the number of limits at the order side and `k`
+    /// the number of orders at the order limit.
+    pub fn queue_position(&self, order_id: &str) -> Option<TickUnit> {
+        let &(side, price) = self.orders.get(order_id)?;
+        Some(
+            self.orders_at_limit(side, price)
+                .take_while(|(id, _)| *id != order_id)
+                .map(|(_, size)| size)
+                .sum()
+        )
+    }
+
+    /// Number of orders in the book.
+    pub fn len(&self) -> usize {
+        self.orders.len()
+    }
+
+    /// Return `true` if there is no order in the book.
+    pub fn is_empty(&self) -> bool {
+        self.orders.is_empty()
+    }
+
+    /// Collapse `self` into an aggregated `OrderBook`.
+    ///
+    /// # Complexity
+    /// `O(n * log(n))` where `n` is the number of limits.
+    pub fn to_order_book(&self) -> OrderBook {
+        let mut order_book = OrderBook::new();
+        for (side, limits) in &[(Side::Bid, &self.bid), (Side::Ask, &self.ask)] {
+            for (price, limit) in limits.iter() {
+                order_book.update(LimitUpdate::new(*price, limit.size, *side));
+            }
+        }
+        order_book
+    }
+}
+
+impl<'a> From<&'a L3OrderBook> for OrderBook {
+    fn from(book: &'a L3OrderBook) -> OrderBook {
+        book.to_order_book()
+    }
+}
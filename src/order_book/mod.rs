@@ -1,6 +1,10 @@
 //! A module defining a simple data structure representing an order book.
 
 pub mod display;
+pub mod l3;
+pub mod array;
+pub mod snapshot;
+mod analytics;
 mod test;
 
 use std::collections::btree_map::BTreeMap;
@@ -8,12 +12,38 @@ use serde_derive::{Serialize, Deserialize};
 use crate::Side;
 use crate::tick::TickUnit;
 
-#[derive(Clone, PartialEq, Eq, Debug, Default)]
+#[derive(Clone, Eq, Debug, Default, Serialize, Deserialize)]
 /// An order book. Internally uses two `BTreeMap`, one
 /// for the bid side and another one for the ask side.
+///
+/// Equality only compares the limits of the books, not their windows.
 pub struct OrderBook {
     ask: BTreeMap<TickUnit, TickUnit>,
     bid: BTreeMap<TickUnit, TickUnit>,
+    #[serde(default)]
+    window: Option<Window>,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+/// Bound on the limits kept by an `OrderBook`, far limits being evicted.
+///
+/// # Note
+/// An evicted limit is forgotten: if the best price later moves towards it, the book
+/// will miss it until the exchange sends an update for it. Use a window larger than
+/// the part of the book which is actually needed.
+pub enum Window {
+    /// Only keep the given number of best limits on each side.
+    Depth(usize),
+
+    /// Only keep the limits within the given number of ticks of the best price of
+    /// their side.
+    PriceBand(TickUnit),
+}
+
+impl PartialEq for OrderBook {
+    fn eq(&self, other: &OrderBook) -> bool {
+        self.ask == other.ask && self.bid == other.bid
+    }
 }
 
 #[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
@@ -40,15 +70,48 @@ impl LimitUpdate {
     }
 }
 
+/// Operations shared by the order book implementations of this crate.
+pub trait Book {
+    /// Update the given limit with the given updated size.
+    fn update(&mut self, update: LimitUpdate);
+
+    /// Return best bid price. If the bid side is empty, return `0`.
+    fn best_bid(&self) -> TickUnit;
+
+    /// Return best ask price. If the ask side is empty, return `TickUnit::max_value()`.
+    fn best_ask(&self) -> TickUnit;
+
+    /// Retrieve the size at the given limit.
+    fn size_at_limit(&self, side: Side, price: TickUnit) -> TickUnit;
+
+    /// Iterator over the `(price, size)` limits at the given side, sorted from the best
+    /// price to the worst one.
+    fn limits<'a>(&'a self, side: Side) -> Box<dyn Iterator<Item = (TickUnit, TickUnit)> + 'a>;
+}
+
 impl OrderBook {
     /// Return an empty `OrderBook`.
     pub fn new() -> Self {
         OrderBook {
             ask: BTreeMap::new(),
             bid: BTreeMap::new(),
+            window: None,
+        }
+    }
+
+    /// Return an empty `OrderBook` only keeping the limits inside `window`.
+    pub fn with_window(window: Window) -> Self {
+        OrderBook {
+            window: Some(window),
+            ..OrderBook::new()
         }
     }
 
+    /// Return the window of `self`, if any.
+    pub fn window(&self) -> Option<Window> {
+        self.window
+    }
+
     /// Return best bid price. If the bid side is empty, return `0`.
     /// 
     /// # Complexity
@@ -65,10 +128,12 @@ impl OrderBook {
         self.ask().next().map(|(price, _)| *price).unwrap_or(TickUnit::max_value())
     }
 
-    /// Update the given limit with the given updated size.
+    /// Update the given limit with the given updated size, then evict the limits which
+    /// fell outside of the window, if any.
     /// 
     /// # Complexity
-    /// `O(log(n))` where `n` is the number of limits at the given side.
+    /// `O(log(n))` where `n` is the number of limits at the given side, amortized over
+    /// the evicted limits.
     pub fn update(&mut self, update: LimitUpdate) {
         use std::collections::btree_map::Entry;
 
@@ -89,6 +154,40 @@ impl OrderBook {
             Entry::Occupied(mut entry) => *entry.get_mut() = update.size,
             Entry::Vacant(entry) => { entry.insert(update.size); },
         };
+
+        // Removing a limit can never bring another limit outside of the window.
+        self.evict();
+    }
+
+    fn evict(&mut self) {
+        match self.window {
+            None => (),
+
+            Some(Window::Depth(depth)) => {
+                while self.bid.len() > depth {
+                    let worst = *self.bid.keys().next().unwrap();
+                    self.bid.remove(&worst);
+                }
+                while self.ask.len() > depth {
+                    let worst = *self.ask.keys().next_back().unwrap();
+                    self.ask.remove(&worst);
+                }
+            }
+
+            Some(Window::PriceBand(band)) => {
+                let best_bid = self.best_bid();
+                let worst_bid = self.bid.keys().next().cloned();
+                if worst_bid.map(|worst| best_bid - worst > band).unwrap_or(false) {
+                    self.bid = self.bid.split_off(&(best_bid - band));
+                }
+
+                let best_ask = self.best_ask();
+                let worst_ask = self.ask.keys().next_back().cloned();
+                if worst_ask.map(|worst| worst - best_ask > band).unwrap_or(false) {
+                    self.ask.split_off(&best_ask.saturating_add(band).saturating_add(1));
+                }
+            }
+        }
     }
 
     /// Retrieve the size at the given limit.
@@ -116,7 +215,8 @@ impl OrderBook {
     }
 
     /// Return an iterator over the set of limit updates to apply to `self` in
-    /// order to be equal to `other`.
+    /// order to be equal to `other`. Updates of the bid side come first, and the
+    /// updates of each side are sorted by ascending price.
     /// 
     /// # Complexity
     /// `O(n + m)` where `n` is `self`'s length and `m` is `other`'s length.
@@ -135,25 +235,41 @@ impl OrderBook {
     /// # }
     /// ```
     pub fn diff(&self, other: &OrderBook) -> impl Iterator<Item = LimitUpdate> {
-        use std::collections::HashMap;
+        use std::cmp::Ordering;
 
         let mut updates = Vec::new();
 
-        let mut compute_diff = |entries: &BTreeMap<_, _>, other_entries, side| {
-            let mut entries: HashMap<_, _> = entries.iter().map(|(x, y)| (*x, *y)).collect();
+        type Limits = BTreeMap<TickUnit, TickUnit>;
 
-            for (&price, &other_size) in other_entries {
-                let need_update = entries.remove(&price)
-                    .map(|size| size != other_size)
-                    .unwrap_or(true);
+        let mut compute_diff = |entries: &Limits, other_entries: &Limits, side| {
+            let mut entries = entries.iter().peekable();
+            let mut other_entries = other_entries.iter().peekable();
 
-                if need_update {
-                    updates.push(LimitUpdate::new(price, other_size, side));
-                }
-            }
+            loop {
+                let ordering = match (entries.peek(), other_entries.peek()) {
+                    (Some((price, _)), Some((other_price, _))) => price.cmp(other_price),
+                    (Some(_), None) => Ordering::Less,
+                    (None, Some(_)) => Ordering::Greater,
+                    (None, None) => break,
+                };
 
-            for (price, _) in entries {
-                updates.push(LimitUpdate::new(price, 0, side));
+                match ordering {
+                    Ordering::Less => {
+                        let (&price, _) = entries.next().unwrap();
+                        updates.push(LimitUpdate::new(price, 0, side));
+                    }
+                    Ordering::Greater => {
+                        let (&price, &other_size) = other_entries.next().unwrap();
+                        updates.push(LimitUpdate::new(price, other_size, side));
+                    }
+                    Ordering::Equal => {
+                        let (_, &size) = entries.next().unwrap();
+                        let (&price, &other_size) = other_entries.next().unwrap();
+                        if size != other_size {
+                            updates.push(LimitUpdate::new(price, other_size, side));
+                        }
+                    }
+                }
             }
         };
 
@@ -163,3 +279,29 @@ impl OrderBook {
         updates.into_iter()
     }
 }
+
+impl Book for OrderBook {
+    fn update(&mut self, update: LimitUpdate) {
+        OrderBook::update(self, update)
+    }
+
+    fn best_bid(&self) -> TickUnit {
+        OrderBook::best_bid(self)
+    }
+
+    fn best_ask(&self) -> TickUnit {
+        OrderBook::best_ask(self)
+    }
+
+    fn size_at_limit(&self, side: Side, price: TickUnit) -> TickUnit {
+        OrderBook::size_at_limit(self, side, price)
+    }
+
+    fn limits<'a>(&'a self, side: Side) -> Box<dyn Iterator<Item = (TickUnit, TickUnit)> + 'a> {
+        let limits = |(price, size): (&TickUnit, &TickUnit)| (*price, *size);
+        match side {
+            Side::Bid => Box::new(self.bid().map(limits)),
+            Side::Ask => Box::new(self.ask().map(limits)),
+        }
+    }
+}
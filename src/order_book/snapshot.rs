@@ -0,0 +1,239 @@
+//! A module defining sequenced snapshots of an order book along with incremental deltas,
+//! so that a downstream process can late-join a book: it first loads a `Snapshot`, then
+//! applies the `Delta`s whose sequence numbers follow the one of the snapshot.
+//!
+//! Snapshots have a compact binary encoding: all integers are LEB128 varints, and the
+//! prices of each side are encoded as the distance to the previous limit of the side.
+//! A snapshot is laid out as `sequence, window, bid, ask` where `window` is a tag (`0` for
+//! none, `1` for `Depth`, `2` for `PriceBand`) followed by the window parameter if any,
+//! and each side is `count` followed by `count` times `price, size`, from the best limit
+//! to the worst one.
+
+use std::collections::VecDeque;
+use std::io::{self, Read, Write};
+use failure::format_err;
+use failure_derive::Fail;
+use serde_derive::{Serialize, Deserialize};
+use crate::Side;
+use crate::tick::TickUnit;
+use crate::order_book::{OrderBook, LimitUpdate, Window};
+
+const NO_WINDOW: u64 = 0;
+const DEPTH: u64 = 1;
+const PRICE_BAND: u64 = 2;
+
+fn write_varint<W: Write>(writer: &mut W, mut value: u64) -> io::Result<()> {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            return writer.write_all(&[byte]);
+        }
+        writer.write_all(&[byte | 0x80])?;
+    }
+}
+
+fn read_varint<R: Read>(reader: &mut R) -> Result<u64, failure::Error> {
+    let mut value = 0;
+    for shift in (0..64).step_by(7) {
+        let mut buf = [0; 1];
+        reader.read_exact(&mut buf)?;
+        value |= u64::from(buf[0] & 0x7f) << shift;
+        if buf[0] & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(format_err!("varint overflow"))
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+/// A snapshot of an order book, including all the deltas up to `sequence` (included).
+pub struct Snapshot {
+    /// Sequence number of the last delta included in the snapshot.
+    pub sequence: u64,
+
+    /// State of the order book.
+    pub order_book: OrderBook,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+/// A batch of limit updates, along with its sequence number.
+pub struct Delta {
+    /// Sequence number of this delta, each delta increments it by one.
+    pub sequence: u64,
+
+    /// Limit updates of this delta.
+    pub updates: Vec<LimitUpdate>,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Fail)]
+#[fail(display = "expected delta {} but received delta {}", expected, received)]
+/// An error indicating that a delta was missed: the snapshot is out of sync and a new
+/// one must be requested.
+pub struct SequenceGap {
+    /// Sequence number of the next delta to apply.
+    pub expected: u64,
+
+    /// Sequence number of the received delta.
+    pub received: u64,
+}
+
+impl Snapshot {
+    /// Apply `delta` to the snapshot. Deltas already included in the snapshot are ignored.
+    pub fn apply(&mut self, delta: &Delta) -> Result<(), SequenceGap> {
+        if delta.sequence <= self.sequence {
+            return Ok(());
+        }
+
+        if delta.sequence != self.sequence + 1 {
+            return Err(SequenceGap {
+                expected: self.sequence + 1,
+                received: delta.sequence,
+            });
+        }
+
+        for update in &delta.updates {
+            self.order_book.update(*update);
+        }
+        self.sequence = delta.sequence;
+        Ok(())
+    }
+
+    /// Write the compact binary encoding of `self` to `writer`.
+    pub fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        write_varint(writer, self.sequence)?;
+
+        match self.order_book.window {
+            None => write_varint(writer, NO_WINDOW)?,
+            Some(Window::Depth(depth)) => {
+                write_varint(writer, DEPTH)?;
+                write_varint(writer, depth as u64)?;
+            }
+            Some(Window::PriceBand(band)) => {
+                write_varint(writer, PRICE_BAND)?;
+                write_varint(writer, band)?;
+            }
+        }
+
+        write_varint(writer, self.order_book.bid.len() as u64)?;
+        let mut previous = None;
+        for (&price, &size) in self.order_book.bid() {
+            write_varint(writer, previous.map(|previous| previous - price).unwrap_or(price))?;
+            write_varint(writer, size)?;
+            previous = Some(price);
+        }
+
+        write_varint(writer, self.order_book.ask.len() as u64)?;
+        let mut previous = None;
+        for (&price, &size) in self.order_book.ask() {
+            write_varint(writer, previous.map(|previous| price - previous).unwrap_or(price))?;
+            write_varint(writer, size)?;
+            previous = Some(price);
+        }
+
+        Ok(())
+    }
+
+    /// Read a snapshot written by `encode` from `reader`.
+    pub fn decode<R: Read>(reader: &mut R) -> Result<Snapshot, failure::Error> {
+        let sequence = read_varint(reader)?;
+
+        let mut order_book = match read_varint(reader)? {
+            NO_WINDOW => OrderBook::new(),
+            DEPTH => OrderBook::with_window(Window::Depth(read_varint(reader)? as usize)),
+            PRICE_BAND => OrderBook::with_window(Window::PriceBand(read_varint(reader)?)),
+            other => return Err(format_err!("invalid window tag `{}`", other)),
+        };
+
+        for &side in &[Side::Bid, Side::Ask] {
+            let count = read_varint(reader)?;
+            let mut previous: Option<TickUnit> = None;
+            for _ in 0..count {
+                let delta = read_varint(reader)?;
+                let price = match (previous, side) {
+                    (None, _) => Some(delta),
+                    (Some(previous), Side::Bid) => previous.checked_sub(delta),
+                    (Some(previous), Side::Ask) => previous.checked_add(delta),
+                }.ok_or_else(|| format_err!("invalid price delta `{}`", delta))?;
+
+                order_book.update(LimitUpdate::new(price, read_varint(reader)?, side));
+                previous = Some(price);
+            }
+        }
+
+        Ok(Snapshot {
+            sequence,
+            order_book,
+        })
+    }
+}
+
+/// An order book maintained from batches of limit updates, keeping the most recent
+/// batches as sequenced `Delta`s for late joiners.
+pub struct Journal {
+    snapshot: Snapshot,
+    deltas: VecDeque<Delta>,
+    capacity: usize,
+}
+
+impl Journal {
+    /// Return a `Journal` starting from `order_book` and keeping the last `capacity`
+    /// deltas. The first delta has sequence number `1`.
+    ///
+    /// # Panics
+    /// Panic if `capacity` is zero.
+    pub fn new(order_book: OrderBook, capacity: usize) -> Self {
+        assert!(capacity > 0, "zero capacity");
+
+        Journal {
+            snapshot: Snapshot {
+                sequence: 0,
+                order_book,
+            },
+            deltas: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// Apply `updates` to the book as a new delta, and return that delta.
+    pub fn apply(&mut self, updates: Vec<LimitUpdate>) -> &Delta {
+        let delta = Delta {
+            sequence: self.snapshot.sequence + 1,
+            updates,
+        };
+        self.snapshot.apply(&delta).unwrap();
+
+        if self.deltas.len() == self.capacity {
+            self.deltas.pop_front();
+        }
+        self.deltas.push_back(delta);
+        self.deltas.back().unwrap()
+    }
+
+    /// Sequence number of the last delta.
+    pub fn sequence(&self) -> u64 {
+        self.snapshot.sequence
+    }
+
+    /// Current state of the book.
+    pub fn order_book(&self) -> &OrderBook {
+        &self.snapshot.order_book
+    }
+
+    /// Return a snapshot of the current state of the book.
+    pub fn snapshot(&self) -> Snapshot {
+        self.snapshot.clone()
+    }
+
+    /// Return the deltas which came after `sequence`, or `None` if some of them were
+    /// already dropped: a snapshot must then be requested.
+    pub fn deltas_since(&self, sequence: u64) -> Option<impl Iterator<Item = &Delta>> {
+        let first = self.deltas.front().map(|delta| delta.sequence)
+            .unwrap_or(self.snapshot.sequence + 1);
+
+        if sequence + 1 < first || sequence > self.snapshot.sequence {
+            return None;
+        }
+        Some(self.deltas.iter().skip((sequence + 1 - first) as usize))
+    }
+}
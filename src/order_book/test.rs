@@ -2,7 +2,9 @@
 
 use crate::Side;
 use crate::tick::TickUnit;
-use crate::order_book::{OrderBook, LimitUpdate};
+use crate::order_book::{Book, OrderBook, LimitUpdate, Window};
+use crate::order_book::array::ArrayOrderBook;
+use crate::order_book::snapshot::{Journal, Snapshot, SequenceGap};
 
 fn lu(price: TickUnit, size: TickUnit, side: Side) -> LimitUpdate {
     LimitUpdate::new(price, size, side)
@@ -49,3 +51,231 @@ fn test_diff() {
     }
     assert_eq!(odb1, odb2);
 }
+
+#[test]
+fn test_window() {
+    let mut depth = OrderBook::with_window(Window::Depth(2));
+    let mut band = OrderBook::with_window(Window::PriceBand(2));
+    for &(price, size, side) in &[
+        (100, 1, Side::Bid),
+        (99, 1, Side::Bid),
+        (97, 1, Side::Bid),
+        (101, 1, Side::Ask),
+        (103, 1, Side::Ask),
+        (104, 1, Side::Ask),
+    ] {
+        depth.update(lu(price, size, side));
+        band.update(lu(price, size, side));
+    }
+
+    let limits = |order_book: &OrderBook| -> (Vec<_>, Vec<_>) {
+        (order_book.bid().map(|(p, _)| *p).collect(), order_book.ask().map(|(p, _)| *p).collect())
+    };
+    assert_eq!(limits(&depth), (vec![100, 99], vec![101, 103]));
+    assert_eq!(limits(&band), (vec![100, 99], vec![101, 103]));
+
+    // Evicted limits are forgotten.
+    depth.update(lu(100, 0, Side::Bid));
+    band.update(lu(100, 0, Side::Bid));
+    assert_eq!(depth.best_bid(), 99);
+    assert_eq!(depth.size_at_limit(Side::Bid, 97), 0);
+
+    // A new best price moves the band.
+    band.update(lu(102, 1, Side::Bid));
+    assert_eq!(limits(&band), (vec![102], vec![101, 103]));
+
+    // A band covering all the prices keeps every limit.
+    let mut wide = OrderBook::with_window(Window::PriceBand(std::u64::MAX));
+    for &(price, side) in &[(1, Side::Bid), (100, Side::Bid), (101, Side::Ask), (1000, Side::Ask)] {
+        wide.update(lu(price, 1, side));
+    }
+    assert_eq!(limits(&wide), (vec![100, 1], vec![101, 1000]));
+
+    // Windows are not compared.
+    assert_eq!(OrderBook::with_window(Window::Depth(1)), OrderBook::new());
+}
+
+#[test]
+fn test_l3() {
+    use crate::order_book::l3::{L3OrderBook, L3Update};
+
+    fn insert(order_id: &str, price: TickUnit, size: TickUnit, side: Side) -> L3Update {
+        L3Update::Insert { order_id: order_id.to_owned(), price, size, side }
+    }
+
+    let mut book = L3OrderBook::new();
+    let mut odb = OrderBook::new();
+    let updates = vec![
+        insert("a", 100, 5, Side::Bid),
+        insert("b", 100, 3, Side::Bid),
+        insert("c", 100, 2, Side::Bid),
+        insert("d", 101, 4, Side::Ask),
+        L3Update::Reduce { order_id: "a".to_owned(), size: 1 },
+        L3Update::Resize { order_id: "b".to_owned(), size: 2 },
+        L3Update::Remove { order_id: "unknown".to_owned() },
+    ];
+    for update in &updates {
+        if let Some(limit_update) = book.update(update) {
+            odb.update(limit_update);
+        }
+    }
+
+    assert_eq!(book.queue_position("c"), Some(6));
+    assert_eq!(book.order("a"), Some((Side::Bid, 100, 4)));
+    assert_eq!(book.size_at_limit(Side::Bid, 100), 8);
+    assert_eq!(book.to_order_book(), odb);
+
+    assert_eq!(
+        book.update(&L3Update::Reduce { order_id: "a".to_owned(), size: 4 }),
+        Some(lu(100, 4, Side::Bid))
+    );
+    assert_eq!(book.queue_position("c"), Some(2));
+    assert_eq!(book.order("a"), None);
+
+    book.update(&L3Update::Remove { order_id: "d".to_owned() });
+    assert_eq!(book.best_ask(), TickUnit::max_value());
+    assert_eq!(book.len(), 2);
+}
+
+#[test]
+fn test_analytics() {
+    let mut odb = OrderBook::new();
+    assert_eq!(odb.mid(), None);
+    assert_eq!(odb.imbalance(5), None);
+
+    odb.update(lu(100, 3, Side::Bid));
+    odb.update(lu(98, 5, Side::Bid));
+    odb.update(lu(95, 10, Side::Bid));
+    odb.update(lu(102, 1, Side::Ask));
+    odb.update(lu(103, 4, Side::Ask));
+    odb.update(lu(110, 20, Side::Ask));
+
+    assert_eq!(odb.mid(), Some(101.));
+    assert_eq!(odb.microprice(), Some((100. * 1. + 102. * 3.) / 4.));
+    assert_eq!(odb.spread(), Some(2));
+
+    assert_eq!(odb.depth(Side::Bid, 0), 3);
+    assert_eq!(odb.depth(Side::Bid, 2), 8);
+    assert_eq!(odb.depth(Side::Ask, 8), 25);
+
+    assert_eq!(odb.vwap(Side::Bid, 1), Some(102.));
+    assert_eq!(odb.vwap(Side::Bid, 5), Some((102. + 4. * 103.) / 5.));
+    assert_eq!(odb.vwap(Side::Ask, 4), Some((3. * 100. + 98.) / 4.));
+    assert_eq!(odb.vwap(Side::Ask, 19), None);
+
+    assert_eq!(odb.price_impact(Side::Bid, 1), Some(0));
+    assert_eq!(odb.price_impact(Side::Bid, 6), Some(8));
+    assert_eq!(odb.price_impact(Side::Ask, 9), Some(5));
+    assert_eq!(odb.price_impact(Side::Ask, 19), None);
+
+    assert_eq!(odb.imbalance(1), Some((3. - 1.) / 4.));
+    assert_eq!(odb.imbalance(2), Some((8. - 5.) / 13.));
+}
+
+#[test]
+fn test_array() {
+    let mut order_book = OrderBook::new();
+    let mut array = ArrayOrderBook::new(16);
+
+    // Random walk of the mid, which forces the window to move around.
+    let mut state: u64 = 42;
+    let mut mid: TickUnit = 1000;
+    for _ in 0..10_000 {
+        state = state.wrapping_mul(6_364_136_223_846_793_005).wrapping_add(1);
+        let random = state >> 33;
+
+        if random % 8 == 0 {
+            mid = if random % 16 == 0 { mid + 3 } else { mid - 3 };
+        }
+
+        let side = if random % 2 == 0 { Side::Bid } else { Side::Ask };
+        let offset = (random >> 4) % 12;
+        let price = match side {
+            Side::Bid => mid - 1 - offset,
+            Side::Ask => mid + offset,
+        };
+        let size = if (random >> 8) % 3 == 0 { 0 } else { (random >> 10) % 100 };
+
+        // Keep the book uncrossed, as an exchange feed would.
+        let update = lu(price, size, side);
+        order_book.update(update);
+        array.update(update);
+        let best_bid = order_book.best_bid();
+        for (price, _) in order_book.ask().map(|(p, s)| (*p, *s)).collect::<Vec<_>>() {
+            if price <= best_bid {
+                order_book.update(lu(price, 0, Side::Ask));
+                array.update(lu(price, 0, Side::Ask));
+            }
+        }
+
+        assert_eq!(Book::best_bid(&array), order_book.best_bid());
+        assert_eq!(Book::best_ask(&array), order_book.best_ask());
+        assert_eq!(
+            Book::size_at_limit(&array, side, price),
+            order_book.size_at_limit(side, price)
+        );
+    }
+
+    assert_eq!(array.to_order_book(), order_book);
+    assert!(array.limits(Side::Bid).eq(Book::limits(&order_book, Side::Bid)));
+    assert!(array.limits(Side::Ask).eq(Book::limits(&order_book, Side::Ask)));
+}
+
+#[test]
+fn test_array_sparse() {
+    let mut order_book = OrderBook::new();
+    let mut array = ArrayOrderBook::new(10_000);
+
+    // Limits spread over many words of the bitmaps, and partly outside of the window.
+    for i in 0..100 {
+        for update in &[lu(1000 + 97 * i, 1, Side::Bid), lu(11_000 + 89 * i, 1, Side::Ask)] {
+            order_book.update(*update);
+            array.update(*update);
+        }
+    }
+
+    for _ in 0..100 {
+        for update in &[
+            lu(order_book.best_bid(), 0, Side::Bid),
+            lu(order_book.best_ask(), 0, Side::Ask),
+        ] {
+            order_book.update(*update);
+            array.update(*update);
+        }
+        assert_eq!(Book::best_bid(&array), order_book.best_bid());
+        assert_eq!(Book::best_ask(&array), order_book.best_ask());
+    }
+}
+
+#[test]
+fn test_snapshot() {
+    let mut journal = Journal::new(OrderBook::with_window(Window::Depth(10)), 2);
+    journal.apply(vec![lu(100, 5, Side::Bid), lu(98, 1, Side::Bid), lu(101, 3, Side::Ask)]);
+
+    let snapshot = journal.snapshot();
+    let mut encoded = Vec::new();
+    snapshot.encode(&mut encoded).unwrap();
+    let mut decoded = Snapshot::decode(&mut &encoded[..]).unwrap();
+    assert_eq!(decoded, snapshot);
+    assert_eq!(decoded.order_book.window(), Some(Window::Depth(10)));
+
+    let json = serde_json::to_string(&snapshot).unwrap();
+    assert_eq!(serde_json::from_str::<Snapshot>(&json).unwrap(), snapshot);
+
+    journal.apply(vec![lu(101, 0, Side::Ask), lu(102, 2, Side::Ask)]);
+    journal.apply(vec![lu(99, 4, Side::Bid)]);
+
+    // The late joiner catches up from its snapshot.
+    for delta in journal.deltas_since(decoded.sequence).unwrap() {
+        decoded.apply(delta).unwrap();
+    }
+    assert_eq!(decoded.order_book, *journal.order_book());
+    assert_eq!(decoded.sequence, 3);
+
+    // Deltas are only kept up to the capacity.
+    journal.apply(vec![lu(99, 0, Side::Bid)]);
+    assert!(journal.deltas_since(1).is_none());
+
+    let delta = journal.apply(vec![lu(103, 1, Side::Ask)]).clone();
+    assert_eq!(decoded.apply(&delta), Err(SequenceGap { expected: 4, received: 5 }));
+}